@@ -1,6 +1,11 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+
 use crate::token;
+use crate::token::Span;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Program {
     pub statements: Vec<Statement>,
 }
@@ -12,16 +17,7 @@ impl Program {
         }
     }
 
-    fn token_literal(&self) -> String {
-        if self.statements.len() > 0 {
-            self.statements[0].token_literal()
-        } else {
-            "".to_string()
-        }
-    }
-
     pub fn string(&self) -> String {
-        let mut out = "".to_string();
         let mut out = Vec::new();
         for statement in &self.statements {
             //out.push_str(&statement.string());
@@ -31,32 +27,76 @@ impl Program {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     LetStatement(LetStatement),
     ReturnStatement(ReturnStatement),
     ExpressionStatement(ExpressionStatement),
+    WhileStatement(WhileStatement),
+    BreakStatement(BreakStatement),
+    ContinueStatement(ContinueStatement),
+    ForStatement(ForStatement),
+    DestructureStatement(DestructureStatement),
+    ThrowStatement(ThrowStatement),
+    FunctionStatement(FunctionStatement),
 }
 
 impl Statement {
-    fn token_literal(&self) -> String {
-        "".to_string()
-    }
     fn string(&self) -> String {
-        let mut out = match self {
+        match self {
             Statement::LetStatement(let_statement) => let_statement.string(),
             Statement::ReturnStatement(return_statement) => return_statement.string(),
             Statement::ExpressionStatement(expression_statement) => expression_statement.string(),
-        };
+            Statement::WhileStatement(while_statement) => while_statement.string(),
+            Statement::BreakStatement(_) => "break;".to_string(),
+            Statement::ContinueStatement(_) => "continue;".to_string(),
+            Statement::ForStatement(for_statement) => for_statement.string(),
+            Statement::DestructureStatement(destructure) => destructure.string(),
+            Statement::ThrowStatement(throw_statement) => throw_statement.string(),
+            Statement::FunctionStatement(declaration) => declaration.string(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::LetStatement(let_statement) => let_statement.span,
+            Statement::ReturnStatement(return_statement) => return_statement.span,
+            Statement::ExpressionStatement(expression_statement) => expression_statement.span,
+            Statement::WhileStatement(while_statement) => while_statement.span,
+            Statement::BreakStatement(break_statement) => break_statement.span,
+            Statement::ContinueStatement(continue_statement) => continue_statement.span,
+            Statement::ForStatement(for_statement) => for_statement.span,
+            Statement::DestructureStatement(destructure) => destructure.span,
+            Statement::ThrowStatement(throw_statement) => throw_statement.span,
+            Statement::FunctionStatement(declaration) => declaration.span,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockStatement {
+    pub token: token::Token,
+    pub statements: Vec<Statement>,
+    pub span: Span,
+}
+
+impl BlockStatement {
+    pub fn string(&self) -> String {
+        let mut out = "".to_string();
+        for statement in &self.statements {
+            out.push_str(&statement.string());
+        }
         out
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LetStatement {
     pub token: token::Token,
+    pub kind: BindingKind,
     pub name: Identifier,
     pub value: Expression,
+    pub span: Span,
 }
 
 impl LetStatement {
@@ -66,19 +106,59 @@ impl LetStatement {
     fn string(&self) -> String {
         let mut out = "".to_string();
         out.push_str(&self.token_literal());
-        out.push_str(" ");
+        out.push(' ');
         out.push_str(&self.name.string());
         out.push_str(" = ");
         out.push_str(&self.value.string());
-        out.push_str(";");
+        out.push(';');
         out
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// `let [a, ...rest] = value;` or `let {name} = value;`, binding every name
+/// in the pattern. Unlike in a match arm, a value that does not match is a
+/// runtime error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DestructureStatement {
+    pub token: token::Token,
+    pub kind: BindingKind,
+    pub pattern: Pattern,
+    pub value: Expression,
+    pub span: Span,
+}
+
+impl DestructureStatement {
+    fn string(&self) -> String {
+        format!(
+            "{} {} = {};",
+            self.token.literal(),
+            self.pattern.string(),
+            self.value.string()
+        )
+    }
+}
+
+/// Whether a name bound by a `LetStatement` can be assigned to later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingKind {
+    Let,
+    Const,
+}
+
+impl BindingKind {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            BindingKind::Let => "let",
+            BindingKind::Const => "const",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStatement {
     pub token: token::Token,
     pub return_value: Expression,
+    pub span: Span,
 }
 
 impl ReturnStatement {
@@ -88,135 +168,739 @@ impl ReturnStatement {
     fn string(&self) -> String {
         let mut out = "".to_string();
         out.push_str(&self.token_literal());
-        out.push_str(" ");
+        out.push(' ');
         out.push_str(&self.return_value.string());
-        out.push_str(";");
+        out.push(';');
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhileStatement {
+    pub token: token::Token,
+    pub condition: Expression,
+    pub body: BlockStatement,
+    pub span: Span,
+}
+
+impl WhileStatement {
+    fn string(&self) -> String {
+        let mut out = "".to_string();
+        out.push_str("while");
+        out.push_str(&self.condition.string());
+        out.push(' ');
+        out.push_str(&self.body.string());
         out
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// `for (value in iterable)` or `for (key, value in iterable)`. Arrays and
+/// strings give their elements or characters, with the index as the key;
+/// hashes give their values with the key as the key, or just their keys
+/// when there is one variable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForStatement {
+    pub token: token::Token,
+    pub key: Option<Identifier>,
+    pub value: Identifier,
+    pub iterable: Expression,
+    pub body: BlockStatement,
+    pub span: Span,
+}
+
+impl ForStatement {
+    fn string(&self) -> String {
+        let mut out = "".to_string();
+        out.push_str("for(");
+        if let Some(key) = &self.key {
+            out.push_str(&key.value);
+            out.push_str(", ");
+        }
+        out.push_str(&self.value.value);
+        out.push_str(" in ");
+        out.push_str(&self.iterable.string());
+        out.push_str(") ");
+        out.push_str(&self.body.string());
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrowStatement {
+    pub token: token::Token,
+    pub value: Expression,
+    pub span: Span,
+}
+
+impl ThrowStatement {
+    fn string(&self) -> String {
+        format!("throw {};", self.value.string())
+    }
+}
+
+/// `fn name(parameters) { body }`. The function is bound to `name` when the
+/// block or program containing the declaration starts running, so it can be
+/// called by statements before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionStatement {
+    pub token: token::Token,
+    pub name: Identifier,
+    /// Has `name` as its name and spans the whole declaration.
+    pub function: FunctionLiteral,
+    pub span: Span,
+}
+
+impl FunctionStatement {
+    fn string(&self) -> String {
+        let parameters: Vec<String> = self
+            .function
+            .parameters
+            .iter()
+            .map(|p| p.string())
+            .collect();
+        format!(
+            "fn {}({}) {}",
+            self.name.string(),
+            parameters.join(", "),
+            self.function.body.string()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BreakStatement {
+    pub token: token::Token,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContinueStatement {
+    pub token: token::Token,
+    pub span: Span,
+}
+
+/// `Clone`, `PartialEq`, `Debug` and `Drop` go through the left operands of
+/// a chain like `a + b + c` one level at a time however long it is, see
+/// `with_stack`.
 pub enum Expression {
     Identifier(Identifier),
     IntegerLiteral(IntegerLiteral),
+    FloatLiteral(FloatLiteral),
     PrefixExpression(PrefixExpression),
     InfixExpression(InfixExpression),
+    Boolean(Boolean),
+    IfExpression(IfExpression),
+    FunctionLiteral(FunctionLiteral),
+    CallExpression(CallExpression),
+    StringLiteral(StringLiteral),
+    ArrayLiteral(ArrayLiteral),
+    HashLiteral(HashLiteral),
+    IndexExpression(IndexExpression),
+    AssignExpression(AssignExpression),
+    MatchExpression(MatchExpression),
+    TryExpression(TryExpression),
+}
+
+macro_rules! expression_impls {
+    ($($variant:ident),*) => {
+        impl Clone for Expression {
+            fn clone(&self) -> Expression {
+                crate::with_stack(|| match self {
+                    $(Expression::$variant(inner) => Expression::$variant(inner.clone()),)*
+                })
+            }
+        }
+
+        impl PartialEq for Expression {
+            fn eq(&self, other: &Expression) -> bool {
+                crate::with_stack(|| match (self, other) {
+                    $((Expression::$variant(a), Expression::$variant(b)) => a == b,)*
+                    _ => false,
+                })
+            }
+        }
+
+        impl fmt::Debug for Expression {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                crate::with_stack(|| match self {
+                    $(Expression::$variant(inner) => {
+                        f.debug_tuple(stringify!($variant)).field(inner).finish()
+                    })*
+                })
+            }
+        }
+    };
+}
+
+expression_impls!(
+    Identifier,
+    IntegerLiteral,
+    FloatLiteral,
+    PrefixExpression,
+    InfixExpression,
+    Boolean,
+    IfExpression,
+    FunctionLiteral,
+    CallExpression,
+    StringLiteral,
+    ArrayLiteral,
+    HashLiteral,
+    IndexExpression,
+    AssignExpression,
+    MatchExpression,
+    TryExpression
+);
+
+// Drops the left operand of a chain here rather than in the drop glue after
+// the node's own `drop` returns, so that it can be done on a new stack segment.
+fn drop_left(left: &mut Expression) {
+    let placeholder = Expression::Boolean(Boolean {
+        token: token::Token::TRUE,
+        value: true,
+        span: Span::default(),
+    });
+    let left = std::mem::replace(left, placeholder);
+    crate::with_stack(move || drop(left));
 }
 
 impl Expression {
-    fn token_literal(&self) -> String {
-        "".to_string()
-    }
     pub fn string(&self) -> String {
+        crate::with_stack(|| self.kind_string())
+    }
+
+    fn kind_string(&self) -> String {
         match self {
             Expression::Identifier(identifier) => identifier.string(),
             Expression::IntegerLiteral(integer_literal) => integer_literal.string(),
+            Expression::FloatLiteral(float_literal) => float_literal.string(),
             Expression::PrefixExpression(prefix_expression) => prefix_expression.string(),
             Expression::InfixExpression(infix_expression) => infix_expression.string(),
+            Expression::Boolean(boolean) => boolean.string(),
+            Expression::IfExpression(if_expression) => if_expression.string(),
+            Expression::FunctionLiteral(function_literal) => function_literal.string(),
+            Expression::CallExpression(call_expression) => call_expression.string(),
+            Expression::StringLiteral(string_literal) => string_literal.string(),
+            Expression::ArrayLiteral(array_literal) => array_literal.string(),
+            Expression::HashLiteral(hash_literal) => hash_literal.string(),
+            Expression::IndexExpression(index_expression) => index_expression.string(),
+            Expression::AssignExpression(assign_expression) => assign_expression.string(),
+            Expression::MatchExpression(match_expression) => match_expression.string(),
+            Expression::TryExpression(try_expression) => try_expression.string(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Identifier(identifier) => identifier.span,
+            Expression::IntegerLiteral(integer_literal) => integer_literal.span,
+            Expression::FloatLiteral(float_literal) => float_literal.span,
+            Expression::PrefixExpression(prefix_expression) => prefix_expression.span,
+            Expression::InfixExpression(infix_expression) => infix_expression.span,
+            Expression::Boolean(boolean) => boolean.span,
+            Expression::IfExpression(if_expression) => if_expression.span,
+            Expression::FunctionLiteral(function_literal) => function_literal.span,
+            Expression::CallExpression(call_expression) => call_expression.span,
+            Expression::StringLiteral(string_literal) => string_literal.span,
+            Expression::ArrayLiteral(array_literal) => array_literal.span,
+            Expression::HashLiteral(hash_literal) => hash_literal.span,
+            Expression::IndexExpression(index_expression) => index_expression.span,
+            Expression::AssignExpression(assign_expression) => assign_expression.span,
+            Expression::MatchExpression(match_expression) => match_expression.span,
+            Expression::TryExpression(try_expression) => try_expression.span,
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+/// `target = value`, or a compound form like `target += value`. The parser
+/// only accepts an identifier as the target, or an index expression whose
+/// left side is itself a target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssignExpression {
+    pub token: token::Token,
+    pub target: Box<Expression>,
+    pub operator: String,
+    pub value: Box<Expression>,
+    pub span: Span,
+}
+
+impl AssignExpression {
+    fn string(&self) -> String {
+        let mut out = "".to_string();
+        out.push('(');
+        out.push_str(&self.target.string());
+        out.push(' ');
+        out.push_str(&self.operator);
+        out.push(' ');
+        out.push_str(&self.value.string());
+        out.push(')');
+        out
+    }
+
+    /// Whether `expression` can be assigned to.
+    pub fn is_target(expression: &Expression) -> bool {
+        match expression {
+            Expression::Identifier(_) => true,
+            Expression::IndexExpression(index) => AssignExpression::is_target(&index.left),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct InfixExpression {
     pub token: token::Token,
     pub left: Box<Expression>,
     pub operator: String,
     pub right: Box<Expression>,
+    pub span: Span,
 }
 
-impl InfixExpression {
-    fn token_literal(&self) -> String {
-        self.token.literal()
+impl Drop for InfixExpression {
+    fn drop(&mut self) {
+        drop_left(&mut self.left);
     }
+}
+
+impl InfixExpression {
     fn string(&self) -> String {
         let mut out = "".to_string();
-        out.push_str("(");
+        out.push('(');
         out.push_str(&self.left.string());
-        out.push_str(" ");
+        out.push(' ');
         out.push_str(&self.operator);
-        out.push_str(" ");
+        out.push(' ');
         out.push_str(&self.right.string());
-        out.push_str(")");
+        out.push(')');
         out
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PrefixExpression {
     pub token: token::Token,
     pub operator: String,
     pub right: Box<Expression>,
+    pub span: Span,
 }
 
 impl PrefixExpression {
-    fn token_literal(&self) -> String {
-        self.token.literal()
-    }
     fn string(&self) -> String {
         let mut out = "".to_string();
-        out.push_str("(");
+        out.push('(');
         out.push_str(&self.operator);
         out.push_str(&self.right.string());
-        out.push_str(")");
+        out.push(')');
         out
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct IntegerLiteral {
     pub token: token::Token,
-    pub value: i64,
+    pub value: BigInt,
+    pub span: Span,
 }
 
 impl IntegerLiteral {
-    fn token_literal(&self) -> String {
+    pub fn string(&self) -> String {
         self.token.literal()
     }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FloatLiteral {
+    pub token: token::Token,
+    pub value: f64,
+    pub span: Span,
+}
+
+impl FloatLiteral {
     pub fn string(&self) -> String {
         self.token.literal()
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Identifier {
+#[derive(Debug, Clone, PartialEq)]
+pub struct Boolean {
     pub token: token::Token,
-    pub value: String,
+    pub value: bool,
+    pub span: Span,
 }
 
-impl Identifier {
-    fn token_literal(&self) -> String {
+impl Boolean {
+    fn string(&self) -> String {
         self.token.literal()
     }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IfExpression {
+    pub token: token::Token,
+    pub condition: Box<Expression>,
+    pub consequence: BlockStatement,
+    pub alternative: Option<BlockStatement>,
+    pub span: Span,
+}
+
+impl IfExpression {
+    fn string(&self) -> String {
+        let mut out = "".to_string();
+        out.push_str("if");
+        out.push_str(&self.condition.string());
+        out.push(' ');
+        out.push_str(&self.consequence.string());
+        if let Some(alternative) = &self.alternative {
+            out.push_str("else ");
+            out.push_str(&alternative.string());
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionLiteral {
+    pub token: token::Token,
+    pub parameters: Vec<Identifier>,
+    pub body: BlockStatement,
+    pub span: Span,
+    /// The name the function is declared with, or bound to by a `let` or
+    /// `const` whose value is the literal itself. Used in stack traces.
+    pub name: Option<String>,
+}
+
+impl FunctionLiteral {
+    fn string(&self) -> String {
+        let parameters: Vec<String> = self.parameters.iter().map(|p| p.string()).collect();
+        let mut out = "".to_string();
+        out.push_str(&self.token.literal());
+        out.push('(');
+        out.push_str(&parameters.join(", "));
+        out.push_str(") ");
+        out.push_str(&self.body.string());
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallExpression {
+    pub token: token::Token,
+    pub function: Box<Expression>,
+    pub arguments: Vec<Expression>,
+    pub span: Span,
+}
+
+impl Drop for CallExpression {
+    fn drop(&mut self) {
+        drop_left(&mut self.function);
+    }
+}
+
+impl CallExpression {
+    fn string(&self) -> String {
+        let arguments: Vec<String> = self.arguments.iter().map(|a| a.string()).collect();
+        let mut out = "".to_string();
+        out.push_str(&self.function.string());
+        out.push('(');
+        out.push_str(&arguments.join(", "));
+        out.push(')');
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringLiteral {
+    pub token: token::Token,
+    pub value: String,
+    pub span: Span,
+}
+
+impl StringLiteral {
+    pub fn string(&self) -> String {
+        quote(&self.value)
+    }
+}
+
+/// Writes `value` as a string literal, escaping what the lexer unescapes.
+pub fn quote(value: &str) -> String {
+    let mut out = "\"".to_string();
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayLiteral {
+    pub token: token::Token,
+    pub elements: Vec<Expression>,
+    pub span: Span,
+}
+
+impl ArrayLiteral {
+    fn string(&self) -> String {
+        let elements: Vec<String> = self.elements.iter().map(|e| e.string()).collect();
+        format!("[{}]", elements.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashLiteral {
+    pub token: token::Token,
+    pub pairs: Vec<(Expression, Expression)>,
+    pub span: Span,
+}
+
+impl HashLiteral {
+    fn string(&self) -> String {
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key.string(), value.string()))
+            .collect();
+        format!("{{{}}}", pairs.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexExpression {
+    pub token: token::Token,
+    pub left: Box<Expression>,
+    pub index: Box<Expression>,
+    pub span: Span,
+}
+
+impl Drop for IndexExpression {
+    fn drop(&mut self) {
+        drop_left(&mut self.left);
+    }
+}
+
+impl IndexExpression {
+    fn string(&self) -> String {
+        format!("({}[{}])", self.left.string(), self.index.string())
+    }
+}
+
+/// `try { ... } catch (e) { ... } finally { ... }`, with at least one of
+/// `catch` and `finally`. Its value is that of the try block, or of the
+/// catch block when it caught an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryExpression {
+    pub token: token::Token,
+    pub body: BlockStatement,
+    pub catch: Option<Box<CatchClause>>,
+    pub finally: Option<BlockStatement>,
+    pub span: Span,
+}
+
+impl TryExpression {
+    fn string(&self) -> String {
+        let mut out = "".to_string();
+        out.push_str("try ");
+        out.push_str(&self.body.string());
+        if let Some(catch) = &self.catch {
+            out.push_str("catch(");
+            out.push_str(&catch.parameter.string());
+            out.push_str(") ");
+            out.push_str(&catch.body.string());
+        }
+        if let Some(finally) = &self.finally {
+            out.push_str("finally ");
+            out.push_str(&finally.string());
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatchClause {
+    pub token: token::Token,
+    pub parameter: Identifier,
+    pub body: BlockStatement,
+    pub span: Span,
+}
+
+/// `match (subject) { pattern => value, ... }`. Arms are tried in order and
+/// the first whose pattern matches, and whose guard is truthy, is evaluated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchExpression {
+    pub token: token::Token,
+    pub subject: Box<Expression>,
+    pub arms: Vec<MatchArm>,
+    pub span: Span,
+}
+
+impl MatchExpression {
     fn string(&self) -> String {
+        let arms: Vec<String> = self.arms.iter().map(|arm| arm.string()).collect();
+        format!("match({}) {{{}}}", self.subject.string(), arms.join(", "))
+    }
+}
+
+/// `pattern => body` or `pattern if guard => body`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+    pub span: Span,
+}
+
+impl MatchArm {
+    pub fn string(&self) -> String {
+        let mut out = self.pattern.string();
+        if let Some(guard) = &self.guard {
+            out.push_str(" if ");
+            out.push_str(&guard.string());
+        }
+        out.push_str(" => ");
+        out.push_str(&self.body.string());
+        out
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// An integer, float, string or boolean literal, or a negated number.
+    /// Matches values equal to it and of the same type.
+    Literal(Expression),
+    /// `_` matches anything without binding it.
+    Wildcard(Span),
+    /// Matches anything and binds it to the name.
+    Binding(Identifier),
+    Array(ArrayPattern),
+    Hash(HashPattern),
+}
+
+impl Pattern {
+    pub fn string(&self) -> String {
+        match self {
+            Pattern::Literal(literal) => literal.string(),
+            Pattern::Wildcard(_) => "_".to_string(),
+            Pattern::Binding(identifier) => identifier.string(),
+            Pattern::Array(array) => array.string(),
+            Pattern::Hash(hash) => hash.string(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Literal(literal) => literal.span(),
+            Pattern::Wildcard(span) => *span,
+            Pattern::Binding(identifier) => identifier.span,
+            Pattern::Array(array) => array.span,
+            Pattern::Hash(hash) => hash.span,
+        }
+    }
+
+    /// The names bound by this pattern, in source order.
+    pub fn bindings(&self) -> Vec<&Identifier> {
+        match self {
+            Pattern::Literal(_) | Pattern::Wildcard(_) => vec![],
+            Pattern::Binding(identifier) => vec![identifier],
+            Pattern::Array(array) => array
+                .elements
+                .iter()
+                .chain(array.rest.as_deref())
+                .flat_map(|e| e.bindings())
+                .collect(),
+            Pattern::Hash(hash) => hash.pairs.iter().flat_map(|(_, p)| p.bindings()).collect(),
+        }
+    }
+}
+
+/// `[p, q]` matches an array of exactly as many elements. With a rest
+/// pattern, `[p, q, ...rest]`, it matches longer arrays too and `rest` is an
+/// array of the elements after `q`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrayPattern {
+    pub token: token::Token,
+    pub elements: Vec<Pattern>,
+    /// A binding or `_`.
+    pub rest: Option<Box<Pattern>>,
+    pub span: Span,
+}
+
+impl ArrayPattern {
+    fn string(&self) -> String {
+        let mut elements: Vec<String> = self.elements.iter().map(|e| e.string()).collect();
+        if let Some(rest) = &self.rest {
+            elements.push(format!("...{}", rest.string()));
+        }
+        format!("[{}]", elements.join(", "))
+    }
+}
+
+/// `{"key": p}` matches a hash that has every listed key, ignoring any
+/// others. `{name}` is short for `{"name": name}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashPattern {
+    pub token: token::Token,
+    pub pairs: Vec<(Expression, Pattern)>,
+    pub span: Span,
+}
+
+impl HashPattern {
+    fn string(&self) -> String {
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|(key, pattern)| {
+                if HashPattern::is_shorthand(key, pattern) {
+                    pattern.string()
+                } else {
+                    format!("{}: {}", key.string(), pattern.string())
+                }
+            })
+            .collect();
+        format!("{{{}}}", pairs.join(", "))
+    }
+
+    /// Whether the pair can be written as `{name}`.
+    pub fn is_shorthand(key: &Expression, pattern: &Pattern) -> bool {
+        match (key, pattern) {
+            (Expression::StringLiteral(key), Pattern::Binding(name)) => key.value == name.value,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier {
+    pub token: token::Token,
+    pub value: String,
+    pub span: Span,
+}
+
+impl Identifier {
+    pub fn string(&self) -> String {
         self.value.clone()
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExpressionStatement {
     pub token: token::Token,
     pub expression: Expression,
+    pub span: Span,
 }
 
 impl ExpressionStatement {
-    fn token_literal(&self) -> String {
-        self.token.literal()
-    }
     fn string(&self) -> String {
-        match &self.expression {
-            Expression::Identifier(identifier) => identifier.string(),
-            Expression::IntegerLiteral(integer_literal) => integer_literal.string(),
-            Expression::PrefixExpression(prefix_expression) => prefix_expression.string(),
-            Expression::InfixExpression(infix_expression) => infix_expression.string(),
-        }
+        self.expression.string()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-
     use crate::lexer::Lexer;
     use crate::parser::Parser;
     use pretty_assertions::assert_eq;
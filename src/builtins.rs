@@ -0,0 +1,244 @@
+//! Functions every interpreter starts with.
+
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+
+use crate::object::{range_end, Builtin, Object};
+
+pub fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::from_host("len", len),
+        Builtin::from_host("first", |elements: Vec<Object>| elements.first().cloned()),
+        Builtin::from_host("last", |elements: Vec<Object>| elements.last().cloned()),
+        Builtin::from_host("rest", rest),
+        Builtin::from_host("push", push),
+        Builtin::new("puts", puts),
+    ]
+}
+
+/// Numeric functions. Those that round return integers.
+pub fn math() -> Vec<Builtin> {
+    vec![
+        Builtin::from_host("sqrt", sqrt),
+        Builtin::from_host("floor", |x: Object| round_with(x, f64::floor)),
+        Builtin::from_host("ceil", |x: Object| round_with(x, f64::ceil)),
+        Builtin::from_host("round", |x: Object| round_with(x, f64::round)),
+        Builtin::from_host("int", |x: Object| round_with(x, f64::trunc)),
+        Builtin::from_host("float", |x: f64| x),
+        Builtin::from_host("abs", abs),
+        Builtin::from_host("pow", pow),
+    ]
+}
+
+fn len(object: Object) -> Result<i64, String> {
+    let len = match &object {
+        Object::String(value) => value.chars().count(),
+        Object::Array(elements) => elements.len(),
+        Object::Hash(pairs) => pairs.len(),
+        &Object::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            let len = range_end(end, inclusive) - i128::from(start);
+            return Ok(len.clamp(0, i64::MAX.into()) as i64);
+        }
+        other => return Err(format!("argument not supported, got {}", other.type_name())),
+    };
+    Ok(len as i64)
+}
+
+fn rest(elements: Vec<Object>) -> Option<Vec<Object>> {
+    elements.split_first().map(|(_, rest)| rest.to_vec())
+}
+
+fn push(array: Object, element: Object) -> Result<Object, String> {
+    let mut elements = match array {
+        Object::Array(elements) => elements,
+        other => {
+            return Err(format!(
+                "argument 1: expected ARRAY, got {}",
+                other.type_name()
+            ))
+        }
+    };
+    Rc::make_mut(&mut elements).push(element);
+    Ok(Object::Array(elements))
+}
+
+fn sqrt(x: f64) -> Result<f64, String> {
+    if x < 0.0 {
+        return Err(format!("negative argument {}", Object::Float(x)));
+    }
+    Ok(x.sqrt())
+}
+
+// Integers are returned as they are, floats are rounded by `round`.
+fn round_with(x: Object, round: fn(f64) -> f64) -> Result<Object, String> {
+    match x {
+        Object::Integer(_) | Object::BigInteger(_) => Ok(x),
+        Object::Float(value) => float_to_integer(round(value)),
+        other => Err(format!("argument not supported, got {}", other.type_name())),
+    }
+}
+
+fn float_to_integer(value: f64) -> Result<Object, String> {
+    match BigInt::from_f64(value) {
+        Some(value) => Ok(Object::from(value)),
+        None => Err(format!(
+            "cannot convert {} to INTEGER",
+            Object::Float(value)
+        )),
+    }
+}
+
+fn abs(x: Object) -> Result<Object, String> {
+    match x {
+        Object::Integer(value) => Ok(match value.checked_abs() {
+            Some(value) => Object::Integer(value),
+            None => Object::from(BigInt::from(value.unsigned_abs())),
+        }),
+        Object::BigInteger(value) => Ok(Object::from(BigInt::from(value.magnitude().clone()))),
+        Object::Float(value) => Ok(Object::Float(value.abs())),
+        other => Err(format!("argument not supported, got {}", other.type_name())),
+    }
+}
+
+// Largest integer result of `pow`, so that a script cannot ask for a
+// number that takes minutes to compute.
+const MAX_POW_BITS: u64 = 1 << 20;
+
+// Integer to the power of a non-negative integer is an integer, everything
+// else is a float.
+fn pow(base: Object, exponent: Object) -> Result<Object, String> {
+    if let (Object::Integer(_) | Object::BigInteger(_), Object::Integer(exponent)) =
+        (&base, &exponent)
+    {
+        if let Ok(exponent) = u32::try_from(*exponent) {
+            let base = match base {
+                Object::Integer(value) => BigInt::from(value),
+                Object::BigInteger(value) => BigInt::clone(&value),
+                _ => unreachable!(),
+            };
+            if base.bits().saturating_mul(u64::from(exponent)) > MAX_POW_BITS {
+                return Err("result too large".to_string());
+            }
+            return Ok(Object::from(base.pow(exponent)));
+        }
+    }
+    match (base.to_f64(), exponent.to_f64()) {
+        (Some(base), Some(exponent)) => Ok(Object::Float(base.powf(exponent))),
+        (None, _) => Err(format!(
+            "argument 1: expected FLOAT, got {}",
+            base.type_name()
+        )),
+        (_, None) => Err(format!(
+            "argument 2: expected FLOAT, got {}",
+            exponent.type_name()
+        )),
+    }
+}
+
+fn puts(arguments: Vec<Object>) -> Result<Object, String> {
+    for argument in arguments {
+        println!("{}", argument);
+    }
+    Ok(Object::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Interpreter;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_builtins() {
+        let tests = vec![
+            (r#"len("")"#, "0"),
+            (r#"len("four")"#, "4"),
+            (r#"len("héllo")"#, "5"),
+            ("len([1, 2, 3])", "3"),
+            (r#"len({"a": 1})"#, "1"),
+            ("len(1..4)", "3"),
+            ("len(1..=4)", "4"),
+            ("len(0..=9223372036854775807)", "9223372036854775807"),
+            ("len(5..1)", "0"),
+            ("first([1, 2, 3])", "1"),
+            ("first([])", "null"),
+            ("last([1, 2, 3])", "3"),
+            ("rest([1, 2, 3])", "[2, 3]"),
+            ("rest([])", "null"),
+            ("let a = [1]; let b = push(a, 2); [a, b]", "[[1], [1, 2]]"),
+            (r#"puts("hello")"#, "null"),
+        ];
+
+        for (input, expected) in tests {
+            let value = Interpreter::new().eval(input).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_math() {
+        let tests = vec![
+            ("sqrt(16)", "4.0"),
+            ("sqrt(2.25)", "1.5"),
+            ("floor(2.7)", "2"),
+            ("floor(-2.5)", "-3"),
+            ("ceil(2.1)", "3"),
+            ("round(2.5)", "3"),
+            ("int(-2.7)", "-2"),
+            ("int(7)", "7"),
+            ("floor(1e20)", "100000000000000000000"),
+            ("float(3)", "3.0"),
+            ("abs(-3)", "3"),
+            ("abs(-9223372036854775807 - 1)", "9223372036854775808"),
+            ("abs(-1.5)", "1.5"),
+            ("pow(2, 10)", "1024"),
+            ("pow(2, 64)", "18446744073709551616"),
+            ("pow(2, -1)", "0.5"),
+            ("pow(4, 0.5)", "2.0"),
+        ];
+
+        for (input, expected) in tests {
+            let value = Interpreter::new().eval(input).unwrap();
+            assert_eq!(value.to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_builtin_errors() {
+        let tests = vec![
+            ("len(1)", "len: argument not supported, got INTEGER at 0..6"),
+            (
+                r#"len("one", "two")"#,
+                "len: wrong number of arguments: expected 1, got 2 at 0..17",
+            ),
+            (
+                "first(1)",
+                "first: argument 1: expected ARRAY, got INTEGER at 0..8",
+            ),
+            ("sqrt(-1)", "sqrt: negative argument -1.0 at 0..8"),
+            (
+                "sqrt(true)",
+                "sqrt: argument 1: expected FLOAT, got BOOLEAN at 0..10",
+            ),
+            (
+                "floor(1e300 * 1e300)",
+                "floor: cannot convert inf to INTEGER at 0..20",
+            ),
+            ("pow(10, 1000000)", "pow: result too large at 0..16"),
+            (
+                "push(1, 1)",
+                "push: argument 1: expected ARRAY, got INTEGER at 0..10",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let error = Interpreter::new().eval(input).unwrap_err();
+            assert_eq!(error.to_string(), expected, "{}", input);
+        }
+    }
+}
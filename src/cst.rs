@@ -0,0 +1,1067 @@
+use crate::ast::{
+    ArrayLiteral, ArrayPattern, AssignExpression, BindingKind, BlockStatement, Boolean,
+    BreakStatement, CallExpression, CatchClause, ContinueStatement, DestructureStatement,
+    Expression, ExpressionStatement, FloatLiteral, ForStatement, FunctionLiteral,
+    FunctionStatement, HashLiteral, HashPattern, Identifier, IfExpression, IndexExpression,
+    InfixExpression, IntegerLiteral, LetStatement, MatchArm, MatchExpression, Pattern,
+    PrefixExpression, Program, ReturnStatement, Statement, StringLiteral, ThrowStatement,
+    TryExpression, WhileStatement,
+};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::token::{Span, Token};
+pub use crate::token::{SyntaxToken, Trivia, TriviaKind};
+use std::cmp::Reverse;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyntaxKind {
+    Program,
+    LetStatement,
+    DestructureStatement,
+    ReturnStatement,
+    ThrowStatement,
+    FunctionStatement,
+    ExpressionStatement,
+    WhileStatement,
+    BreakStatement,
+    ContinueStatement,
+    ForStatement,
+    Identifier,
+    IntegerLiteral,
+    FloatLiteral,
+    PrefixExpression,
+    InfixExpression,
+    AssignExpression,
+    Boolean,
+    ParenExpression,
+    IfExpression,
+    BlockStatement,
+    FunctionLiteral,
+    CallExpression,
+    StringLiteral,
+    ArrayLiteral,
+    HashLiteral,
+    IndexExpression,
+    MatchExpression,
+    MatchArm,
+    ArrayPattern,
+    HashPattern,
+    TryExpression,
+    CatchClause,
+    // tokens skipped while recovering from a parse error
+    Error,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// Concrete syntax tree node. Unlike the ast, every token of the source is
+/// kept (trivia included), so `text` reproduces the input exactly.
+///
+/// A chain like `a + b + c` nests one node per operator, so the tree is as deep
+/// as the chain is long; `Clone`, `PartialEq`, `Debug` and `Drop` run on a
+/// growing stack for it, see `with_stack`.
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl Clone for SyntaxNode {
+    fn clone(&self) -> Self {
+        crate::with_stack(|| SyntaxNode {
+            kind: self.kind,
+            children: self.children.clone(),
+        })
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        crate::with_stack(|| self.kind == other.kind && self.children == other.children)
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        crate::with_stack(|| {
+            f.debug_struct("SyntaxNode")
+                .field("kind", &self.kind)
+                .field("children", &self.children)
+                .finish()
+        })
+    }
+}
+
+impl Drop for SyntaxNode {
+    fn drop(&mut self) {
+        let children = std::mem::take(&mut self.children);
+        crate::with_stack(|| drop(children));
+    }
+}
+
+impl SyntaxNode {
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        for token in self.tokens() {
+            out.push_str(&token.full_text());
+        }
+        out
+    }
+
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        // an explicit stack rather than recursion, the tree may be very deep
+        let mut tokens = vec![];
+        let mut pending = vec![self.children.iter()];
+        while let Some(children) = pending.last_mut() {
+            match children.next() {
+                Some(SyntaxElement::Node(node)) => pending.push(node.children.iter()),
+                Some(SyntaxElement::Token(token)) => tokens.push(token),
+                None => {
+                    pending.pop();
+                }
+            }
+        }
+        tokens
+    }
+
+    pub fn child_nodes(&self) -> Vec<&SyntaxNode> {
+        let mut nodes = vec![];
+        for child in &self.children {
+            if let SyntaxElement::Node(node) = child {
+                nodes.push(node);
+            }
+        }
+        nodes
+    }
+
+    /// Span of the node's tokens, leading trivia excluded.
+    pub fn span(&self) -> Span {
+        let tokens = self.tokens();
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => Span::new(first.span.start, last.span.end),
+            _ => Span::default(),
+        }
+    }
+}
+
+/// Parses `input` into its concrete syntax tree along with the ast derived
+/// from the same pass.
+pub fn parse(input: &str) -> (SyntaxNode, Result<Program, String>) {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program();
+    (parser.syntax_tree(), program)
+}
+
+/// Lossless token stream of `input`, ending with `EOF`.
+///
+/// ```
+/// use monkey_language_rust::cst::{self, TriviaKind};
+///
+/// let tokens = cst::tokenize("// answer\n42");
+/// assert_eq!(tokens[0].text, "42");
+/// assert_eq!(tokens[0].leading_trivia[0].kind, TriviaKind::LineComment);
+/// ```
+pub fn tokenize(input: &str) -> Vec<SyntaxToken> {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next_syntax_token();
+        let done = token.kind == Token::EOF;
+        tokens.push(token);
+        if done {
+            return tokens;
+        }
+    }
+}
+
+enum Event {
+    Token(SyntaxToken),
+    Finish,
+}
+
+/// Collects the tokens consumed by the parser and the nodes wrapped around
+/// them. Nodes are only added once their parse succeeded, so a failed parse
+/// leaves its tokens in the enclosing node and the tree stays balanced.
+pub(crate) struct TreeBuilder {
+    events: Vec<Event>,
+    // the nodes started before the event at each index, kept apart so that
+    // wrapping is not an insert into `events`
+    starts: Vec<(usize, SyntaxKind)>,
+    cur_token: usize,
+}
+
+impl TreeBuilder {
+    pub fn new() -> TreeBuilder {
+        TreeBuilder {
+            events: vec![],
+            starts: vec![],
+            cur_token: 0,
+        }
+    }
+
+    pub fn token(&mut self, token: SyntaxToken) {
+        self.cur_token = self.events.len();
+        self.events.push(Event::Token(token));
+    }
+
+    /// Position of the current token, to later wrap a node starting there.
+    pub fn checkpoint(&self) -> usize {
+        self.cur_token
+    }
+
+    /// Wraps everything from `checkpoint` up to the current token in a node.
+    pub fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.starts.push((checkpoint, kind));
+        self.events.push(Event::Finish);
+    }
+
+    pub fn finish(self) -> SyntaxNode {
+        // of the nodes started at the same place, the last one wrapped is the
+        // outermost
+        let mut starts: Vec<_> = self.starts.into_iter().enumerate().collect();
+        starts.sort_by_key(|&(order, (at, _))| (at, Reverse(order)));
+        let mut starts = starts.into_iter().map(|(_, start)| start).peekable();
+
+        let mut stack = vec![SyntaxNode {
+            kind: SyntaxKind::Program,
+            children: vec![],
+        }];
+        for (at, event) in self.events.into_iter().enumerate() {
+            while let Some((_, kind)) = starts.next_if(|&(start, _)| start == at) {
+                stack.push(SyntaxNode {
+                    kind,
+                    children: vec![],
+                });
+            }
+            match event {
+                Event::Token(token) => stack
+                    .last_mut()
+                    .unwrap()
+                    .children
+                    .push(SyntaxElement::Token(token)),
+                Event::Finish => {
+                    let node = stack.pop().unwrap();
+                    stack
+                        .last_mut()
+                        .unwrap()
+                        .children
+                        .push(SyntaxElement::Node(node));
+                }
+            }
+        }
+        let mut root = stack.pop().unwrap();
+        // unwrap the program node produced by `Parser::parse_program`
+        if let [SyntaxElement::Node(node)] = root.children.as_mut_slice() {
+            if node.kind == SyntaxKind::Program {
+                return SyntaxNode {
+                    kind: SyntaxKind::Program,
+                    children: std::mem::take(&mut node.children),
+                };
+            }
+        }
+        root.kind = SyntaxKind::Program;
+        root
+    }
+}
+
+/// Lowers a syntax tree to its ast, the same ast the parser builds alongside
+/// it, so a tool can edit the tree and evaluate the result without printing
+/// and parsing it again. Only the shape of the tree is checked: an `Error`
+/// node fails, but what the parser reports without changing the shape, like
+/// an assignment to a constant, does not.
+impl TryFrom<&SyntaxNode> for Program {
+    type Error = String;
+
+    fn try_from(node: &SyntaxNode) -> Result<Program, String> {
+        if node.kind != SyntaxKind::Program {
+            return Err(unexpected(node, node.kind));
+        }
+        let mut program = Program::new();
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(node) => program.statements.push(lower_statement(node)?),
+                SyntaxElement::Token(token) if token.kind == Token::EOF => {}
+                SyntaxElement::Token(token) => return Err(unexpected(node, &token.kind)),
+            }
+        }
+        Ok(program)
+    }
+}
+
+// The children of a node, taken from the front as a node of its kind is
+// expected to have them.
+struct Children<'a> {
+    node: &'a SyntaxNode,
+    next: usize,
+}
+
+impl<'a> Children<'a> {
+    fn new(node: &'a SyntaxNode) -> Children<'a> {
+        Children { node, next: 0 }
+    }
+
+    fn peek(&self) -> Option<&'a SyntaxElement> {
+        self.node.children.get(self.next)
+    }
+
+    fn peek_is(&self, kind: &Token) -> bool {
+        matches!(self.peek(), Some(SyntaxElement::Token(token)) if token.kind == *kind)
+    }
+
+    fn element(&mut self) -> Result<&'a SyntaxElement, String> {
+        let element = self.peek().ok_or_else(|| {
+            format!(
+                "{:?} at {} ends too early",
+                self.node.kind,
+                node_span(self.node)
+            )
+        })?;
+        self.next += 1;
+        Ok(element)
+    }
+
+    fn token(&mut self) -> Result<&'a SyntaxToken, String> {
+        match self.element()? {
+            SyntaxElement::Token(token) => Ok(token),
+            SyntaxElement::Node(node) => Err(unexpected(self.node, node.kind)),
+        }
+    }
+
+    fn expect(&mut self, kind: Token) -> Result<&'a SyntaxToken, String> {
+        let token = self.token()?;
+        match token.kind == kind {
+            true => Ok(token),
+            false => Err(unexpected(self.node, &token.kind)),
+        }
+    }
+
+    // Takes the next token if it is `kind`, like an optional semicolon.
+    fn skip(&mut self, kind: Token) -> bool {
+        let found = self.peek_is(&kind);
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn node(&mut self) -> Result<&'a SyntaxNode, String> {
+        match self.element()? {
+            SyntaxElement::Node(node) => Ok(node),
+            SyntaxElement::Token(token) => Err(unexpected(self.node, &token.kind)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, String> {
+        lower_expression(self.node()?)
+    }
+
+    fn block(&mut self) -> Result<BlockStatement, String> {
+        lower_block(self.node()?)
+    }
+
+    fn identifier(&mut self) -> Result<Identifier, String> {
+        lower_identifier(self.node()?)
+    }
+
+    // Expressions separated by commas, up to `end`.
+    fn expression_list(&mut self, end: Token) -> Result<Vec<Expression>, String> {
+        let mut list = vec![];
+        while !self.skip(end.clone()) {
+            list.push(self.expression()?);
+            if !self.peek_is(&end) {
+                self.expect(Token::COMMA)?;
+            }
+        }
+        Ok(list)
+    }
+
+    fn finish(&self) -> Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(SyntaxElement::Token(token)) => Err(unexpected(self.node, &token.kind)),
+            Some(SyntaxElement::Node(node)) => Err(unexpected(self.node, node.kind)),
+        }
+    }
+}
+
+fn unexpected(node: &SyntaxNode, found: impl fmt::Debug) -> String {
+    format!(
+        "unexpected {:?} in {:?} at {}",
+        found,
+        node.kind,
+        node_span(node)
+    )
+}
+
+// Like `SyntaxNode::span`, but only going down the first and last children
+// rather than collecting every token.
+fn node_span(node: &SyntaxNode) -> Span {
+    match (first_token(node), last_token(node)) {
+        (Some(first), Some(last)) => Span::new(first.span.start, last.span.end),
+        _ => Span::default(),
+    }
+}
+
+fn first_token(mut node: &SyntaxNode) -> Option<&SyntaxToken> {
+    loop {
+        match node.children.first()? {
+            SyntaxElement::Node(child) => node = child,
+            SyntaxElement::Token(token) => return Some(token),
+        }
+    }
+}
+
+fn last_token(mut node: &SyntaxNode) -> Option<&SyntaxToken> {
+    loop {
+        match node.children.last()? {
+            SyntaxElement::Node(child) => node = child,
+            SyntaxElement::Token(token) => return Some(token),
+        }
+    }
+}
+
+fn binding_kind(token: &SyntaxToken) -> BindingKind {
+    match token.kind {
+        Token::CONST => BindingKind::Const,
+        _ => BindingKind::Let,
+    }
+}
+
+fn lower_statement(node: &SyntaxNode) -> Result<Statement, String> {
+    let mut children = Children::new(node);
+    let span = node_span(node);
+    let statement = match node.kind {
+        SyntaxKind::LetStatement => {
+            let token = children.token()?;
+            let name = children.identifier()?;
+            children.expect(Token::ASSIGN)?;
+            let mut value = children.expression()?;
+            if let Expression::FunctionLiteral(function) = &mut value {
+                function.name = Some(name.value.clone());
+            }
+            Statement::LetStatement(LetStatement {
+                token: token.kind.clone(),
+                kind: binding_kind(token),
+                name,
+                value,
+                span,
+            })
+        }
+        SyntaxKind::DestructureStatement => {
+            let token = children.token()?;
+            let pattern = lower_pattern(children.element()?)?;
+            children.expect(Token::ASSIGN)?;
+            Statement::DestructureStatement(DestructureStatement {
+                token: token.kind.clone(),
+                kind: binding_kind(token),
+                pattern,
+                value: children.expression()?,
+                span,
+            })
+        }
+        SyntaxKind::ReturnStatement => Statement::ReturnStatement(ReturnStatement {
+            token: children.expect(Token::RETURN)?.kind.clone(),
+            return_value: children.expression()?,
+            span,
+        }),
+        SyntaxKind::ThrowStatement => Statement::ThrowStatement(ThrowStatement {
+            token: children.expect(Token::THROW)?.kind.clone(),
+            value: children.expression()?,
+            span,
+        }),
+        SyntaxKind::FunctionStatement => {
+            let token = children.expect(Token::FUNCTION)?;
+            let name = children.identifier()?;
+            let function = lower_function(&mut children, token, Some(name.value.clone()))?;
+            Statement::FunctionStatement(FunctionStatement {
+                token: token.kind.clone(),
+                name,
+                function,
+                span,
+            })
+        }
+        SyntaxKind::ExpressionStatement => Statement::ExpressionStatement(ExpressionStatement {
+            token: first_token(node).map_or(Token::EOF, |token| token.kind.clone()),
+            expression: children.expression()?,
+            span,
+        }),
+        SyntaxKind::WhileStatement => {
+            let token = children.expect(Token::WHILE)?;
+            children.expect(Token::LPAREN)?;
+            let condition = children.expression()?;
+            children.expect(Token::RPAREN)?;
+            Statement::WhileStatement(WhileStatement {
+                token: token.kind.clone(),
+                condition,
+                body: children.block()?,
+                span,
+            })
+        }
+        SyntaxKind::ForStatement => {
+            let token = children.expect(Token::FOR)?;
+            children.expect(Token::LPAREN)?;
+            let mut key = None;
+            let mut value = children.identifier()?;
+            if children.skip(Token::COMMA) {
+                key = Some(value);
+                value = children.identifier()?;
+            }
+            children.expect(Token::IN)?;
+            let iterable = children.expression()?;
+            children.expect(Token::RPAREN)?;
+            Statement::ForStatement(ForStatement {
+                token: token.kind.clone(),
+                key,
+                value,
+                iterable,
+                body: children.block()?,
+                span,
+            })
+        }
+        SyntaxKind::BreakStatement => Statement::BreakStatement(BreakStatement {
+            token: children.expect(Token::BREAK)?.kind.clone(),
+            span,
+        }),
+        SyntaxKind::ContinueStatement => Statement::ContinueStatement(ContinueStatement {
+            token: children.expect(Token::CONTINUE)?.kind.clone(),
+            span,
+        }),
+        SyntaxKind::Error => return Err(format!("syntax error at {}", span)),
+        kind => return Err(format!("expected a statement at {}, got {:?}", span, kind)),
+    };
+    children.skip(Token::SEMICOLON);
+    children.finish()?;
+    Ok(statement)
+}
+
+fn lower_block(node: &SyntaxNode) -> Result<BlockStatement, String> {
+    if node.kind != SyntaxKind::BlockStatement {
+        return Err(format!(
+            "expected a block at {}, got {:?}",
+            node_span(node),
+            node.kind
+        ));
+    }
+    let mut children = Children::new(node);
+    let token = children.expect(Token::LBRACE)?;
+    let mut statements = vec![];
+    while !children.skip(Token::RBRACE) {
+        statements.push(lower_statement(children.node()?)?);
+    }
+    children.finish()?;
+    Ok(BlockStatement {
+        token: token.kind.clone(),
+        statements,
+        span: node_span(node),
+    })
+}
+
+fn lower_identifier(node: &SyntaxNode) -> Result<Identifier, String> {
+    let mut children = Children::new(node);
+    let token = children.token()?;
+    children.finish()?;
+    match (node.kind, &token.kind) {
+        (SyntaxKind::Identifier, Token::IDENT(name)) => Ok(Identifier {
+            token: token.kind.clone(),
+            value: name.clone(),
+            span: token.span,
+        }),
+        _ => Err(unexpected(node, &token.kind)),
+    }
+}
+
+// The parameters and body of a function, from the `(` on.
+fn lower_function(
+    children: &mut Children,
+    token: &SyntaxToken,
+    name: Option<String>,
+) -> Result<FunctionLiteral, String> {
+    children.expect(Token::LPAREN)?;
+    let mut parameters = vec![];
+    while !children.skip(Token::RPAREN) {
+        parameters.push(children.identifier()?);
+        if !children.peek_is(&Token::RPAREN) {
+            children.expect(Token::COMMA)?;
+        }
+    }
+    let body = children.block()?;
+    Ok(FunctionLiteral {
+        token: token.kind.clone(),
+        parameters,
+        span: Span::new(token.span.start, body.span.end),
+        body,
+        name,
+    })
+}
+
+fn lower_expression(node: &SyntaxNode) -> Result<Expression, String> {
+    crate::with_stack(|| lower_expression_kind(node))
+}
+
+fn lower_expression_kind(node: &SyntaxNode) -> Result<Expression, String> {
+    let mut children = Children::new(node);
+    // the chains start where their left operand does, which can be deep
+    let span = || node_span(node);
+    let end = || last_token(node).map_or(0, |token| token.span.end);
+    let expression = match node.kind {
+        SyntaxKind::Identifier => return lower_identifier(node).map(Expression::Identifier),
+        SyntaxKind::IntegerLiteral | SyntaxKind::FloatLiteral | SyntaxKind::Boolean => {
+            let token = children.token()?;
+            let kind = token.kind.clone();
+            match (node.kind, &token.kind) {
+                (SyntaxKind::IntegerLiteral, Token::INT(value)) => {
+                    Expression::IntegerLiteral(IntegerLiteral {
+                        value: value.clone(),
+                        token: kind,
+                        span: span(),
+                    })
+                }
+                (SyntaxKind::FloatLiteral, Token::FLOAT(value)) => {
+                    Expression::FloatLiteral(FloatLiteral {
+                        value: *value,
+                        token: kind,
+                        span: span(),
+                    })
+                }
+                (SyntaxKind::Boolean, Token::TRUE | Token::FALSE) => Expression::Boolean(Boolean {
+                    value: kind == Token::TRUE,
+                    token: kind,
+                    span: span(),
+                }),
+                _ => return Err(unexpected(node, &token.kind)),
+            }
+        }
+        SyntaxKind::StringLiteral => {
+            let token = children.token()?;
+            match &token.kind {
+                Token::STRING(value) => Expression::StringLiteral(StringLiteral {
+                    token: token.kind.clone(),
+                    value: value.clone(),
+                    span: span(),
+                }),
+                _ => return Err(unexpected(node, &token.kind)),
+            }
+        }
+        SyntaxKind::PrefixExpression => {
+            let token = children.token()?;
+            Expression::PrefixExpression(PrefixExpression {
+                token: token.kind.clone(),
+                operator: token.kind.literal(),
+                right: Box::new(children.expression()?),
+                span: span(),
+            })
+        }
+        // the parentheses are only in the tree, the ast has what is inside
+        SyntaxKind::ParenExpression => {
+            children.expect(Token::LPAREN)?;
+            let expression = children.expression()?;
+            children.expect(Token::RPAREN)?;
+            expression
+        }
+        SyntaxKind::InfixExpression => {
+            let left = children.expression()?;
+            let token = children.token()?;
+            Expression::InfixExpression(InfixExpression {
+                token: token.kind.clone(),
+                operator: token.kind.literal(),
+                span: Span::new(left.span().start, end()),
+                left: Box::new(left),
+                right: Box::new(children.expression()?),
+            })
+        }
+        SyntaxKind::AssignExpression => {
+            let target = children.expression()?;
+            let token = children.token()?;
+            Expression::AssignExpression(AssignExpression {
+                token: token.kind.clone(),
+                operator: token.kind.literal(),
+                span: Span::new(target.span().start, end()),
+                target: Box::new(target),
+                value: Box::new(children.expression()?),
+            })
+        }
+        SyntaxKind::IfExpression => {
+            let token = children.expect(Token::IF)?;
+            children.expect(Token::LPAREN)?;
+            let condition = children.expression()?;
+            children.expect(Token::RPAREN)?;
+            let consequence = children.block()?;
+            let alternative = match children.skip(Token::ELSE) {
+                true => Some(children.block()?),
+                false => None,
+            };
+            Expression::IfExpression(IfExpression {
+                token: token.kind.clone(),
+                condition: Box::new(condition),
+                consequence,
+                alternative,
+                span: span(),
+            })
+        }
+        SyntaxKind::FunctionLiteral => {
+            let token = children.expect(Token::FUNCTION)?;
+            Expression::FunctionLiteral(lower_function(&mut children, token, None)?)
+        }
+        SyntaxKind::CallExpression => {
+            let function = children.expression()?;
+            let token = children.expect(Token::LPAREN)?;
+            Expression::CallExpression(CallExpression {
+                token: token.kind.clone(),
+                span: Span::new(function.span().start, end()),
+                function: Box::new(function),
+                arguments: children.expression_list(Token::RPAREN)?,
+            })
+        }
+        SyntaxKind::ArrayLiteral => Expression::ArrayLiteral(ArrayLiteral {
+            token: children.expect(Token::LBRACKET)?.kind.clone(),
+            elements: children.expression_list(Token::RBRACKET)?,
+            span: span(),
+        }),
+        SyntaxKind::HashLiteral => {
+            let token = children.expect(Token::LBRACE)?;
+            let mut pairs = vec![];
+            while !children.skip(Token::RBRACE) {
+                let key = children.expression()?;
+                children.expect(Token::COLON)?;
+                pairs.push((key, children.expression()?));
+                if !children.peek_is(&Token::RBRACE) {
+                    children.expect(Token::COMMA)?;
+                }
+            }
+            Expression::HashLiteral(HashLiteral {
+                token: token.kind.clone(),
+                pairs,
+                span: span(),
+            })
+        }
+        SyntaxKind::IndexExpression => {
+            let left = children.expression()?;
+            let token = children.expect(Token::LBRACKET)?;
+            let index = children.expression()?;
+            children.expect(Token::RBRACKET)?;
+            Expression::IndexExpression(IndexExpression {
+                token: token.kind.clone(),
+                span: Span::new(left.span().start, end()),
+                left: Box::new(left),
+                index: Box::new(index),
+            })
+        }
+        SyntaxKind::MatchExpression => {
+            let token = children.expect(Token::MATCH)?;
+            children.expect(Token::LPAREN)?;
+            let subject = children.expression()?;
+            children.expect(Token::RPAREN)?;
+            children.expect(Token::LBRACE)?;
+            let mut arms = vec![];
+            while !children.skip(Token::RBRACE) {
+                arms.push(lower_match_arm(children.node()?)?);
+                if !children.peek_is(&Token::RBRACE) {
+                    children.expect(Token::COMMA)?;
+                }
+            }
+            Expression::MatchExpression(MatchExpression {
+                token: token.kind.clone(),
+                subject: Box::new(subject),
+                arms,
+                span: span(),
+            })
+        }
+        SyntaxKind::TryExpression => {
+            let token = children.expect(Token::TRY)?;
+            let body = children.block()?;
+            let catch = match children.peek() {
+                Some(SyntaxElement::Node(node)) if node.kind == SyntaxKind::CatchClause => {
+                    children.next += 1;
+                    Some(Box::new(lower_catch_clause(node)?))
+                }
+                _ => None,
+            };
+            let finally = match children.skip(Token::FINALLY) {
+                true => Some(children.block()?),
+                false => None,
+            };
+            Expression::TryExpression(TryExpression {
+                token: token.kind.clone(),
+                body,
+                catch,
+                finally,
+                span: span(),
+            })
+        }
+        kind => {
+            return Err(format!(
+                "expected an expression at {}, got {:?}",
+                span(),
+                kind
+            ))
+        }
+    };
+    children.finish()?;
+    Ok(expression)
+}
+
+fn lower_catch_clause(node: &SyntaxNode) -> Result<CatchClause, String> {
+    let mut children = Children::new(node);
+    let token = children.expect(Token::CATCH)?;
+    children.expect(Token::LPAREN)?;
+    let parameter = children.identifier()?;
+    children.expect(Token::RPAREN)?;
+    let body = children.block()?;
+    children.finish()?;
+    Ok(CatchClause {
+        token: token.kind.clone(),
+        parameter,
+        body,
+        span: node_span(node),
+    })
+}
+
+fn lower_match_arm(node: &SyntaxNode) -> Result<MatchArm, String> {
+    if node.kind != SyntaxKind::MatchArm {
+        return Err(unexpected(node, node.kind));
+    }
+    let mut children = Children::new(node);
+    let pattern = lower_pattern(children.element()?)?;
+    let guard = match children.skip(Token::IF) {
+        true => Some(children.expression()?),
+        false => None,
+    };
+    children.expect(Token::FatArrow)?;
+    let body = children.expression()?;
+    children.finish()?;
+    Ok(MatchArm {
+        pattern,
+        guard,
+        body,
+        span: node_span(node),
+    })
+}
+
+// A pattern is a node, except for `_`, which is a token of its own.
+fn lower_pattern(element: &SyntaxElement) -> Result<Pattern, String> {
+    let node = match element {
+        SyntaxElement::Token(token) => {
+            return match &token.kind {
+                Token::IDENT(name) if name == "_" => Ok(Pattern::Wildcard(token.span)),
+                kind => Err(format!(
+                    "expected a pattern at {}, got {:?}",
+                    token.span, kind
+                )),
+            }
+        }
+        SyntaxElement::Node(node) => node,
+    };
+    crate::with_stack(|| match node.kind {
+        SyntaxKind::Identifier => lower_identifier(node).map(Pattern::Binding),
+        SyntaxKind::ArrayPattern => lower_array_pattern(node).map(Pattern::Array),
+        SyntaxKind::HashPattern => lower_hash_pattern(node).map(Pattern::Hash),
+        _ => lower_expression(node).map(Pattern::Literal),
+    })
+}
+
+fn lower_array_pattern(node: &SyntaxNode) -> Result<ArrayPattern, String> {
+    let mut children = Children::new(node);
+    let token = children.expect(Token::LBRACKET)?;
+    let mut elements = vec![];
+    let mut rest = None;
+    while !children.skip(Token::RBRACKET) {
+        if children.skip(Token::ELLIPSIS) {
+            rest = Some(Box::new(lower_pattern(children.element()?)?));
+        } else {
+            elements.push(lower_pattern(children.element()?)?);
+        }
+        if !children.peek_is(&Token::RBRACKET) {
+            children.expect(Token::COMMA)?;
+        }
+    }
+    children.finish()?;
+    Ok(ArrayPattern {
+        token: token.kind.clone(),
+        elements,
+        rest,
+        span: node_span(node),
+    })
+}
+
+fn lower_hash_pattern(node: &SyntaxNode) -> Result<HashPattern, String> {
+    let mut children = Children::new(node);
+    let token = children.expect(Token::LBRACE)?;
+    let mut pairs = vec![];
+    while !children.skip(Token::RBRACE) {
+        match children.element()? {
+            // `{name}` binds the value stored under "name"
+            SyntaxElement::Node(node) if node.kind == SyntaxKind::Identifier => {
+                let name = lower_identifier(node)?;
+                let key = Expression::StringLiteral(StringLiteral {
+                    token: Token::STRING(name.value.clone()),
+                    value: name.value.clone(),
+                    span: name.span,
+                });
+                pairs.push((key, Pattern::Binding(name)));
+            }
+            SyntaxElement::Node(node) => {
+                let key = lower_expression(node)?;
+                children.expect(Token::COLON)?;
+                pairs.push((key, lower_pattern(children.element()?)?));
+            }
+            SyntaxElement::Token(token) => return Err(unexpected(node, &token.kind)),
+        }
+        if !children.peek_is(&Token::RBRACE) {
+            children.expect(Token::COMMA)?;
+        }
+    }
+    children.finish()?;
+    Ok(HashPattern {
+        token: token.kind.clone(),
+        pairs,
+        span: node_span(node),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+
+    fn kinds(node: &SyntaxNode) -> Vec<SyntaxKind> {
+        node.child_nodes().iter().map(|node| node.kind).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = vec![
+            "",
+            "   \n",
+            "let x = 5;",
+            "  let   x=5 ;\n// trailing comment\n",
+            "/* header */\nlet add = 1 + 2 * -3; /* nested /* comment */ */\nreturn add;\n\n",
+            "let = 10; let x 5; é + 1",
+            "a + /* unterminated",
+        ];
+
+        for input in inputs {
+            let (tree, _) = parse(input);
+            assert_eq!(tree.text(), input);
+
+            let text: String = tokenize(input).iter().map(|t| t.full_text()).collect();
+            assert_eq!(text, input);
+        }
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let (tree, program) = parse("let x = 5;\n-a * b + c;");
+        assert!(program.is_ok());
+        assert_eq!(tree.kind, SyntaxKind::Program);
+        assert_eq!(
+            kinds(&tree),
+            vec![SyntaxKind::LetStatement, SyntaxKind::ExpressionStatement]
+        );
+
+        let statement = tree.child_nodes()[1];
+        assert_eq!(statement.span(), Span::new(11, 22));
+        let sum = statement.child_nodes()[0];
+        assert_eq!(sum.kind, SyntaxKind::InfixExpression);
+        assert_eq!(sum.text(), "\n-a * b + c");
+        assert_eq!(
+            kinds(sum),
+            vec![SyntaxKind::InfixExpression, SyntaxKind::Identifier]
+        );
+        let product = sum.child_nodes()[0];
+        assert_eq!(
+            kinds(product),
+            vec![SyntaxKind::PrefixExpression, SyntaxKind::Identifier]
+        );
+    }
+
+    #[test]
+    fn test_destructure_statement() {
+        let (tree, program) = parse("const [a, b] = xs;");
+        assert!(program.is_ok());
+        assert_eq!(kinds(&tree), vec![SyntaxKind::DestructureStatement]);
+        assert_eq!(
+            kinds(tree.child_nodes()[0]),
+            vec![SyntaxKind::ArrayPattern, SyntaxKind::Identifier]
+        );
+    }
+
+    #[test]
+    fn test_error_nodes() {
+        let (tree, program) = parse("let = 10; x;");
+        assert!(program.is_err());
+        assert_eq!(
+            kinds(&tree),
+            vec![SyntaxKind::Error, SyntaxKind::ExpressionStatement]
+        );
+        assert_eq!(tree.child_nodes()[0].text(), "let = 10;");
+    }
+
+    fn assert_lowers(input: &str) {
+        let (tree, program) = parse(input);
+        match program {
+            Ok(program) => assert_eq!(Program::try_from(&tree), Ok(program), "{}", input),
+            Err(_) => assert!(Program::try_from(&tree).is_err(), "{}", input),
+        }
+    }
+
+    #[test]
+    fn test_lowering() {
+        let inputs = vec![
+            "(1 + 2) * 3; a * (b + c)",
+            "let add = fn(a, b) { a + b }; fn twice(f, x) { f(f(x)) }",
+            "const { name, \"age\": [_, ...rest] } = person;",
+            "match (x) { -1 => a, [a, _] if a > 0 => b, { \"k\": 1.5 } => c, _ => d }",
+            "try { throw 1 } catch (e) { e } finally { 2 }; xs[0] += 1",
+            "for (k, v in h) { if (v) { break; } else { continue } }",
+            "let = 10; x;",
+        ];
+        for input in inputs {
+            assert_lowers(input);
+        }
+    }
+
+    // The lowered ast is the one the parser built alongside the tree, for
+    // every fixture of the golden tests.
+    #[test]
+    fn test_lowering_golden_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let mut count = 0;
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "mk") {
+                assert_lowers(&fs::read_to_string(&path).unwrap());
+                count += 1;
+            }
+        }
+        assert!(count > 0, "no fixtures in {}", dir.display());
+    }
+
+    #[test]
+    fn test_lowering_errors() {
+        let (tree, _) = parse("let x = 1; let = 2;");
+        assert_eq!(
+            Program::try_from(&tree),
+            Err("syntax error at 11..19".to_string())
+        );
+
+        let (mut tree, _) = parse("f(1)");
+        tree.children.truncate(1);
+        let SyntaxElement::Node(statement) = &mut tree.children[0] else {
+            unreachable!()
+        };
+        let SyntaxElement::Node(call) = &mut statement.children[0] else {
+            unreachable!()
+        };
+        call.children.pop();
+        assert_eq!(
+            Program::try_from(&tree),
+            Err("CallExpression at 0..3 ends too early".to_string())
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::ast::BindingKind;
+use crate::object::Object;
+
+/// Variable bindings of one scope. Function calls get a new environment
+/// enclosed by the one the function was defined in.
+#[derive(Debug, Default)]
+pub struct Environment {
+    store: HashMap<String, Object>,
+    // names in `store` bound with `const`
+    constants: HashSet<String>,
+    outer: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            store: HashMap::new(),
+            constants: HashSet::new(),
+            outer: Some(outer),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Object> {
+        match self.store.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.outer.as_ref()?.borrow().get(name),
+        }
+    }
+
+    pub fn set(&mut self, name: String, value: Object) {
+        self.constants.remove(&name);
+        self.store.insert(name, value);
+    }
+
+    /// Binds `name` in this scope like `let` or `const` would. A constant
+    /// cannot be bound again in the same scope; this returns false without
+    /// binding it.
+    pub fn declare(&mut self, name: String, value: Object, kind: BindingKind) -> bool {
+        if self.constants.contains(&name) {
+            return false;
+        }
+        if kind == BindingKind::Const {
+            self.constants.insert(name.clone());
+        }
+        self.store.insert(name, value);
+        true
+    }
+
+    /// How the innermost binding of `name` was made, if there is one.
+    pub fn kind(&self, name: &str) -> Option<BindingKind> {
+        if self.constants.contains(name) {
+            Some(BindingKind::Const)
+        } else if self.store.contains_key(name) {
+            Some(BindingKind::Let)
+        } else {
+            self.outer.as_ref()?.borrow().kind(name)
+        }
+    }
+
+    /// Calls `f` with the innermost existing binding of `name`, so that it
+    /// can be changed in place. Returns `None` if `name` is not bound.
+    pub fn update<R>(&mut self, name: &str, f: impl FnOnce(&mut Object) -> R) -> Option<R> {
+        match self.store.get_mut(name) {
+            Some(value) => Some(f(value)),
+            None => self.outer.as_ref()?.borrow_mut().update(name, f),
+        }
+    }
+}
@@ -0,0 +1,2316 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use num_bigint::BigInt;
+
+use crate::ast::{
+    AssignExpression, BindingKind, BlockStatement, CallExpression, CatchClause,
+    DestructureStatement, Expression, ForStatement, FunctionLiteral, HashLiteral, Identifier,
+    IfExpression, IndexExpression, InfixExpression, LetStatement, MatchArm, MatchExpression,
+    Pattern, PrefixExpression, Program, Statement, ThrowStatement, TryExpression, WhileStatement,
+};
+use crate::environment::Environment;
+use crate::object::{range_end, ErrorObject, Function, HashKey, Object};
+use crate::parser::MAX_NESTING_DEPTH;
+use crate::token::Span;
+
+/// What stopped evaluation, so that embedders can tell a script's own
+/// failure from one of the `Limits` being hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Type errors, unknown identifiers, errors from builtins and the like.
+    Runtime,
+    StepLimit,
+    DepthLimit,
+    MemoryLimit,
+    /// The `CancellationToken` was cancelled or its timeout passed.
+    Cancelled,
+}
+
+/// An error raised while evaluating, with the span of the node that failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub span: Span,
+    /// The function calls the error unwound through, innermost first.
+    pub stack: Vec<Frame>,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, span: Span) -> RuntimeError {
+        RuntimeError::with_kind(ErrorKind::Runtime, message, span)
+    }
+
+    pub fn with_kind(kind: ErrorKind, message: String, span: Span) -> RuntimeError {
+        RuntimeError {
+            kind,
+            message,
+            span,
+            stack: vec![],
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// A call to a Monkey function that was running when an error was raised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// `None` for a function that was never given a name.
+    pub function: Option<String>,
+    /// The call expression that made the call.
+    pub span: Span,
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let function = self.function.as_deref().unwrap_or("<anonymous>");
+        write!(f, "in {} called at {}", function, self.span)
+    }
+}
+
+/// Why evaluation stopped before reaching the end of a block. `return`
+/// unwinds to the enclosing function call, `break` and `continue` to the
+/// enclosing loop, errors to the enclosing `try` or the caller of
+/// `eval_program`. Errors are boxed to keep `EvalResult`, and with it
+/// every recursive frame, small.
+enum Unwind {
+    Return(Object),
+    Break(Span),
+    Continue(Span),
+    Error(Box<RuntimeError>),
+    /// A `throw`, with the value thrown and the error it becomes if nothing
+    /// catches it.
+    Throw(Object, Box<RuntimeError>),
+}
+
+impl Unwind {
+    // For an unwind that reached the top of a function or program. The
+    // parser rejects `break` and `continue` outside of a loop, but an ast
+    // built by hand or read from JSON can still have them.
+    fn into_error(self) -> RuntimeError {
+        match self {
+            Unwind::Error(error) | Unwind::Throw(_, error) => *error,
+            Unwind::Break(span) => RuntimeError::new("break outside of a loop".to_string(), span),
+            Unwind::Continue(span) => {
+                RuntimeError::new("continue outside of a loop".to_string(), span)
+            }
+            Unwind::Return(_) => unreachable!("return is caught by the caller"),
+        }
+    }
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(error: RuntimeError) -> Unwind {
+        Unwind::Error(Box::new(error))
+    }
+}
+
+type EvalResult = Result<Object, Unwind>;
+// keys of an assignment target with the index expressions they came from
+type AssignPath<'a> = Vec<(&'a IndexExpression, Object)>;
+type Env = Rc<RefCell<Environment>>;
+
+/// Resource limits for a run of the evaluator. `None` means unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Statements and expressions evaluated.
+    pub max_steps: Option<u64>,
+    /// Function calls being evaluated inside one another. However deep they
+    /// go, the evaluator moves on to a new stack segment as the current one
+    /// fills up, so this only stops runaway recursion.
+    pub max_depth: Option<usize>,
+    /// An allocation budget: bytes allocated over the whole run for new
+    /// strings, arrays and hashes, and for copies of shared arrays and hashes
+    /// made to change them. Memory freed again is not given back to the
+    /// budget. String literals are free, and a builtin is charged only for
+    /// what its result adds to its largest argument.
+    pub max_memory: Option<usize>,
+    /// How deeply the source may nest, checked as it is parsed; see
+    /// `MAX_NESTING_DEPTH`.
+    pub max_nesting: Option<usize>,
+}
+
+impl Default for Limits {
+    /// No step or memory limit. The call depth is limited by default so that
+    /// runaway recursion is reported rather than using up memory.
+    fn default() -> Limits {
+        Limits {
+            max_steps: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_memory: None,
+            max_nesting: Some(MAX_NESTING_DEPTH),
+        }
+    }
+}
+
+/// A call whose body is a single `if` takes about 11 KiB of stack in a debug
+/// build and 6 KiB in a release build, so recursion this deep uses up to
+/// about 110 MiB.
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+
+/// Stops a running script from another thread. The evaluator checks the
+/// token before every function call and every loop iteration.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// A token that cancels itself once `timeout` has passed, measured from
+    /// now. It can still be cancelled early with `cancel`.
+    pub fn with_timeout(timeout: Duration) -> CancellationToken {
+        CancellationToken::new().timeout(timeout)
+    }
+
+    /// A clone of this token that is also cancelled once `timeout` has
+    /// passed. Cancelling either one cancels both.
+    pub fn timeout(&self, timeout: Duration) -> CancellationToken {
+        let deadline = Instant::now().checked_add(timeout);
+        CancellationToken {
+            cancelled: Arc::clone(&self.cancelled),
+            deadline: match (self.deadline, deadline) {
+                (Some(current), Some(deadline)) => Some(current.min(deadline)),
+                (current, deadline) => current.or(deadline),
+            },
+        }
+    }
+
+    /// Cancels this token and every clone of it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Tree-walking evaluator. Counters for `Limits` start at zero for each
+/// evaluator, so one `Evaluator` is one budget.
+pub struct Evaluator {
+    limits: Limits,
+    steps: u64,
+    depth: usize,
+    allocated: usize,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Evaluator {
+    pub fn new(limits: Limits) -> Evaluator {
+        Evaluator {
+            limits,
+            steps: 0,
+            depth: 0,
+            allocated: 0,
+            cancellation: None,
+        }
+    }
+
+    /// Makes evaluation fail with `ErrorKind::Cancelled` once `token` is
+    /// cancelled.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    /// Evaluates `program` in `env` and returns the value of its last statement.
+    pub fn eval_program(&mut self, program: &Program, env: &Env) -> Result<Object, RuntimeError> {
+        hoist(&program.statements, env).map_err(Unwind::into_error)?;
+        let mut result = Object::Null;
+        for statement in &program.statements {
+            result = match self.eval_statement(statement, env) {
+                Ok(value) => value,
+                Err(Unwind::Return(value)) => return Ok(value),
+                Err(unwind) => return Err(unwind.into_error()),
+            };
+        }
+        Ok(result)
+    }
+
+    fn step(&mut self, span: Span) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        match self.limits.max_steps {
+            Some(max) if self.steps > max => Err(RuntimeError::with_kind(
+                ErrorKind::StepLimit,
+                format!("step limit of {} exceeded", max),
+                span,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Counts a function call against `max_depth`. The caller takes it off
+    // again once the call returns.
+    fn enter(&mut self, span: Span) -> Result<(), RuntimeError> {
+        match self.limits.max_depth {
+            Some(max) if self.depth >= max => Err(RuntimeError::with_kind(
+                ErrorKind::DepthLimit,
+                format!("maximum call depth of {} exceeded", max),
+                span,
+            )),
+            _ => {
+                self.depth += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn check_cancelled(&self, span: Span) -> Result<(), RuntimeError> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(RuntimeError::with_kind(
+                ErrorKind::Cancelled,
+                "cancelled".to_string(),
+                span,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    // Charges the memory taken by a newly created `object`.
+    fn allocate(&mut self, object: Object, span: Span) -> EvalResult {
+        self.charge(allocation_size(&object), span)?;
+        Ok(object)
+    }
+
+    fn charge(&mut self, bytes: usize, span: Span) -> Result<(), RuntimeError> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        match self.limits.max_memory {
+            Some(max) if self.allocated > max => Err(RuntimeError::with_kind(
+                ErrorKind::MemoryLimit,
+                format!("memory limit of {} bytes exceeded", max),
+                span,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn eval_block_statement(&mut self, block: &BlockStatement, env: &Env) -> EvalResult {
+        hoist(&block.statements, env)?;
+        let mut result = Object::Null;
+        for statement in &block.statements {
+            result = self.eval_statement(statement, env)?;
+        }
+        Ok(result)
+    }
+
+    fn eval_statement(&mut self, statement: &Statement, env: &Env) -> EvalResult {
+        self.step(statement.span())?;
+        crate::with_stack(|| self.eval_statement_kind(statement, env))
+    }
+
+    fn eval_statement_kind(&mut self, statement: &Statement, env: &Env) -> EvalResult {
+        match statement {
+            Statement::LetStatement(let_statement) => self.eval_let_statement(let_statement, env),
+            Statement::DestructureStatement(destructure) => {
+                self.eval_destructure_statement(destructure, env)
+            }
+            Statement::ReturnStatement(return_statement) => {
+                let value = self.eval_expression(&return_statement.return_value, env)?;
+                Err(Unwind::Return(value))
+            }
+            Statement::ExpressionStatement(expression_statement) => {
+                self.eval_expression(&expression_statement.expression, env)
+            }
+            Statement::WhileStatement(while_statement) => {
+                self.eval_while_statement(while_statement, env)
+            }
+            Statement::BreakStatement(break_statement) => Err(Unwind::Break(break_statement.span)),
+            Statement::ContinueStatement(continue_statement) => {
+                Err(Unwind::Continue(continue_statement.span))
+            }
+            Statement::ForStatement(for_statement) => self.eval_for_statement(for_statement, env),
+            Statement::ThrowStatement(throw) => self.eval_throw_statement(throw, env),
+            // bound by `hoist` when the enclosing block started
+            Statement::FunctionStatement(_) => Ok(Object::Null),
+        }
+    }
+
+    fn eval_let_statement(&mut self, let_statement: &LetStatement, env: &Env) -> EvalResult {
+        let value = self.eval_expression(&let_statement.value, env)?;
+        declare(env, &let_statement.name, value, let_statement.kind)?;
+        Ok(Object::Null)
+    }
+
+    fn eval_destructure_statement(
+        &mut self,
+        destructure: &DestructureStatement,
+        env: &Env,
+    ) -> EvalResult {
+        let value = self.eval_expression(&destructure.value, env)?;
+        let mut bindings = vec![];
+        if let Some(error) = self.match_pattern(&destructure.pattern, &value, &mut bindings, env)? {
+            return Err(error.into());
+        }
+        for (name, value) in bindings {
+            declare(env, name, value, destructure.kind)?;
+        }
+        Ok(Object::Null)
+    }
+
+    // Throwing a caught error again keeps where it was first raised.
+    fn eval_throw_statement(&mut self, throw: &ThrowStatement, env: &Env) -> EvalResult {
+        let value = self.eval_expression(&throw.value, env)?;
+        let error = match &value {
+            Object::Error(error) => {
+                let mut rethrown = RuntimeError::new(error.message.clone(), error.span);
+                rethrown.stack = error.stack.clone();
+                return Err(Unwind::Throw(error.value.clone(), Box::new(rethrown)));
+            }
+            Object::String(message) => RuntimeError::new(message.clone(), throw.span),
+            other => RuntimeError::new(other.inspect(), throw.span),
+        };
+        Err(Unwind::Throw(value, Box::new(error)))
+    }
+
+    // Only errors of kind `ErrorKind::Runtime` are caught, so a script
+    // cannot outlive its limits. `finally` also runs after `return`, `break`
+    // and `continue`, and an unwind of its own replaces the pending one.
+    fn eval_try_expression(&mut self, try_expression: &TryExpression, env: &Env) -> EvalResult {
+        let result = match self.eval_block_statement(&try_expression.body, env) {
+            Err(Unwind::Throw(value, error)) => match &try_expression.catch {
+                Some(catch) => self.eval_catch(catch, value, *error, env),
+                None => Err(Unwind::Throw(value, error)),
+            },
+            Err(Unwind::Error(error)) if error.kind == ErrorKind::Runtime => {
+                match &try_expression.catch {
+                    Some(catch) => self.eval_catch(catch, Object::Null, *error, env),
+                    None => Err(Unwind::Error(error)),
+                }
+            }
+            result => result,
+        };
+        if let Some(finally) = &try_expression.finally {
+            let uncatchable =
+                matches!(&result, Err(Unwind::Error(error)) if error.kind != ErrorKind::Runtime);
+            if !uncatchable {
+                self.eval_block_statement(finally, env)?;
+            }
+        }
+        result
+    }
+
+    fn eval_catch(
+        &mut self,
+        catch: &CatchClause,
+        value: Object,
+        error: RuntimeError,
+        env: &Env,
+    ) -> EvalResult {
+        let error = Object::Error(Rc::new(ErrorObject {
+            message: error.message,
+            value,
+            span: error.span,
+            stack: error.stack,
+        }));
+        let mut scope = Environment::new_enclosed(Rc::clone(env));
+        scope.set(catch.parameter.value.clone(), error);
+        self.eval_block_statement(&catch.body, &Rc::new(RefCell::new(scope)))
+    }
+
+    fn eval_for_statement(&mut self, for_statement: &ForStatement, env: &Env) -> EvalResult {
+        let iterable = self.eval_expression(&for_statement.iterable, env)?;
+        let items = self.for_items(for_statement, &iterable)?;
+        for (key, value) in items {
+            if !self.eval_for_body(for_statement, env, key, value)? {
+                break;
+            }
+        }
+        Ok(Object::Null)
+    }
+
+    // The keys and values a `for` loop visits in `iterable`.
+    fn for_items<'a>(
+        &mut self,
+        for_statement: &ForStatement,
+        iterable: &'a Object,
+    ) -> Result<Box<dyn Iterator<Item = (Object, Object)> + 'a>, RuntimeError> {
+        let index = |i: usize| Object::Integer(i as i64);
+        Ok(match iterable {
+            Object::Array(elements) => {
+                let items = elements.iter().enumerate();
+                Box::new(items.map(move |(i, e)| (index(i), e.clone())))
+            }
+            Object::String(value) => {
+                // one string per character, the same bytes as `value` in all
+                self.charge(value.len(), for_statement.span)?;
+                let items = value.chars().enumerate();
+                Box::new(items.map(move |(i, c)| (index(i), Object::String(c.to_string()))))
+            }
+            &Object::Range {
+                start,
+                end,
+                inclusive: false,
+            } => {
+                let items = (start..end).enumerate();
+                Box::new(items.map(move |(i, n)| (index(i), Object::Integer(n))))
+            }
+            &Object::Range {
+                start,
+                end,
+                inclusive: true,
+            } => {
+                let items = (start..=end).enumerate();
+                Box::new(items.map(move |(i, n)| (index(i), Object::Integer(n))))
+            }
+            Object::Hash(pairs) => {
+                let with_key = for_statement.key.is_some();
+                Box::new(pairs.iter().map(move |(key, value)| match with_key {
+                    true => (Object::from(key.clone()), value.clone()),
+                    false => (Object::Null, Object::from(key.clone())),
+                }))
+            }
+            other => {
+                return Err(RuntimeError::new(
+                    format!("not iterable: {}", other.type_name()),
+                    for_statement.iterable.span(),
+                ))
+            }
+        })
+    }
+
+    // Runs one iteration in a scope of its own holding the loop variables,
+    // so closures made in the body each see their own. Returns false after
+    // a `break`.
+    fn eval_for_body(
+        &mut self,
+        for_statement: &ForStatement,
+        env: &Env,
+        key: Object,
+        value: Object,
+    ) -> Result<bool, Unwind> {
+        self.check_cancelled(for_statement.span)?;
+        let mut scope = Environment::new_enclosed(Rc::clone(env));
+        if let Some(name) = &for_statement.key {
+            scope.set(name.value.clone(), key);
+        }
+        scope.set(for_statement.value.value.clone(), value);
+        match self.eval_block_statement(&for_statement.body, &Rc::new(RefCell::new(scope))) {
+            Ok(_) | Err(Unwind::Continue(_)) => Ok(true),
+            Err(Unwind::Break(_)) => Ok(false),
+            Err(unwind) => Err(unwind),
+        }
+    }
+
+    // Like a `for` loop, each iteration runs in a scope of its own.
+    fn eval_while_statement(&mut self, while_statement: &WhileStatement, env: &Env) -> EvalResult {
+        loop {
+            self.check_cancelled(while_statement.span)?;
+            let condition = self.eval_expression(&while_statement.condition, env)?;
+            if !condition.is_truthy() {
+                break;
+            }
+            let scope = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(env))));
+            match self.eval_block_statement(&while_statement.body, &scope) {
+                Ok(_) | Err(Unwind::Continue(_)) => {}
+                Err(Unwind::Break(_)) => break,
+                Err(unwind) => return Err(unwind),
+            }
+        }
+        Ok(Object::Null)
+    }
+
+    fn eval_expression(&mut self, expression: &Expression, env: &Env) -> EvalResult {
+        self.step(expression.span())?;
+        crate::with_stack(|| self.eval_expression_kind(expression, env))
+    }
+
+    fn eval_expression_kind(&mut self, expression: &Expression, env: &Env) -> EvalResult {
+        match expression {
+            Expression::Identifier(identifier) => eval_identifier(identifier, env),
+            Expression::IntegerLiteral(integer_literal) => Ok(Object::from(&integer_literal.value)),
+            Expression::FloatLiteral(float_literal) => Ok(Object::Float(float_literal.value)),
+            Expression::Boolean(boolean) => Ok(Object::Boolean(boolean.value)),
+            Expression::PrefixExpression(prefix) => self.eval_prefix_expression(prefix, env),
+            Expression::InfixExpression(infix) => self.eval_infix_expression(infix, env),
+            Expression::IfExpression(if_expression) => self.eval_if_expression(if_expression, env),
+            Expression::FunctionLiteral(function) => Ok(make_function(function, env)),
+            Expression::CallExpression(call) => self.eval_call_expression(call, env),
+            // not charged, as the program already holds the text
+            Expression::StringLiteral(string_literal) => {
+                Ok(Object::String(string_literal.value.clone()))
+            }
+            Expression::ArrayLiteral(array) => {
+                let mut elements = Vec::with_capacity(array.elements.len());
+                for element in &array.elements {
+                    elements.push(self.eval_expression(element, env)?);
+                }
+                self.allocate(Object::Array(Rc::new(elements)), array.span)
+            }
+            Expression::HashLiteral(hash) => self.eval_hash_literal(hash, env),
+            Expression::IndexExpression(index) => self.eval_index_expression(index, env),
+            Expression::AssignExpression(assign) => self.eval_assign_expression(assign, env),
+            Expression::MatchExpression(match_expression) => {
+                self.eval_match_expression(match_expression, env)
+            }
+            Expression::TryExpression(try_expression) => {
+                self.eval_try_expression(try_expression, env)
+            }
+        }
+    }
+
+    fn eval_call_expression(&mut self, call: &CallExpression, env: &Env) -> EvalResult {
+        let function = self.eval_expression(&call.function, env)?;
+        let mut arguments = Vec::with_capacity(call.arguments.len());
+        for argument in &call.arguments {
+            arguments.push(self.eval_expression(argument, env)?);
+        }
+        self.apply_function(function, arguments, call.span)
+    }
+
+    fn eval_hash_literal(&mut self, hash: &HashLiteral, env: &Env) -> EvalResult {
+        let mut pairs = BTreeMap::new();
+        for (key, value) in &hash.pairs {
+            let key_object = self.eval_expression(key, env)?;
+            let hash_key = match key_object.hash_key() {
+                Some(hash_key) => hash_key,
+                None => {
+                    return Err(RuntimeError::new(
+                        format!("unusable as hash key: {}", key_object.type_name()),
+                        key.span(),
+                    )
+                    .into())
+                }
+            };
+            pairs.insert(hash_key, self.eval_expression(value, env)?);
+        }
+        self.allocate(Object::Hash(Rc::new(pairs)), hash.span)
+    }
+
+    fn eval_index_expression(&mut self, index: &IndexExpression, env: &Env) -> EvalResult {
+        let left = self.eval_expression(&index.left, env)?;
+        let key = self.eval_expression(&index.index, env)?;
+        self.index_object(index, left, key)
+    }
+
+    fn index_object(&mut self, index: &IndexExpression, left: Object, key: Object) -> EvalResult {
+        match (&left, &key) {
+            (Object::Array(elements), Object::Integer(i)) => {
+                let element = usize::try_from(*i).ok().and_then(|i| elements.get(i));
+                Ok(element.cloned().unwrap_or(Object::Null))
+            }
+            (Object::Array(_), Object::BigInteger(_)) => Ok(Object::Null),
+            (
+                Object::Array(elements),
+                &Object::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+            ) => {
+                let (start, end) = slice_bounds(start, range_end(end, inclusive), elements.len());
+                let slice = elements[start..end].to_vec();
+                self.allocate(Object::Array(Rc::new(slice)), index.span)
+            }
+            (
+                Object::String(value),
+                &Object::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+            ) => {
+                let len = value.chars().count();
+                let (start, end) = slice_bounds(start, range_end(end, inclusive), len);
+                let slice = value.chars().skip(start).take(end - start).collect();
+                self.allocate(Object::String(slice), index.span)
+            }
+            (Object::Error(error), Object::String(key)) => match key.as_str() {
+                "message" => self.allocate(Object::String(error.message.clone()), index.span),
+                "value" => Ok(error.value.clone()),
+                "stack" => {
+                    let frames = error.stack.iter().map(|frame| &frame.span);
+                    let spans = std::iter::once(&error.span).chain(frames);
+                    let stack = spans
+                        .map(|span| Object::Range {
+                            start: span.start as i64,
+                            end: span.end as i64,
+                            inclusive: false,
+                        })
+                        .collect();
+                    self.allocate(Object::Array(Rc::new(stack)), index.span)
+                }
+                _ => Ok(Object::Null),
+            },
+            (Object::Hash(pairs), _) => match key.hash_key() {
+                Some(hash_key) => Ok(pairs.get(&hash_key).cloned().unwrap_or(Object::Null)),
+                None => Err(RuntimeError::new(
+                    format!("unusable as hash key: {}", key.type_name()),
+                    index.index.span(),
+                )
+                .into()),
+            },
+            _ => Err(RuntimeError::new(
+                format!(
+                    "index operator not supported: {}[{}]",
+                    left.type_name(),
+                    key.type_name()
+                ),
+                index.span,
+            )
+            .into()),
+        }
+    }
+
+    // The target is resolved before the value is evaluated, and its index
+    // expressions are evaluated once, also for compound assignments.
+    fn eval_assign_expression(&mut self, assign: &AssignExpression, env: &Env) -> EvalResult {
+        let (name, path) = self.eval_assign_target(&assign.target, env)?;
+        check_assignable(name, env)?;
+        let value = match assign.operator.strip_suffix('=').filter(|o| !o.is_empty()) {
+            Some(operator) => self.eval_compound_assignment(assign, operator, name, &path, env)?,
+            None => self.eval_expression(&assign.value, env)?,
+        };
+        let copied = store(name, &path, value.clone(), env)?;
+        self.charge(copied, assign.span)?;
+        Ok(value)
+    }
+
+    // The new value for `target op= value`.
+    fn eval_compound_assignment(
+        &mut self,
+        assign: &AssignExpression,
+        operator: &str,
+        name: &Identifier,
+        path: &AssignPath,
+        env: &Env,
+    ) -> EvalResult {
+        let mut current = eval_identifier(name, env)?;
+        for (index, key) in path {
+            current = self.index_object(index, current, key.clone())?;
+        }
+        let value = self.eval_expression(&assign.value, env)?;
+        let result = eval_infix_operator(operator, assign.span, current, value)?;
+        self.allocate(result, assign.span)
+    }
+
+    // Splits a target like `a[i][j]` into the identifier `a` and the
+    // evaluated keys `i` and `j`, each with the index expression it came from.
+    fn eval_assign_target<'a>(
+        &mut self,
+        target: &'a Expression,
+        env: &Env,
+    ) -> Result<(&'a Identifier, AssignPath<'a>), Unwind> {
+        match target {
+            Expression::Identifier(identifier) => Ok((identifier, vec![])),
+            Expression::IndexExpression(index) => {
+                let (name, mut path) = self.eval_assign_target(&index.left, env)?;
+                let key = self.eval_expression(&index.index, env)?;
+                path.push((index, key));
+                Ok((name, path))
+            }
+            other => Err(RuntimeError::new(
+                format!("cannot assign to {}", other.string()),
+                other.span(),
+            )
+            .into()),
+        }
+    }
+
+    fn eval_prefix_expression(&mut self, prefix: &PrefixExpression, env: &Env) -> EvalResult {
+        let right = self.eval_expression(&prefix.right, env)?;
+        eval_prefix_operator(prefix, right)
+    }
+
+    fn eval_infix_expression(&mut self, infix: &InfixExpression, env: &Env) -> EvalResult {
+        let left = self.eval_expression(&infix.left, env)?;
+        // `&&` and `||` only evaluate their right side when it decides the
+        // result
+        match infix.operator.as_str() {
+            "&&" if !left.is_truthy() => return Ok(Object::Boolean(false)),
+            "||" if left.is_truthy() => return Ok(Object::Boolean(true)),
+            "&&" | "||" => {
+                let right = self.eval_expression(&infix.right, env)?;
+                return Ok(Object::Boolean(right.is_truthy()));
+            }
+            _ => {}
+        }
+        let right = self.eval_expression(&infix.right, env)?;
+        self.apply_infix_operator(infix, left, right)
+    }
+
+    fn apply_infix_operator(
+        &mut self,
+        infix: &InfixExpression,
+        left: Object,
+        right: Object,
+    ) -> EvalResult {
+        let result = eval_infix_operator(&infix.operator, infix.span, left, right)?;
+        self.allocate(result, infix.span)
+    }
+
+    fn eval_if_expression(&mut self, if_expression: &IfExpression, env: &Env) -> EvalResult {
+        let condition = self.eval_expression(&if_expression.condition, env)?;
+        if condition.is_truthy() {
+            self.eval_block_statement(&if_expression.consequence, env)
+        } else if let Some(alternative) = &if_expression.alternative {
+            self.eval_block_statement(alternative, env)
+        } else {
+            Ok(Object::Null)
+        }
+    }
+
+    fn eval_match_expression(
+        &mut self,
+        match_expression: &MatchExpression,
+        env: &Env,
+    ) -> EvalResult {
+        let subject = self.eval_expression(&match_expression.subject, env)?;
+        for arm in &match_expression.arms {
+            if let Some(scope) = self.match_arm(arm, &subject, env)? {
+                return self.eval_expression(&arm.body, &scope);
+            }
+        }
+        Err(RuntimeError::new(
+            format!("no match arm matches {}", subject.inspect()),
+            match_expression.span,
+        )
+        .into())
+    }
+
+    // The scope to evaluate the arm's body in, if its pattern and guard match.
+    fn match_arm(
+        &mut self,
+        arm: &MatchArm,
+        subject: &Object,
+        env: &Env,
+    ) -> Result<Option<Env>, Unwind> {
+        let mut bindings = vec![];
+        if self
+            .match_pattern(&arm.pattern, subject, &mut bindings, env)?
+            .is_some()
+        {
+            return Ok(None);
+        }
+        let mut scope = Environment::new_enclosed(Rc::clone(env));
+        for (name, value) in bindings {
+            scope.set(name.value.clone(), value);
+        }
+        let scope = Rc::new(RefCell::new(scope));
+        if let Some(guard) = &arm.guard {
+            if !self.eval_expression(guard, &scope)?.is_truthy() {
+                return Ok(None);
+            }
+        }
+        Ok(Some(scope))
+    }
+
+    // Matches `value` against `pattern`, collecting the names it binds in
+    // `bindings`. A mismatch comes back as the error a destructuring `let`
+    // reports, where `match` just tries its next arm.
+    fn match_pattern<'a>(
+        &mut self,
+        pattern: &'a Pattern,
+        value: &Object,
+        bindings: &mut Vec<(&'a Identifier, Object)>,
+        env: &Env,
+    ) -> Result<Option<RuntimeError>, Unwind> {
+        let mismatch = |message: String| Ok(Some(RuntimeError::new(message, pattern.span())));
+        match pattern {
+            Pattern::Literal(literal) => {
+                let expected = self.eval_expression(literal, env)?;
+                if equals(&expected, value) {
+                    return Ok(None);
+                }
+                mismatch(format!(
+                    "expected {}, got {}",
+                    expected.inspect(),
+                    value.inspect()
+                ))
+            }
+            Pattern::Wildcard(_) => Ok(None),
+            Pattern::Binding(name) => {
+                bindings.push((name, value.clone()));
+                Ok(None)
+            }
+            Pattern::Array(array) => {
+                let elements = match value {
+                    Object::Array(elements) => elements,
+                    _ => {
+                        return mismatch(format!(
+                            "cannot destructure {} with an array pattern",
+                            value.type_name()
+                        ))
+                    }
+                };
+                let len = array.elements.len();
+                if array.rest.is_none() && elements.len() != len {
+                    return mismatch(format!(
+                        "expected an array of length {}, got length {}",
+                        len,
+                        elements.len()
+                    ));
+                }
+                if elements.len() < len {
+                    return mismatch(format!(
+                        "expected an array of length at least {}, got length {}",
+                        len,
+                        elements.len()
+                    ));
+                }
+                for (pattern, element) in array.elements.iter().zip(elements.iter()) {
+                    if let Some(error) = self.match_pattern(pattern, element, bindings, env)? {
+                        return Ok(Some(error));
+                    }
+                }
+                if let Some(Pattern::Binding(name)) = array.rest.as_deref() {
+                    let rest = Object::Array(Rc::new(elements[len..].to_vec()));
+                    bindings.push((name, self.allocate(rest, name.span)?));
+                }
+                Ok(None)
+            }
+            Pattern::Hash(hash) => {
+                let pairs = match value {
+                    Object::Hash(pairs) => pairs,
+                    _ => {
+                        return mismatch(format!(
+                            "cannot destructure {} with a hash pattern",
+                            value.type_name()
+                        ))
+                    }
+                };
+                for (key, pattern) in &hash.pairs {
+                    let key_object = self.eval_expression(key, env)?;
+                    let hash_key = key_object.hash_key().ok_or_else(|| {
+                        RuntimeError::new(
+                            format!("unusable as hash key: {}", key_object.type_name()),
+                            key.span(),
+                        )
+                    })?;
+                    let element = match pairs.get(&hash_key) {
+                        Some(element) => element,
+                        None => {
+                            return Ok(Some(RuntimeError::new(
+                                format!("missing key {} in hash", key_object.inspect()),
+                                key.span(),
+                            )))
+                        }
+                    };
+                    if let Some(error) = self.match_pattern(pattern, element, bindings, env)? {
+                        return Ok(Some(error));
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    fn apply_function(
+        &mut self,
+        function: Object,
+        arguments: Vec<Object>,
+        span: Span,
+    ) -> EvalResult {
+        self.check_cancelled(span)?;
+        let function = match function {
+            Object::Function(function) => function,
+            other => return self.apply_builtin(other, arguments, span),
+        };
+        let env = bind_arguments(&function, arguments, span)?;
+        self.enter(span)?;
+        let result = self.eval_block_statement(&function.body, &env);
+        self.depth -= 1;
+        return_from(result, &function, span)
+    }
+
+    fn apply_builtin(
+        &mut self,
+        function: Object,
+        arguments: Vec<Object>,
+        span: Span,
+    ) -> EvalResult {
+        match function {
+            Object::Builtin(builtin) => {
+                // only what the result adds to its largest argument is
+                // charged, so `push` costs one element rather than a copy
+                let given = arguments.iter().map(allocation_size).max().unwrap_or(0);
+                let result = (builtin.function)(arguments)
+                    .map_err(|e| RuntimeError::new(format!("{}: {}", builtin.name, e), span))?;
+                self.charge(allocation_size(&result).saturating_sub(given), span)?;
+                Ok(result)
+            }
+            other => Err(
+                RuntimeError::new(format!("not a function: {}", other.type_name()), span).into(),
+            ),
+        }
+    }
+}
+
+// The checks and bookkeeping around a call are kept out of
+// `apply_function`, whose frame stays on the stack for as long as the call
+// runs.
+fn bind_arguments(
+    function: &Function,
+    arguments: Vec<Object>,
+    span: Span,
+) -> Result<Env, RuntimeError> {
+    if arguments.len() != function.parameters.len() {
+        return Err(RuntimeError::new(
+            format!(
+                "wrong number of arguments: expected {}, got {}",
+                function.parameters.len(),
+                arguments.len()
+            ),
+            span,
+        ));
+    }
+    let mut env = Environment::new_enclosed(Rc::clone(&function.env));
+    for (parameter, argument) in function.parameters.iter().zip(arguments) {
+        env.set(parameter.value.clone(), argument);
+    }
+    Ok(Rc::new(RefCell::new(env)))
+}
+
+fn return_from(result: EvalResult, function: &Function, span: Span) -> EvalResult {
+    match result {
+        Err(Unwind::Return(value)) => Ok(value),
+        Err(unwind @ (Unwind::Break(_) | Unwind::Continue(_))) => Err(unwind.into_error().into()),
+        Err(Unwind::Error(mut error)) => {
+            error.stack.push(Frame {
+                function: function.name.clone(),
+                span,
+            });
+            Err(Unwind::Error(error))
+        }
+        Err(Unwind::Throw(value, mut error)) => {
+            error.stack.push(Frame {
+                function: function.name.clone(),
+                span,
+            });
+            Err(Unwind::Throw(value, error))
+        }
+        result => result,
+    }
+}
+
+fn check_assignable(name: &Identifier, env: &Env) -> Result<(), RuntimeError> {
+    if env.borrow().kind(&name.value) == Some(BindingKind::Const) {
+        return Err(RuntimeError::new(
+            format!("cannot assign to constant {}", name.value),
+            name.span,
+        ));
+    }
+    Ok(())
+}
+
+// Returns the bytes copied from arrays and hashes that were shared.
+fn store(
+    name: &Identifier,
+    path: &AssignPath,
+    value: Object,
+    env: &Env,
+) -> Result<usize, RuntimeError> {
+    let assigned = env
+        .borrow_mut()
+        .update(&name.value, |target| assign_path(target, path, value));
+    assigned.unwrap_or_else(|| {
+        Err(RuntimeError::new(
+            format!("assignment to undeclared identifier: {}", name.value),
+            name.span,
+        ))
+    })
+}
+
+// Stores `value` at `path` inside `target`. Arrays and hashes are shared
+// until they are changed, so other references to them keep the old
+// contents; the bytes copied to change them are returned.
+fn assign_path(
+    target: &mut Object,
+    path: &[(&IndexExpression, Object)],
+    value: Object,
+) -> Result<usize, RuntimeError> {
+    let ((index, key), rest) = match path.split_first() {
+        Some(first) => first,
+        None => {
+            *target = value;
+            return Ok(0);
+        }
+    };
+    let copied = match target {
+        Object::Array(elements) if Rc::strong_count(elements) > 1 => allocation_size(target),
+        Object::Hash(pairs) if Rc::strong_count(pairs) > 1 => allocation_size(target),
+        _ => 0,
+    };
+    let unsupported = |target: &Object| {
+        RuntimeError::new(
+            format!(
+                "index assignment not supported: {}[{}]",
+                target.type_name(),
+                key.type_name()
+            ),
+            index.span,
+        )
+    };
+    let slot = match target {
+        Object::Array(elements) => {
+            let len = elements.len();
+            let i = match key {
+                Object::Integer(i) => usize::try_from(*i).ok().filter(|i| *i < len),
+                Object::BigInteger(_) => None,
+                _ => return Err(unsupported(target)),
+            };
+            match i {
+                Some(i) => &mut Rc::make_mut(elements)[i],
+                None => {
+                    return Err(RuntimeError::new(
+                        format!("index {} out of bounds for array of length {}", key, len),
+                        index.index.span(),
+                    ))
+                }
+            }
+        }
+        Object::Hash(pairs) => {
+            let hash_key = key.hash_key().ok_or_else(|| {
+                RuntimeError::new(
+                    format!("unusable as hash key: {}", key.type_name()),
+                    index.index.span(),
+                )
+            })?;
+            let pairs = Rc::make_mut(pairs);
+            if rest.is_empty() {
+                let added = match pairs.insert(hash_key, value) {
+                    Some(_) => 0,
+                    None => std::mem::size_of::<HashKey>() + std::mem::size_of::<Object>(),
+                };
+                return Ok(copied + added);
+            }
+            match pairs.get_mut(&hash_key) {
+                Some(slot) => slot,
+                // reports the missing entry as a null that cannot be indexed
+                None => return assign_path(&mut Object::Null, rest, value),
+            }
+        }
+        _ => return Err(unsupported(target)),
+    };
+    Ok(copied + assign_path(slot, rest, value)?)
+}
+
+fn eval_prefix_operator(prefix: &PrefixExpression, right: Object) -> EvalResult {
+    match (prefix.operator.as_str(), right) {
+        ("!", right) => Ok(Object::Boolean(!right.is_truthy())),
+        ("-", Object::Integer(value)) => match value.checked_neg() {
+            Some(value) => Ok(Object::Integer(value)),
+            None => Ok(Object::from(-BigInt::from(value))),
+        },
+        ("-", Object::BigInteger(value)) => Ok(Object::from(-BigInt::clone(&value))),
+        ("-", Object::Float(value)) => Ok(Object::Float(-value)),
+        ("~", Object::Integer(value)) => Ok(Object::Integer(!value)),
+        ("~", Object::BigInteger(value)) => Ok(Object::from(-BigInt::clone(&value) - 1)),
+        (operator, right) => Err(RuntimeError::new(
+            format!("unknown operator: {}{}", operator, right.type_name()),
+            prefix.span,
+        )
+        .into()),
+    }
+}
+
+fn make_function(function: &FunctionLiteral, env: &Env) -> Object {
+    Object::Function(Rc::new(Function {
+        name: function.name.clone(),
+        parameters: function.parameters.clone(),
+        body: function.body.clone(),
+        env: Rc::clone(env),
+    }))
+}
+
+// Binds the functions declared directly in `statements` before any of them
+// runs, so that a function can be called before its declaration and
+// declarations in the same block can call each other. A declaration nested
+// in an `if` or loop body is bound when that body starts running.
+fn hoist(statements: &[Statement], env: &Env) -> Result<(), Unwind> {
+    for statement in statements {
+        if let Statement::FunctionStatement(declaration) = statement {
+            let function = make_function(&declaration.function, env);
+            declare(env, &declaration.name, function, BindingKind::Let)?;
+        }
+    }
+    Ok(())
+}
+
+fn declare(env: &Env, name: &Identifier, value: Object, kind: BindingKind) -> Result<(), Unwind> {
+    if !env.borrow_mut().declare(name.value.clone(), value, kind) {
+        return Err(RuntimeError::new(
+            format!("cannot redeclare constant {}", name.value),
+            name.span,
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn eval_infix_operator(operator: &str, span: Span, left: Object, right: Object) -> EvalResult {
+    let error = |message: String| Err(RuntimeError::new(message, span).into());
+
+    if let ".." | "..=" = operator {
+        return eval_range(operator, span, &left, &right);
+    }
+    match (&left, &right) {
+        (Object::Integer(left), Object::Integer(right)) => {
+            let value = match operator {
+                "+" => left.checked_add(*right),
+                "-" => left.checked_sub(*right),
+                "*" => left.checked_mul(*right),
+                "/" if *right == 0 => return error("division by zero".to_string()),
+                "/" => left.checked_div(*right),
+                "%" if *right == 0 => return error("division by zero".to_string()),
+                "%" => left.checked_rem(*right),
+                "<" => return Ok(Object::Boolean(left < right)),
+                ">" => return Ok(Object::Boolean(left > right)),
+                "<=" => return Ok(Object::Boolean(left <= right)),
+                ">=" => return Ok(Object::Boolean(left >= right)),
+                "==" => return Ok(Object::Boolean(left == right)),
+                "!=" => return Ok(Object::Boolean(left != right)),
+                "&" => Some(left & right),
+                "|" => Some(left | right),
+                "^" => Some(left ^ right),
+                // shifts can grow past `i64`, so they are always done on big
+                // integers
+                "<<" | ">>" => None,
+                _ => return error(format!("unknown operator: INTEGER {} INTEGER", operator)),
+            };
+            match value {
+                Some(value) => Ok(Object::Integer(value)),
+                None => eval_big_integer_infix(
+                    operator,
+                    span,
+                    &BigInt::from(*left),
+                    &BigInt::from(*right),
+                ),
+            }
+        }
+        (
+            Object::Integer(_) | Object::BigInteger(_),
+            Object::Integer(_) | Object::BigInteger(_),
+        ) => eval_big_integer_infix(operator, span, &big_integer(&left), &big_integer(&right)),
+        (
+            Object::Integer(_) | Object::BigInteger(_) | Object::Float(_),
+            Object::Integer(_) | Object::BigInteger(_) | Object::Float(_),
+        ) => eval_float_infix(operator, span, &left, &right),
+        (Object::String(left), Object::String(right)) if operator == "+" => {
+            Ok(Object::String(format!("{}{}", left, right)))
+        }
+        _ if left.type_name() != right.type_name() => error(format!(
+            "type mismatch: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        )),
+        _ => match operator {
+            "==" => Ok(Object::Boolean(equals(&left, &right))),
+            "!=" => Ok(Object::Boolean(!equals(&left, &right))),
+            _ => error(format!(
+                "unknown operator: {} {} {}",
+                left.type_name(),
+                operator,
+                right.type_name()
+            )),
+        },
+    }
+}
+
+// Whether `==` holds for two values. Numbers are compared by value whatever
+// their type, also inside arrays and hashes.
+fn equals(left: &Object, right: &Object) -> bool {
+    match (left, right) {
+        (Object::Float(_), Object::Integer(_) | Object::BigInteger(_) | Object::Float(_))
+        | (Object::Integer(_) | Object::BigInteger(_), Object::Float(_)) => {
+            left.to_f64().is_some() && left.to_f64() == right.to_f64()
+        }
+        (Object::Array(left), Object::Array(right)) => {
+            left.len() == right.len() && left.iter().zip(right.iter()).all(|(a, b)| equals(a, b))
+        }
+        (Object::Hash(left), Object::Hash(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .all(|(key, a)| right.get(key).is_some_and(|b| equals(a, b)))
+        }
+        _ => left == right,
+    }
+}
+
+// Largest left shift, so that a script cannot ask for a number that takes
+// minutes to compute.
+const MAX_SHIFT: u64 = 1 << 20;
+
+// `start..end` or `start..=end`, whose bounds must fit in an `i64`.
+fn eval_range(operator: &str, span: Span, left: &Object, right: &Object) -> EvalResult {
+    let bound = |object: &Object| match object {
+        Object::Integer(value) => Ok(*value),
+        Object::BigInteger(value) => Err(format!("range bound {} out of range", value)),
+        _ => Err(format!(
+            "unknown operator: {} {} {}",
+            left.type_name(),
+            operator,
+            right.type_name()
+        )),
+    };
+    let range = bound(left).and_then(|start| {
+        Ok(Object::Range {
+            start,
+            end: bound(right)?,
+            inclusive: operator == "..=",
+        })
+    });
+    range.map_err(|message| RuntimeError::new(message, span).into())
+}
+
+// Clamps a range to the indices of a sequence of `len` elements, so a slice
+// that runs past either end is cut short, like an index out of bounds gives
+// null instead of an error.
+fn slice_bounds(start: i64, end: i128, len: usize) -> (usize, usize) {
+    let clamp = |i: i128| usize::try_from(i.max(0)).unwrap_or(usize::MAX).min(len);
+    let start = clamp(start.into());
+    (start, clamp(end).max(start))
+}
+
+// Integer arithmetic that overflowed `i64`, or has an operand that already
+// did.
+fn eval_big_integer_infix(operator: &str, span: Span, left: &BigInt, right: &BigInt) -> EvalResult {
+    let zero = BigInt::default();
+    let value = match operator {
+        "+" => left + right,
+        "-" => left - right,
+        "*" => left * right,
+        "/" | "%" if *right == zero => {
+            return Err(RuntimeError::new("division by zero".to_string(), span).into())
+        }
+        "/" => left / right,
+        "%" => left % right,
+        "<" => return Ok(Object::Boolean(left < right)),
+        ">" => return Ok(Object::Boolean(left > right)),
+        "<=" => return Ok(Object::Boolean(left <= right)),
+        ">=" => return Ok(Object::Boolean(left >= right)),
+        "==" => return Ok(Object::Boolean(left == right)),
+        "!=" => return Ok(Object::Boolean(left != right)),
+        "&" => left & right,
+        "|" => left | right,
+        "^" => left ^ right,
+        "<<" | ">>" if *right < zero => {
+            return Err(RuntimeError::new(format!("negative shift count {}", right), span).into())
+        }
+        // bits shifted out to the right are gone whatever the count
+        ">>" => left >> u64::try_from(right).unwrap_or(u64::MAX),
+        "<<" => match u64::try_from(right) {
+            Ok(count) if count <= MAX_SHIFT => left << count,
+            _ => {
+                return Err(
+                    RuntimeError::new(format!("shift count {} too large", right), span).into(),
+                )
+            }
+        },
+        operator => {
+            return Err(RuntimeError::new(
+                format!("unknown operator: INTEGER {} INTEGER", operator),
+                span,
+            )
+            .into())
+        }
+    };
+    Ok(Object::from(value))
+}
+
+// Arithmetic on floats, with integer operands converted to floats.
+fn eval_float_infix(operator: &str, span: Span, left: &Object, right: &Object) -> EvalResult {
+    let unknown = format!(
+        "unknown operator: {} {} {}",
+        left.type_name(),
+        operator,
+        right.type_name()
+    );
+    let (left, right) = match (left.to_f64(), right.to_f64()) {
+        (Some(left), Some(right)) => (left, right),
+        _ => return Err(RuntimeError::new("integer too large for FLOAT".to_string(), span).into()),
+    };
+    let value = match operator {
+        "+" => left + right,
+        "-" => left - right,
+        "*" => left * right,
+        "/" | "%" if right == 0.0 => {
+            return Err(RuntimeError::new("division by zero".to_string(), span).into())
+        }
+        "/" => left / right,
+        "%" => left % right,
+        "<" => return Ok(Object::Boolean(left < right)),
+        ">" => return Ok(Object::Boolean(left > right)),
+        "<=" => return Ok(Object::Boolean(left <= right)),
+        ">=" => return Ok(Object::Boolean(left >= right)),
+        "==" => return Ok(Object::Boolean(left == right)),
+        "!=" => return Ok(Object::Boolean(left != right)),
+        _ => return Err(RuntimeError::new(unknown, span).into()),
+    };
+    Ok(Object::Float(value))
+}
+
+fn big_integer(object: &Object) -> BigInt {
+    match object {
+        Object::Integer(value) => BigInt::from(*value),
+        Object::BigInteger(value) => BigInt::clone(value),
+        _ => unreachable!("not an integer: {}", object.type_name()),
+    }
+}
+
+fn eval_identifier(identifier: &Identifier, env: &Env) -> EvalResult {
+    match env.borrow().get(&identifier.value) {
+        Some(value) => Ok(value),
+        None => Err(RuntimeError::new(
+            format!("identifier not found: {}", identifier.value),
+            identifier.span,
+        )
+        .into()),
+    }
+}
+
+// Approximate heap size of an object, not counting what it shares with
+// other objects.
+fn allocation_size(object: &Object) -> usize {
+    match object {
+        Object::String(value) => value.len(),
+        Object::BigInteger(value) => value.bits().div_ceil(8) as usize,
+        Object::Array(elements) => elements.len() * std::mem::size_of::<Object>(),
+        Object::Hash(pairs) => {
+            pairs.len() * (std::mem::size_of::<HashKey>() + std::mem::size_of::<Object>())
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+
+    fn eval(input: &str) -> Result<Object, RuntimeError> {
+        eval_with_limits(input, Limits::default())
+    }
+
+    fn eval_with_limits(input: &str, limits: Limits) -> Result<Object, RuntimeError> {
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(e) => panic!("parse_program() returned an error: {}", e),
+        };
+        let env = Rc::new(RefCell::new(Environment::new()));
+        Evaluator::new(limits).eval_program(&program, &env)
+    }
+
+    #[test]
+    fn test_eval_integer_expression() {
+        let tests = vec![
+            ("5", 5),
+            ("-10", -10),
+            ("5 + 5 + 5 + 5 - 10", 10),
+            ("2 * 2 * 2 * 2 * 2", 32),
+            ("-50 + 100 + -50", 0),
+            ("20 + 2 * -10", 0),
+            ("2 * (5 + 10)", 30),
+            ("(5 + 10 * 2 + 15 / 3) * 2 + -10", 50),
+            ("7 % 3", 1),
+            ("-7 % 3", -1),
+            ("1 + 10 % 4 * 2", 5),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input), Ok(Object::Integer(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_eval_boolean_expression() {
+        let tests = vec![
+            ("true", true),
+            ("1 < 2", true),
+            ("1 > 2", false),
+            ("1 == 1", true),
+            ("1 != 1", false),
+            ("true == true", true),
+            ("true != false", true),
+            ("(1 < 2) == true", true),
+            ("!true", false),
+            ("!!5", true),
+            ("1 <= 1", true),
+            ("2 <= 1", false),
+            ("1 >= 2", false),
+            ("2.5 >= 2", true),
+            ("99999999999999999999 >= 99999999999999999999", true),
+            ("true && 1", true),
+            ("true && false", false),
+            ("false || if (false) { 1 }", false),
+            ("0 || false", true),
+            ("1 < 2 && 2 < 3", true),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input), Ok(Object::Boolean(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_if_else_expressions() {
+        let tests = vec![
+            ("if (true) { 10 }", Object::Integer(10)),
+            ("if (false) { 10 }", Object::Null),
+            ("if (1) { 10 }", Object::Integer(10)),
+            ("if (1 > 2) { 10 } else { 20 }", Object::Integer(20)),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input), Ok(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_return_statements() {
+        let tests = vec![
+            ("return 10; 9;", 10),
+            ("9; return 2 * 5; 9;", 10),
+            ("if (10 > 1) { if (10 > 1) { return 10; } return 1; }", 10),
+            ("let f = fn() { if (true) { return 1; } 2 }; f() + 1", 2),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input), Ok(Object::Integer(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_while_loops() {
+        let tests = vec![
+            ("let i = 0; while (i < 5) { i = i + 1; } i", 5),
+            (
+                "let i = 0; let sum = 0; while (true) { i = i + 1; \
+                 if (i > 10) { break; } if (i % 2 == 0) { continue; } sum = sum + i; } sum",
+                25,
+            ),
+            (
+                "let n = 0; let i = 0; while (i < 3) { i = i + 1; let j = 0; \
+                 while (true) { j = j + 1; if (j > i) { break; } n = n + 1; } } n",
+                6,
+            ),
+            ("let f = fn() { while (true) { return 7; } }; f()", 7),
+            // each iteration has a scope of its own
+            (
+                "let i = 0; let fs = [0, 0]; \
+                 while (i < 2) { const j = i; fs[i] = fn() { j * 10 }; i += 1 } fs[0]() + fs[1]()",
+                10,
+            ),
+            (
+                "let x = 1; let i = 0; while (i < 2) { let x = 5; i += 1 } x",
+                1,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input), Ok(Object::Integer(expected)), "{}", input);
+        }
+        assert_eq!(eval("while (false) { 1 }"), Ok(Object::Null));
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let tests = vec![
+            (
+                "let f = fn(xs) { for (x in xs) { if (x > 2) { return x; } } }; f([1, 5, 3])",
+                "5",
+            ),
+            (
+                "let f = fn(xs) { for (i, x in xs) { if (x == 3) { return i; } } }; f([1, 5, 3])",
+                "2",
+            ),
+            (
+                r#"let f = fn(s) { for (i, c in s) { if (i == 1) { return c; } } }; f("héllo")"#,
+                "é",
+            ),
+            (
+                r#"let f = fn(h) { for (k in h) { return k; } }; f({"b": 1, "a": 2})"#,
+                "a",
+            ),
+            (
+                r#"let f = fn(h) { for (k, v in h) { if (k == "b") { return v; } } }; f({"b": 1, "a": 2})"#,
+                "1",
+            ),
+            (
+                "let f = fn() { for (x in [1, 2, 3]) { if (x < 3) { continue; } return x; } }; f()",
+                "3",
+            ),
+            (
+                "let f = fn() { for (x in [1, 2, 3]) { if (x == 2) { break; } } 9 }; f()",
+                "9",
+            ),
+            (
+                "let fs = fn(xs) { for (x in xs) { return fn() { x } } }; fs([7, 8])()",
+                "7",
+            ),
+            ("for (x in []) { x }", "null"),
+            ("let x = 1; for (x in [2]) { let y = x; } x", "1"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let error = eval("for (x in [1]) { x } x").unwrap_err();
+        assert_eq!(error.to_string(), "identifier not found: x at 21..22");
+        let error = eval("for (x in 5) { x }").unwrap_err();
+        assert_eq!(error.to_string(), "not iterable: INTEGER at 10..11");
+    }
+
+    #[test]
+    fn test_assignment() {
+        let tests =
+            vec![
+            ("let x = 1; x = 2; x", "2"),
+            ("let x = 1; x = 2", "2"),
+            ("let a = 1; let b = 1; a = b = 3; a + b", "6"),
+            ("let x = 10; x += 5; x -= 3; x *= 2; x /= 4; x", "6"),
+            (r#"let s = "a"; s += "b"; s"#, "ab"),
+            ("let x = 1; let f = fn() { x = 5; }; f(); x", "5"),
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c()",
+                "2",
+            ),
+            ("let a = [1, 2, 3]; a[1] = 9; a", "[1, 9, 3]"),
+            ("let a = [1, 2]; let b = a; b[0] = 5; [a, b]", "[[1, 2], [5, 2]]"),
+            ("let a = [[1, 2], [3]]; a[0][1] += 10; a", "[[1, 12], [3]]"),
+            (r#"let h = {}; h["k"] = 1; h["k"] += 1; h"#, r#"{"k": 2}"#),
+            (r#"let h = {"a": {}}; h["a"]["b"] = [1]; h"#, r#"{"a": {"b": [1]}}"#),
+            (
+                "let sum = 0; for (x in 1..=4) { sum += x; } sum",
+                "10",
+            ),
+            (
+                "let i = 0; let a = [0, 0, 0]; while (i < 3) { a[i] = i * i; i += 1; } a",
+                "[0, 1, 4]",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            ("x = 1", "assignment to undeclared identifier: x at 0..1"),
+            ("x += 1", "identifier not found: x at 0..1"),
+            (
+                "let f = fn() { y = 1 }; f()",
+                "assignment to undeclared identifier: y at 15..16",
+            ),
+            (
+                "let a = [1]; a[1] = 2",
+                "index 1 out of bounds for array of length 1 at 15..16",
+            ),
+            (
+                r#"let h = {}; h["a"]["b"] = 1"#,
+                "index assignment not supported: NULL[STRING] at 12..23",
+            ),
+            (
+                "let x = 1; x[0] = 2",
+                "index assignment not supported: INTEGER[INTEGER] at 11..15",
+            ),
+            (
+                "let h = {}; h[[]] = 1",
+                "unusable as hash key: ARRAY at 14..16",
+            ),
+            (
+                "let x = true; x += 1",
+                "type mismatch: BOOLEAN + INTEGER at 14..20",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(eval(input).unwrap_err().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_constants() {
+        let tests = vec![
+            ("const x = 1; x", "1"),
+            (
+                "const x = 1; let f = fn() { let x = 2; x = 3; x }; f() + x",
+                "4",
+            ),
+            ("let n = 0; while (n < 2) { const x = n; n += 1; } n", "2"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        // these pass the parser's check, which cannot know which branch runs
+        // or what a function refers to when it is defined
+        let errors = vec![
+            (
+                "let x = 1; if (true) { const x = 2; } x = 3;",
+                "cannot assign to constant x at 38..39",
+            ),
+            (
+                "let f = fn() { x = 1 }; const x = 2; f()",
+                "cannot assign to constant x at 15..16",
+            ),
+            (
+                "if (true) { const x = 1; } let x = 2;",
+                "cannot redeclare constant x at 31..32",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(eval(input).unwrap_err().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        let tests = vec![
+            (
+                r#"match (2) { 1 => "one", 2 => "two", _ => "many" }"#,
+                "two",
+            ),
+            (
+                r#"let f = fn(x) { match (x) { 0 => "zero", n if n < 0 => "negative", _ => "positive" } };
+                [f(0), f(-3), f(5)]"#,
+                r#"["zero", "negative", "positive"]"#,
+            ),
+            ("match ([1, [2, 3]]) { [a, [b, c]] => a + b + c }", "6"),
+            (
+                "match ([1, 2]) { [a] => a, [a, b, c] => c, [a, b] => b }",
+                "2",
+            ),
+            (
+                r#"match ({"name": "ann", "age": 3}) { {"age": 4} => 0, {name, "age": a} => [name, a] }"#,
+                r#"["ann", 3]"#,
+            ),
+            (r#"match (1.0) { 1 => "int", _ => "other" }"#, "int"),
+            (r#"match (2) { 1.0 => "one", 2.0 => "two" }"#, "two"),
+            (
+                r#"match ([1.0, 2]) { [1, 2.0] => "same", _ => "other" }"#,
+                "same",
+            ),
+            ("match (-1) { -1 => true }", "true"),
+            ("let x = 5; match (1) { x => x }; x", "5"),
+            (r#"match ("a") { {a} => 1, "a" => 2 }"#, "2"),
+            (
+                "match ([1, 2, 3]) { [x] => x, [x, ...rest] => rest }",
+                "[2, 3]",
+            ),
+            (
+                r#"match ([3, 1]) { [a, b] if a < b => "ascending", [a, b] => "descending" }"#,
+                "descending",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            (
+                "match (3) { 1 => 1, 2 => 2 }",
+                "no match arm matches 3 at 0..28",
+            ),
+            (r#"match ("s") {}"#, r#"no match arm matches "s" at 0..14"#),
+            (
+                "match ({}) { {1.5: x} => x }",
+                "unusable as hash key: FLOAT at 14..17",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(eval(input).unwrap_err().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_destructuring() {
+        let tests = vec![
+            (
+                "let [a, b, ...rest] = [1, 2, 3, 4]; [a, b, rest]",
+                "[1, 2, [3, 4]]",
+            ),
+            ("let [a, ...rest] = [1]; rest", "[]"),
+            (
+                r#"let {name, age} = {"name": "ann", "age": 3, "x": 0}; [name, age]"#,
+                r#"["ann", 3]"#,
+            ),
+            (r#"let {"pos": [x, y]} = {"pos": [1, 2]}; x + y"#, "3"),
+            ("let [_, [b]] = [1, [2]]; b", "2"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            (
+                "let [a, b] = [1];",
+                "expected an array of length 2, got length 1 at 4..10",
+            ),
+            (
+                "let [a, ...r] = [];",
+                "expected an array of length at least 1, got length 0 at 4..13",
+            ),
+            (
+                "let [a] = 1;",
+                "cannot destructure INTEGER with an array pattern at 4..7",
+            ),
+            (
+                "let {a} = [1];",
+                "cannot destructure ARRAY with a hash pattern at 4..7",
+            ),
+            (
+                r#"let {name} = {};"#,
+                r#"missing key "name" in hash at 5..9"#,
+            ),
+            ("let [1, x] = [2, 3];", "expected 1, got 2 at 5..6"),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(eval(input).unwrap_err().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_try_catch() {
+        let tests = vec![
+            (
+                r#"try { 1 + true } catch (e) { [e["message"], e["value"]] }"#,
+                r#"["type mismatch: INTEGER + BOOLEAN", null]"#,
+            ),
+            (
+                r#"try { throw "boom"; 1 } catch (e) { [e["message"], e["value"]] }"#,
+                r#"["boom", "boom"]"#,
+            ),
+            (
+                r#"try { throw {"code": 2} } catch (e) { e["value"]["code"] }"#,
+                "2",
+            ),
+            (
+                r#"try { throw 1 } catch (e) { [e, e["stack"]] }"#,
+                "[error: 1, [6..13]]",
+            ),
+            (
+                r#"let f = fn() { throw "x" }; try { f() } catch (e) { e["stack"] }"#,
+                "[15..24, 34..37]",
+            ),
+            ("let x = try { 1 } catch (e) { 2 }; x", "1"),
+            (
+                r#"let log = ""; try { log += "a" } finally { log += "b" }; log"#,
+                "ab",
+            ),
+            (
+                r#"let log = ""; try { throw 1 } catch (e) { log += "b" } finally { log += "c" }; log"#,
+                "bc",
+            ),
+            (
+                r#"let log = ""; let f = fn() { try { return 1 } finally { log += "b" } }; [f(), log]"#,
+                r#"[1, "b"]"#,
+            ),
+            (
+                r#"try { try { throw "a" } catch (e) { throw e } } catch (e) { e["stack"] }"#,
+                "[12..21]",
+            ),
+            (r#"let e = 1; try { throw 2 } catch (e) {}; e"#, "1"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            ("throw 1;", "1 at 0..8"),
+            (r#"try { throw "a" } finally { 1 }"#, "a at 6..15"),
+            (
+                r#"try { throw "a" } catch (e) { throw "b" }"#,
+                "b at 30..39",
+            ),
+            (r#"try { 1 } finally { throw "c" }"#, "c at 20..29"),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(eval(input).unwrap_err().to_string(), expected, "{}", input);
+        }
+
+        let limits = Limits {
+            max_steps: Some(20),
+            ..Limits::default()
+        };
+        let input = "let x = 0; try { while (true) {} } catch (e) { x = 1 } finally { x = 2 }";
+        let error = eval_with_limits(input, limits).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::StepLimit);
+    }
+
+    #[test]
+    fn test_stack_trace() {
+        let input = "let f = fn(x) { x + true }; let g = fn() { f(1) }; g();";
+        let error = eval(input).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "type mismatch: INTEGER + BOOLEAN at 16..24"
+        );
+        assert_eq!(
+            error.stack,
+            vec![
+                Frame {
+                    function: Some("f".to_string()),
+                    span: Span::new(43, 47),
+                },
+                Frame {
+                    function: Some("g".to_string()),
+                    span: Span::new(51, 54),
+                },
+            ]
+        );
+        assert_eq!(error.stack[0].to_string(), "in f called at 43..47");
+
+        let anonymous = eval("fn(x) { x + true }(1)").unwrap_err();
+        assert_eq!(
+            anonymous.stack[0].to_string(),
+            "in <anonymous> called at 0..21"
+        );
+
+        let rethrown = "let f = fn() { throw 1 }; try { f() } catch (e) { throw e }";
+        let error = eval(rethrown).unwrap_err();
+        assert_eq!(error.span, Span::new(15, 22));
+        assert_eq!(error.stack.len(), 1);
+
+        assert_eq!(eval("1 + true").unwrap_err().stack, vec![]);
+    }
+
+    #[test]
+    fn test_function_declarations() {
+        let tests = vec![
+            ("fn add(a, b) { a + b } add(1, 2)", "3"),
+            ("let x = double(4); fn double(n) { n * 2 }; x", "8"),
+            (
+                "fn even(n) { if (n == 0) { true } else { odd(n - 1) } } \
+                 fn odd(n) { if (n == 0) { false } else { even(n - 1) } } \
+                 [even(10), odd(7)]",
+                "[true, true]",
+            ),
+            ("fn f() { g() } fn g() { 2 } f()", "2"),
+            ("fn f() { 1 } fn f() { 2 } f()", "2"),
+            ("fn f() { 1 } f = 3; f", "3"),
+            (
+                "let f = fn() { inner() }; if (true) { fn inner() { 1 } }; f()",
+                "1",
+            ),
+            ("fn f() {}", "null"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let error = eval("fn fail() { 1 + true } fail()").unwrap_err();
+        assert_eq!(error.stack[0].to_string(), "in fail called at 23..29");
+    }
+
+    #[test]
+    fn test_ranges() {
+        let tests = vec![
+            ("1..4", "1..4"),
+            ("1..=4", "1..=4"),
+            ("0..=9223372036854775807", "0..=9223372036854775807"),
+            ("let n = 3; 0..n * 2", "0..6"),
+            ("1..3 == 1..=2", "true"),
+            ("[1, 2, 3, 4][1..3]", "[2, 3]"),
+            ("[1, 2, 3, 4][2..=9]", "[3, 4]"),
+            ("[1, 2, 3][-5..1]", "[1]"),
+            ("[1, 2, 3][2..1]", "[]"),
+            (r#""héllo"[1..=3]"#, "éll"),
+            (
+                "let f = fn() { for (i, x in 10..20) { if (x * x > 200) { return [i, x]; } } }; f()",
+                "[5, 15]",
+            ),
+            (
+                "let f = fn() { for (x in 0..9223372036854775807) { if (x == 3) { return x; } } }; f()",
+                "3",
+            ),
+            ("for (x in 3..1) { x }", "null"),
+            (
+                "let n = 0; for (x in 9223372036854775806..=9223372036854775807) { n += x - 9223372036854775805 }; n",
+                "3",
+            ),
+            ("[1, 2, 3][1..=9223372036854775807]", "[2, 3]"),
+            ("for (x in 3..=2) { x }", "null"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+
+        let errors = vec![
+            ("1.5..2", "unknown operator: FLOAT .. INTEGER at 0..6"),
+            (
+                "0..9223372036854775808",
+                "range bound 9223372036854775808 out of range at 0..22",
+            ),
+        ];
+        for (input, expected) in errors {
+            assert_eq!(eval(input).unwrap_err().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_functions_and_closures() {
+        let tests = vec![
+            ("let identity = fn(x) { x; }; identity(5);", 5),
+            ("let add = fn(x, y) { x + y; }; add(5 + 5, add(5, 5));", 20),
+            ("fn(x) { x; }(5)", 5),
+            (
+                "let newAdder = fn(x) { fn(y) { x + y } }; let addTwo = newAdder(2); addTwo(3);",
+                5,
+            ),
+            (
+                "let fact = fn(n) { if (n < 2) { 1 } else { n * fact(n - 1) } }; fact(10)",
+                3628800,
+            ),
+            (
+                "let count = fn(n) { if (n == 0) { 0 } else { 1 + count(n - 1) } }; count(5000)",
+                5000,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input), Ok(Object::Integer(expected)), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_strings() {
+        let tests = vec![
+            (r#""Hello World!""#, "Hello World!"),
+            (r#""Hello" + " " + "World!""#, "Hello World!"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(
+                eval(input),
+                Ok(Object::String(expected.to_string())),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(eval(r#""a" == "a""#), Ok(Object::Boolean(true)));
+        assert_eq!(eval(r#""a" != "b""#), Ok(Object::Boolean(true)));
+    }
+
+    #[test]
+    fn test_arrays_and_hashes() {
+        let tests = vec![
+            ("[1, 2 * 2, 3 + 3]", "[1, 4, 6]"),
+            ("[1, 2, 3][0]", "1"),
+            ("let i = 0; [1][i]", "1"),
+            ("[1, 2, 3][1 + 1]", "3"),
+            (
+                "let myArray = [1, 2, 3]; myArray[0] + myArray[1] + myArray[2]",
+                "6",
+            ),
+            ("[1, 2, 3][3]", "null"),
+            ("[1, 2, 3][-1]", "null"),
+            (r#"["a", [true]]"#, r#"["a", [true]]"#),
+            (
+                r#"let two = "two"; {"one": 10 - 9, two: 1 + 1, "thr" + "ee": 6 / 2, 4: 4, true: 5}"#,
+                r#"{4: 4, true: 5, "one": 1, "three": 3, "two": 2}"#,
+            ),
+            (r#"{"foo": 5}["foo"]"#, "5"),
+            (r#"{"foo": 5}["bar"]"#, "null"),
+            ("{5: 5}[5]", "5"),
+            ("{true: 5}[true]", "5"),
+            ("{}[1]", "null"),
+            ("[1, [2]] == [1, [2]]", "true"),
+            ("[1] == [1.0]", "true"),
+            (r#"{"a": [2]} != {"a": [2.5]}"#, "true"),
+            (r#"{"a": 1} == {"a": 1.0}"#, "true"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_big_integers() {
+        let tests = vec![
+            ("9223372036854775807 + 1", "9223372036854775808"),
+            ("-9223372036854775807 - 2", "-9223372036854775809"),
+            ("-(-9223372036854775807 - 1)", "9223372036854775808"),
+            ("(-9223372036854775807 - 1) / -1", "9223372036854775808"),
+            (
+                "4294967296 * 4294967296 * 4294967296",
+                "79228162514264337593543950336",
+            ),
+            (
+                "123456789012345678901234567890",
+                "123456789012345678901234567890",
+            ),
+            ("99999999999999999999 - 99999999999999999998", "1"),
+            ("99999999999999999999 > 1", "true"),
+            ("-99999999999999999999 < 1", "true"),
+            ("9223372036854775807 + 1 == 9223372036854775808", "true"),
+            ("9223372036854775808 - 1 == 9223372036854775807", "true"),
+            ("{9223372036854775808: 1}[9223372036854775807 + 1]", "1"),
+            ("[1, 2][99999999999999999999]", "null"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        // results are demoted back to `Integer` when they fit
+        assert_eq!(
+            eval("9223372036854775808 - 1"),
+            Ok(Object::Integer(i64::MAX))
+        );
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let tests = vec![
+            ("12 & 10", "8"),
+            ("12 | 10", "14"),
+            ("12 ^ 10", "6"),
+            ("~5", "-6"),
+            ("~-1", "0"),
+            ("-8 & 0xFF", "248"),
+            ("1 << 4", "16"),
+            ("-16 >> 2", "-4"),
+            ("-1 >> 100", "-1"),
+            ("5 >> 99999999999999999999", "0"),
+            ("1 << 64", "18446744073709551616"),
+            ("(1 << 64) >> 63", "2"),
+            ("~(1 << 64)", "-18446744073709551617"),
+            ("(1 << 64 | 1) & 3", "1"),
+            ("(0x0F | 0xF0) == 0xFF", "true"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        let tests = vec![
+            ("false && undefined", "false"),
+            ("true || 1 / 0", "true"),
+            ("let f = fn() { f() }; false && f()", "false"),
+            ("if (1 > 2 && missing) { 1 } else { 2 }", "2"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            eval("true && undefined").unwrap_err().to_string(),
+            "identifier not found: undefined at 8..17"
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        let tests = vec![
+            ("3.5", "3.5"),
+            ("-1.25", "-1.25"),
+            ("1.0", "1.0"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("1 + 0.5", "1.5"),
+            ("0.5 * 4", "2.0"),
+            ("7 / 2.0", "3.5"),
+            ("99999999999999999999 * 1.0", "1e20"),
+            ("1.5 > 1", "true"),
+            ("1 == 1.0", "true"),
+            ("1.5 != 1.5", "false"),
+            ("[1.5][0]", "1.5"),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(eval(input).unwrap().to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_error_handling() {
+        let tests = vec![
+            ("5 + true;", "type mismatch: INTEGER + BOOLEAN at 0..8"),
+            ("5; -true; 5", "unknown operator: -BOOLEAN at 3..8"),
+            (
+                "if (10 > 1) { true + false; }",
+                "unknown operator: BOOLEAN + BOOLEAN at 14..26",
+            ),
+            ("foobar", "identifier not found: foobar at 0..6"),
+            ("1 / 0", "division by zero at 0..5"),
+            (
+                "99999999999999999999 / (5 - 5)",
+                "division by zero at 0..30",
+            ),
+            ("let x = 1; x(2)", "not a function: INTEGER at 11..15"),
+            (
+                "fn(a) { a }()",
+                "wrong number of arguments: expected 1, got 0 at 0..13",
+            ),
+            (
+                r#""Hello" - "World""#,
+                "unknown operator: STRING - STRING at 0..17",
+            ),
+            (
+                r#"{"name": "Monkey"}[fn(x) { x }];"#,
+                "unusable as hash key: FUNCTION at 19..30",
+            ),
+            ("{[1]: 2}", "unusable as hash key: ARRAY at 1..4"),
+            ("1.5 / 0", "division by zero at 0..7"),
+            ("5 % 0", "division by zero at 0..5"),
+            (
+                "0x0F | 0xF0 == 0xFF",
+                "type mismatch: INTEGER | BOOLEAN at 0..19",
+            ),
+            ("1 << -1", "negative shift count -1 at 0..7"),
+            ("8 >> -2", "negative shift count -2 at 0..7"),
+            ("1 << 2000000", "shift count 2000000 too large at 0..12"),
+            ("1.5 & 1", "unknown operator: FLOAT & INTEGER at 0..7"),
+            ("~true", "unknown operator: ~BOOLEAN at 0..5"),
+            (
+                "true <= false",
+                "unknown operator: BOOLEAN <= BOOLEAN at 0..13",
+            ),
+            ("{1.5: 1}", "unusable as hash key: FLOAT at 1..4"),
+            ("1.5 + true", "type mismatch: FLOAT + BOOLEAN at 0..10"),
+            (
+                "1[0]",
+                "index operator not supported: INTEGER[INTEGER] at 0..4",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            match eval(input) {
+                Err(error) => assert_eq!(error.to_string(), expected, "{}", input),
+                Ok(value) => panic!("{}: expected an error, got {}", input, value),
+            }
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let unlimited = Limits {
+            max_steps: None,
+            max_depth: None,
+            max_memory: None,
+            max_nesting: None,
+        };
+        let tests = vec![
+            (
+                "let f = fn() { f() }; f();",
+                Limits::default(),
+                ErrorKind::DepthLimit,
+                "maximum call depth of 10000 exceeded at 15..18",
+            ),
+            (
+                "let f = fn(n) { f(n + 1) }; f(0);",
+                Limits {
+                    max_steps: Some(50),
+                    ..unlimited
+                },
+                ErrorKind::StepLimit,
+                "step limit of 50 exceeded at 16..17",
+            ),
+            (
+                r#"let f = fn(s) { f(s + s) }; f("ab");"#,
+                Limits {
+                    max_memory: Some(1024),
+                    ..Limits::default()
+                },
+                ErrorKind::MemoryLimit,
+                "memory limit of 1024 bytes exceeded at 18..23",
+            ),
+            (
+                // the array is copied before `b` changes it, as `a` shares it
+                "let a = [1, 2, 3]; let b = a; b[0] = 0; b[1] = 0;",
+                Limits {
+                    max_memory: Some(100),
+                    ..Limits::default()
+                },
+                ErrorKind::MemoryLimit,
+                "memory limit of 100 bytes exceeded at 30..38",
+            ),
+            (
+                "while (true) { 1 }",
+                Limits {
+                    max_steps: Some(100),
+                    ..unlimited
+                },
+                ErrorKind::StepLimit,
+                "step limit of 100 exceeded at 7..11",
+            ),
+        ];
+
+        for (input, limits, kind, expected) in tests {
+            let error = eval_with_limits(input, limits).unwrap_err();
+            assert_eq!(error.kind, kind, "{}", input);
+            assert_eq!(error.to_string(), expected, "{}", input);
+        }
+
+        let fits = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(150)";
+        assert_eq!(
+            eval_with_limits(fits, Limits::default()),
+            Ok(Object::Integer(150))
+        );
+        let literals = r#"let n = 0; while (n < 200000) { let s = "0123456789"; n += 1 }; n"#;
+        let small = Limits {
+            max_memory: Some(1 << 20),
+            ..Limits::default()
+        };
+        assert_eq!(
+            eval_with_limits(literals, small),
+            Ok(Object::Integer(200000))
+        );
+        assert_eq!(eval("foobar").unwrap_err().kind, ErrorKind::Runtime);
+    }
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let with_timeout = token.timeout(Duration::from_secs(3600));
+        assert!(!with_timeout.is_cancelled());
+        token.clone().cancel();
+        assert!(token.is_cancelled());
+        assert!(with_timeout.is_cancelled());
+        assert!(CancellationToken::with_timeout(Duration::ZERO).is_cancelled());
+
+        let program = Parser::new(Lexer::new("let f = fn(x) { x }; 1 + f(2)"))
+            .parse_program()
+            .unwrap();
+        let mut evaluator = Evaluator::new(Limits::default());
+        evaluator.set_cancellation_token(token);
+        let error = evaluator
+            .eval_program(&program, &Rc::new(RefCell::new(Environment::new())))
+            .unwrap_err();
+        assert_eq!(error.kind, ErrorKind::Cancelled);
+        assert_eq!(error.to_string(), "cancelled at 25..29");
+
+        // a loop without calls still notices a cancel from another thread
+        let token = CancellationToken::new();
+        let canceller = token.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            canceller.cancel();
+        });
+        let program = Parser::new(Lexer::new("while (true) {}"))
+            .parse_program()
+            .unwrap();
+        let mut evaluator = Evaluator::new(Limits::default());
+        evaluator.set_cancellation_token(token);
+        let error = evaluator
+            .eval_program(&program, &Rc::new(RefCell::new(Environment::new())))
+            .unwrap_err();
+        thread.join().unwrap();
+        assert_eq!(error.to_string(), "cancelled at 0..15");
+    }
+}
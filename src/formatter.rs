@@ -0,0 +1,933 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{
+    quote, BlockStatement, Expression, FunctionLiteral, HashPattern, MatchArm, MatchExpression,
+    Pattern, Program, Statement,
+};
+use crate::cst;
+use crate::lexer::Lexer;
+use crate::parser::{Parser, Precedence};
+use crate::token::{Span, Token, TriviaKind};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FormatOptions {
+    /// Preferred maximum line width.
+    pub width: usize,
+    /// Spaces per indentation level.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions {
+            width: 80,
+            indent: 4,
+        }
+    }
+}
+
+/// Formats a Monkey program, keeping its comments. Formatting is idempotent:
+/// formatting the output again returns it unchanged.
+pub fn format(input: &str, options: &FormatOptions) -> Result<String, String> {
+    let mut parser = Parser::new(Lexer::new(input));
+    let program = parser.parse_program()?;
+
+    let mut formatter = Formatter::new(input);
+    let doc = formatter.program(&program);
+    let formatted = render(&doc, options);
+    dismantle(doc);
+    Ok(formatted)
+}
+
+/// Layout document, printed by `render` in the style of Wadler's "prettier
+/// printer": a `Group` is printed flat when it fits on the current line and
+/// has its `Line`s broken otherwise.
+#[derive(Debug, Clone)]
+enum Doc {
+    Text(String),
+    // a space when flat, a newline when broken
+    Line,
+    // nothing when flat, a newline when broken
+    SoftLine,
+    HardLine,
+    Indent(Box<Doc>),
+    Group(Box<Doc>),
+    // always printed broken, used for blocks
+    Broken(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+// Drops `doc` one level at a time; the drop glue would recurse as deep as the
+// doc nests, which is as deep as the longest chain of operators.
+fn dismantle(doc: Doc) {
+    let mut stack = vec![doc];
+    while let Some(doc) = stack.pop() {
+        match doc {
+            Doc::Indent(doc) | Doc::Group(doc) | Doc::Broken(doc) => stack.push(*doc),
+            Doc::Concat(docs) => stack.extend(docs),
+            Doc::Text(_) | Doc::Line | Doc::SoftLine | Doc::HardLine => {}
+        }
+    }
+}
+
+fn text(s: &str) -> Doc {
+    Doc::Text(s.to_string())
+}
+
+fn indent(doc: Doc) -> Doc {
+    Doc::Indent(Box::new(doc))
+}
+
+fn group(doc: Doc) -> Doc {
+    Doc::Group(Box::new(doc))
+}
+
+struct Comment {
+    text: String,
+    start: usize,
+    // false when the comment trails code on the same line
+    own_line: bool,
+    // a `//` comment, which runs to the end of its line
+    line_comment: bool,
+    blank_line_before: bool,
+}
+
+struct Formatter {
+    comments: Vec<Comment>,
+    next_comment: usize,
+    // starts of tokens preceded by an empty line
+    blank_lines: HashSet<usize>,
+    // source text of number literals by their start, so that `0xFF` and
+    // `1_000` keep their spelling
+    numbers: HashMap<usize, String>,
+}
+
+impl Formatter {
+    fn new(input: &str) -> Formatter {
+        let mut comments = vec![];
+        let mut blank_lines = HashSet::new();
+        let mut numbers = HashMap::new();
+        let mut newlines = 0;
+        let mut at_start = true;
+
+        for token in cst::tokenize(input) {
+            let trivia_len: usize = token
+                .leading_trivia
+                .iter()
+                .map(|trivia| trivia.text.chars().count())
+                .sum();
+            let mut position = token.span.start - trivia_len;
+            for trivia in &token.leading_trivia {
+                match trivia.kind {
+                    TriviaKind::Whitespace => newlines += trivia.text.matches('\n').count(),
+                    TriviaKind::LineComment | TriviaKind::BlockComment => {
+                        comments.push(Comment {
+                            text: trivia.text.clone(),
+                            start: position,
+                            own_line: at_start || newlines > 0,
+                            line_comment: trivia.kind == TriviaKind::LineComment,
+                            blank_line_before: !at_start && newlines > 1,
+                        });
+                        newlines = 0;
+                        at_start = false;
+                    }
+                }
+                position += trivia.text.chars().count();
+            }
+            if !at_start && newlines > 1 {
+                blank_lines.insert(token.span.start);
+            }
+            if let Token::INT(_) | Token::FLOAT(_) = token.kind {
+                numbers.insert(token.span.start, token.text.clone());
+            }
+            newlines = 0;
+            at_start = false;
+        }
+
+        Formatter {
+            comments,
+            next_comment: 0,
+            blank_lines,
+            numbers,
+        }
+    }
+
+    fn number(&self, span: Span, string: impl FnOnce() -> String) -> Doc {
+        match self.numbers.get(&span.start) {
+            Some(literal) => text(literal),
+            None => text(&string()),
+        }
+    }
+
+    fn has_comment_before(&self, position: usize) -> bool {
+        match self.comments.get(self.next_comment) {
+            Some(comment) => comment.start < position,
+            None => false,
+        }
+    }
+
+    fn program(&mut self, program: &Program) -> Doc {
+        let mut parts = self.statements(&program.statements, usize::MAX, false);
+        if !parts.is_empty() {
+            parts.push(Doc::HardLine);
+        }
+        Doc::Concat(parts)
+    }
+
+    fn block(&mut self, block: &BlockStatement) -> Doc {
+        if block.statements.is_empty() && !self.has_comment_before(block.span.end) {
+            return text("{}");
+        }
+        let body = self.statements(&block.statements, block.span.end, true);
+        Doc::Broken(Box::new(Doc::Concat(vec![
+            text("{"),
+            indent(Doc::Concat(body)),
+            Doc::HardLine,
+            text("}"),
+        ])))
+    }
+
+    // Lays out statements one per line, along with the comments that come
+    // before `end`. Comments inside an expression stay there; the few that
+    // have no expression after them, like one before a `;`, follow the
+    // statement.
+    fn statements(&mut self, statements: &[Statement], end: usize, in_block: bool) -> Vec<Doc> {
+        let mut parts = vec![];
+        for (i, statement) in statements.iter().enumerate() {
+            let start = statement.span().start;
+            self.comments_before(start, &mut parts, in_block);
+            let blank_line = self.blank_lines.contains(&start);
+            Self::separator(&mut parts, blank_line, in_block);
+            let last_in_block = in_block && i == statements.len() - 1;
+            parts.push(self.statement(statement, last_in_block));
+        }
+        self.comments_before(end, &mut parts, in_block);
+        parts
+    }
+
+    fn comments_before(&mut self, position: usize, parts: &mut Vec<Doc>, in_block: bool) {
+        let mut after_line_comment = false;
+        while self.has_comment_before(position) {
+            let comment = &self.comments[self.next_comment];
+            self.next_comment += 1;
+            if comment.own_line || after_line_comment || (parts.is_empty() && !in_block) {
+                Self::separator(parts, comment.blank_line_before, in_block);
+            } else {
+                parts.push(text(" "));
+            }
+            parts.push(text(&comment.text));
+            after_line_comment = comment.line_comment;
+        }
+    }
+
+    fn separator(parts: &mut Vec<Doc>, blank_line: bool, in_block: bool) {
+        if parts.is_empty() {
+            if in_block {
+                parts.push(Doc::HardLine);
+            }
+            return;
+        }
+        parts.push(Doc::HardLine);
+        if blank_line {
+            parts.push(Doc::HardLine);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, last_in_block: bool) -> Doc {
+        match statement {
+            Statement::LetStatement(let_statement) => Doc::Concat(vec![
+                text(let_statement.kind.keyword()),
+                text(" "),
+                text(&let_statement.name.value),
+                text(" = "),
+                self.expression(&let_statement.value),
+                text(";"),
+            ]),
+            Statement::DestructureStatement(destructure) => Doc::Concat(vec![
+                text(destructure.kind.keyword()),
+                text(" "),
+                self.pattern(&destructure.pattern),
+                text(" = "),
+                self.expression(&destructure.value),
+                text(";"),
+            ]),
+            Statement::ReturnStatement(return_statement) => Doc::Concat(vec![
+                text("return "),
+                self.expression(&return_statement.return_value),
+                text(";"),
+            ]),
+            Statement::ThrowStatement(throw_statement) => Doc::Concat(vec![
+                text("throw "),
+                self.expression(&throw_statement.value),
+                text(";"),
+            ]),
+            Statement::FunctionStatement(declaration) => {
+                let keyword = format!("fn {}", declaration.name.value);
+                self.function(&keyword, &declaration.function)
+            }
+            Statement::WhileStatement(while_statement) => Doc::Concat(vec![
+                text("while ("),
+                self.expression(&while_statement.condition),
+                text(") "),
+                self.block(&while_statement.body),
+            ]),
+            Statement::ForStatement(for_statement) => {
+                let mut parts = vec![text("for (")];
+                if let Some(key) = &for_statement.key {
+                    parts.push(text(&key.value));
+                    parts.push(text(", "));
+                }
+                parts.push(text(&for_statement.value.value));
+                parts.push(text(" in "));
+                parts.push(self.expression(&for_statement.iterable));
+                parts.push(text(") "));
+                parts.push(self.block(&for_statement.body));
+                Doc::Concat(parts)
+            }
+            Statement::BreakStatement(_) => text("break;"),
+            Statement::ContinueStatement(_) => text("continue;"),
+            // the value of a block is its last expression, which reads
+            // better without a semicolon
+            Statement::ExpressionStatement(expression_statement) => {
+                let expression = self.expression(&expression_statement.expression);
+                if last_in_block {
+                    expression
+                } else {
+                    Doc::Concat(vec![expression, text(";")])
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Doc {
+        let mut parts = self.leading_comments(expression.span().start);
+        let doc = crate::with_stack(|| self.expression_kind(expression));
+        if parts.is_empty() {
+            return doc;
+        }
+        parts.push(doc);
+        Doc::Concat(parts)
+    }
+
+    // The comments before `position`, printed in place ahead of the code that
+    // follows them. A line comment ends its line, which breaks every group
+    // around it.
+    fn leading_comments(&mut self, position: usize) -> Vec<Doc> {
+        let mut parts = vec![];
+        while self.has_comment_before(position) {
+            let comment = &self.comments[self.next_comment];
+            self.next_comment += 1;
+            parts.push(text(&comment.text));
+            parts.push(if comment.line_comment {
+                Doc::HardLine
+            } else {
+                text(" ")
+            });
+        }
+        parts
+    }
+
+    // `list` for the items of a literal or call ending at `end`, with the
+    // comments after the last item kept before the closing bracket. A line
+    // comment there puts the items one per line.
+    fn closed_list(&mut self, open: &str, close: &str, mut items: Vec<Doc>, end: usize) -> Doc {
+        let mut trailing = vec![];
+        let mut broken = false;
+        while self.has_comment_before(end) {
+            let comment = &self.comments[self.next_comment];
+            self.next_comment += 1;
+            // a comment after a line comment starts a line of its own
+            trailing.push(if broken { Doc::HardLine } else { text(" ") });
+            trailing.push(text(&comment.text));
+            broken |= comment.line_comment;
+        }
+        if trailing.is_empty() {
+            return list(open, close, items);
+        }
+        match items.pop() {
+            Some(last) => trailing.insert(0, last),
+            None if broken => {
+                return Doc::Concat(vec![
+                    text(open),
+                    indent(Doc::Concat(trailing)),
+                    Doc::HardLine,
+                    text(close),
+                ])
+            }
+            None => {
+                trailing[0] = text(open);
+                trailing.push(text(close));
+                return Doc::Concat(trailing);
+            }
+        }
+        items.push(Doc::Concat(trailing));
+        if broken {
+            Doc::Broken(Box::new(list_items(open, close, items)))
+        } else {
+            list(open, close, items)
+        }
+    }
+
+    fn expression_kind(&mut self, expression: &Expression) -> Doc {
+        match expression {
+            Expression::Identifier(identifier) => text(&identifier.value),
+            Expression::IntegerLiteral(integer_literal) => {
+                self.number(integer_literal.span, || integer_literal.string())
+            }
+            Expression::FloatLiteral(float_literal) => {
+                self.number(float_literal.span, || float_literal.string())
+            }
+            Expression::Boolean(boolean) => text(&boolean.token.literal()),
+            Expression::PrefixExpression(prefix) => {
+                let needs_parens = precedence(&prefix.right) < Precedence::PREFIX.value();
+                Doc::Concat(vec![
+                    text(&prefix.operator),
+                    self.operand(&prefix.right, needs_parens),
+                ])
+            }
+            Expression::InfixExpression(infix) => {
+                let operator = Precedence::from_token(&infix.token).value();
+                let left = self.operand(&infix.left, precedence(&infix.left) < operator);
+                let right = self.operand(&infix.right, precedence(&infix.right) <= operator);
+                // ranges read as one unit, like `0..n`
+                if operator == Precedence::RANGE.value() {
+                    return Doc::Concat(vec![left, text(&infix.operator), right]);
+                }
+                group(Doc::Concat(vec![
+                    left,
+                    text(" "),
+                    text(&infix.operator),
+                    indent(Doc::Concat(vec![Doc::Line, right])),
+                ]))
+            }
+            Expression::AssignExpression(assign) => {
+                let operator = Precedence::ASSIGN.value();
+                let value = self.operand(&assign.value, precedence(&assign.value) < operator);
+                group(Doc::Concat(vec![
+                    self.expression(&assign.target),
+                    text(" "),
+                    text(&assign.operator),
+                    indent(Doc::Concat(vec![Doc::Line, value])),
+                ]))
+            }
+            Expression::IfExpression(if_expression) => {
+                let mut parts = vec![
+                    text("if ("),
+                    self.expression(&if_expression.condition),
+                    text(") "),
+                    self.block(&if_expression.consequence),
+                ];
+                if let Some(alternative) = &if_expression.alternative {
+                    parts.push(text(" else "));
+                    parts.push(self.block(alternative));
+                }
+                Doc::Concat(parts)
+            }
+            Expression::FunctionLiteral(function) => self.function("fn", function),
+            Expression::CallExpression(call) => {
+                let needs_parens = precedence(&call.function) < Precedence::CALL.value();
+                let function = self.operand(&call.function, needs_parens);
+                let arguments = call
+                    .arguments
+                    .iter()
+                    .map(|argument| self.expression(argument))
+                    .collect();
+                let arguments = self.closed_list("(", ")", arguments, call.span.end);
+                Doc::Concat(vec![function, arguments])
+            }
+            Expression::StringLiteral(string_literal) => text(&quote(&string_literal.value)),
+            Expression::ArrayLiteral(array) => {
+                let elements = array
+                    .elements
+                    .iter()
+                    .map(|element| self.expression(element))
+                    .collect();
+                self.closed_list("[", "]", elements, array.span.end)
+            }
+            Expression::HashLiteral(hash) => {
+                let pairs = hash
+                    .pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        Doc::Concat(vec![
+                            self.expression(key),
+                            text(": "),
+                            self.expression(value),
+                        ])
+                    })
+                    .collect();
+                self.closed_list("{", "}", pairs, hash.span.end)
+            }
+            Expression::IndexExpression(index) => {
+                let needs_parens = precedence(&index.left) < Precedence::INDEX.value();
+                Doc::Concat(vec![
+                    self.operand(&index.left, needs_parens),
+                    text("["),
+                    self.expression(&index.index),
+                    text("]"),
+                ])
+            }
+            Expression::MatchExpression(match_expression) => {
+                self.match_expression(match_expression)
+            }
+            Expression::TryExpression(try_expression) => {
+                let mut parts = vec![text("try "), self.block(&try_expression.body)];
+                if let Some(catch) = &try_expression.catch {
+                    parts.push(text(" catch ("));
+                    parts.push(text(&catch.parameter.value));
+                    parts.push(text(") "));
+                    parts.push(self.block(&catch.body));
+                }
+                if let Some(finally) = &try_expression.finally {
+                    parts.push(text(" finally "));
+                    parts.push(self.block(finally));
+                }
+                Doc::Concat(parts)
+            }
+        }
+    }
+
+    // `keyword` is `fn`, followed by the name for a declaration.
+    fn function(&mut self, keyword: &str, function: &FunctionLiteral) -> Doc {
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|parameter| text(&parameter.value))
+            .collect();
+        Doc::Concat(vec![
+            text(keyword),
+            list("(", ")", parameters),
+            text(" "),
+            self.block(&function.body),
+        ])
+    }
+
+    // Arms go one per line, like the statements of a block.
+    fn match_expression(&mut self, match_expression: &MatchExpression) -> Doc {
+        let mut parts = vec![
+            text("match ("),
+            self.expression(&match_expression.subject),
+            text(") "),
+        ];
+        let end = match_expression.span.end;
+        if match_expression.arms.is_empty() && !self.has_comment_before(end) {
+            parts.push(text("{}"));
+            return Doc::Concat(parts);
+        }
+        let mut arms = vec![];
+        for arm in &match_expression.arms {
+            let start = arm.span.start;
+            self.comments_before(start, &mut arms, true);
+            Self::separator(&mut arms, self.blank_lines.contains(&start), true);
+            arms.push(self.match_arm(arm));
+            arms.push(text(","));
+        }
+        self.comments_before(end, &mut arms, true);
+        parts.push(Doc::Broken(Box::new(Doc::Concat(vec![
+            text("{"),
+            indent(Doc::Concat(arms)),
+            Doc::HardLine,
+            text("}"),
+        ]))));
+        Doc::Concat(parts)
+    }
+
+    fn match_arm(&mut self, arm: &MatchArm) -> Doc {
+        let mut parts = vec![self.pattern(&arm.pattern)];
+        if let Some(guard) = &arm.guard {
+            parts.push(text(" if "));
+            parts.push(self.expression(guard));
+        }
+        parts.push(text(" => "));
+        parts.push(self.expression(&arm.body));
+        Doc::Concat(parts)
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) -> Doc {
+        crate::with_stack(|| self.pattern_kind(pattern))
+    }
+
+    fn pattern_kind(&mut self, pattern: &Pattern) -> Doc {
+        match pattern {
+            Pattern::Literal(literal) => self.expression(literal),
+            Pattern::Wildcard(_) => text("_"),
+            Pattern::Binding(identifier) => text(&identifier.value),
+            Pattern::Array(array) => {
+                let mut elements: Vec<Doc> = array
+                    .elements
+                    .iter()
+                    .map(|element| self.pattern(element))
+                    .collect();
+                if let Some(rest) = &array.rest {
+                    elements.push(Doc::Concat(vec![text("..."), self.pattern(rest)]));
+                }
+                list("[", "]", elements)
+            }
+            Pattern::Hash(hash) => {
+                let pairs = hash
+                    .pairs
+                    .iter()
+                    .map(|(key, pattern)| {
+                        if HashPattern::is_shorthand(key, pattern) {
+                            return self.pattern(pattern);
+                        }
+                        Doc::Concat(vec![
+                            self.expression(key),
+                            text(": "),
+                            self.pattern(pattern),
+                        ])
+                    })
+                    .collect();
+                list("{", "}", pairs)
+            }
+        }
+    }
+
+    fn operand(&mut self, expression: &Expression, needs_parens: bool) -> Doc {
+        let doc = self.expression(expression);
+        if needs_parens {
+            Doc::Concat(vec![text("("), doc, text(")")])
+        } else {
+            doc
+        }
+    }
+}
+
+// Binding strength of an expression, to decide where parentheses are needed.
+fn precedence(expression: &Expression) -> i32 {
+    match expression {
+        Expression::InfixExpression(infix) => Precedence::from_token(&infix.token).value(),
+        Expression::AssignExpression(_) => Precedence::ASSIGN.value(),
+        Expression::PrefixExpression(_) => Precedence::PREFIX.value(),
+        Expression::CallExpression(_) => Precedence::CALL.value(),
+        Expression::IndexExpression(_) => Precedence::INDEX.value(),
+        _ => i32::MAX,
+    }
+}
+
+// Comma separated list between `open` and `close` that puts one item per
+// line when it does not fit.
+fn list(open: &str, close: &str, items: Vec<Doc>) -> Doc {
+    if items.is_empty() {
+        return Doc::Concat(vec![text(open), text(close)]);
+    }
+    group(list_items(open, close, items))
+}
+
+// The items of a non-empty `list`, without the group deciding how it breaks.
+fn list_items(open: &str, close: &str, items: Vec<Doc>) -> Doc {
+    let mut inner = vec![Doc::SoftLine];
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            inner.push(text(","));
+            inner.push(Doc::Line);
+        }
+        inner.push(item);
+    }
+    Doc::Concat(vec![
+        text(open),
+        indent(Doc::Concat(inner)),
+        Doc::SoftLine,
+        text(close),
+    ])
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+fn render(doc: &Doc, options: &FormatOptions) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    // indentation is written lazily so empty lines get no trailing spaces
+    let mut pending_indent = None;
+    let mut stack = vec![(0, Mode::Break, doc)];
+
+    while let Some((level, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(s) => {
+                if let Some(level) = pending_indent.take() {
+                    out.push_str(&" ".repeat(level));
+                }
+                out.push_str(s);
+                column = match s.rfind('\n') {
+                    Some(i) => s[i + 1..].chars().count(),
+                    None => column + s.chars().count(),
+                };
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if let Doc::Line = doc {
+                    out.push(' ');
+                    column += 1;
+                }
+            }
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push('\n');
+                pending_indent = Some(level);
+                column = level;
+            }
+            Doc::Indent(doc) => {
+                // indentation only applies to lines that are actually broken
+                let level = match mode {
+                    Mode::Break => level + options.indent,
+                    Mode::Flat => level,
+                };
+                stack.push((level, mode, doc));
+            }
+            Doc::Group(doc) => {
+                let remaining = options.width as isize - column as isize;
+                let mode = if mode == Mode::Flat || fits(remaining, doc, &stack) {
+                    Mode::Flat
+                } else {
+                    Mode::Break
+                };
+                stack.push((level, mode, doc));
+            }
+            Doc::Broken(doc) => stack.push((level, Mode::Break, doc)),
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((level, mode, doc));
+                }
+            }
+        }
+    }
+    out
+}
+
+// Whether `doc` printed flat, followed by whatever comes after it, fits in
+// `remaining` columns up to the next line break. A hard line in `doc` itself,
+// after a line comment, means it cannot be flat.
+fn fits(mut remaining: isize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut stack = vec![(Mode::Flat, doc)];
+    let mut rest = rest.iter().rev();
+    let mut in_rest = false;
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some((_, mode, doc)) => {
+                    in_rest = true;
+                    (*mode, *doc)
+                }
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(s) => {
+                if let Some(i) = s.find('\n') {
+                    return remaining - s[..i].chars().count() as isize >= 0;
+                }
+                remaining -= s.chars().count() as isize;
+                if remaining < 0 {
+                    return false;
+                }
+            }
+            Doc::Line if mode == Mode::Flat => {
+                remaining -= 1;
+                if remaining < 0 {
+                    return false;
+                }
+            }
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::HardLine if mode == Mode::Flat && !in_rest => return false,
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::Indent(doc) | Doc::Group(doc) => stack.push((mode, doc)),
+            Doc::Broken(doc) => stack.push((Mode::Break, doc)),
+            Doc::Concat(docs) => {
+                for doc in docs.iter().rev() {
+                    stack.push((mode, doc));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn assert_formats(input: &str, expected: &str, options: &FormatOptions) {
+        let formatted = format(input, options).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted, options).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format() {
+        let tests =
+            vec![
+            ("", ""),
+            ("let   x=5", "let x = 5;\n"),
+            ("const  x=5", "const x = 5;\n"),
+            ("return   x\n\n\n", "return x;\n"),
+            ("a+b*c;(a+b)*c", "a + b * c;\n(a + b) * c;\n"),
+            ("((a - b) - c); a - (b - c)", "a - b - c;\na - (b - c);\n"),
+            ("-(a + b); !(-a); (-a) * b", "-(a + b);\n!-a;\n-a * b;\n"),
+            ("(3 > 5) == false", "3 > 5 == false;\n"),
+            ("(add)(1, (2))", "add(1, 2);\n"),
+            (
+                "let x = 1;\n\n\n\nlet y = 2;\nx",
+                "let x = 1;\n\nlet y = 2;\nx;\n",
+            ),
+            (
+                "if (x<y) {x} else {y;}",
+                "if (x < y) {\n    x\n} else {\n    y\n};\n",
+            ),
+            (
+                "let add = fn(a,b){ return a+b; };",
+                "let add = fn(a, b) {\n    return a + b;\n};\n",
+            ),
+            ("let f = fn() {};", "let f = fn() {};\n"),
+            ("fn(x) { x }(5);", "fn(x) {\n    x\n}(5);\n"),
+            (
+                r#"let a=[1,"two\t",{ "k" :[] }] ; (a)[0][1+1]"#,
+                "let a = [1, \"two\\t\", {\"k\": []}];\na[0][1 + 1];\n",
+            ),
+            ("({})[x]", "{}[x];\n"),
+            ("1.50 * 2e-9", "1.50 * 2e-9;\n"),
+            ("0xFF+1_000", "0xFF + 1_000;\n"),
+            ("(a||b)&&c; a||(b&&c)", "(a || b) && c;\na || b && c;\n"),
+            ("a[1 .. n-1]; (0..=9)+x", "a[1..n - 1];\n(0..=9) + x;\n"),
+            ("x=y=1; a[0]+=2*3; (x=1)+2", "x = y = 1;\na[0] += 2 * 3;\n(x = 1) + 2;\n"),
+            (
+                "for(k,v in {1:2}){v}; for (c in \"ab\") {}",
+                "for (k, v in {1: 2}) {\n    v\n}\nfor (c in \"ab\") {}\n",
+            ),
+            (
+                "while(i<3){ if (i==1) {break} continue; }",
+                "while (i < 3) {\n    if (i == 1) {\n        break;\n    };\n    continue;\n}\n",
+            ),
+            (
+                "match(x){1=>a,[b,_] if b>0=>b,{name,\"k\":-1}=>name}",
+                "match (x) {\n    1 => a,\n    [b, _] if b > 0 => b,\n    {name, \"k\": -1} => name,\n};\n",
+            ),
+            (
+                "match (x) { // first\n 1 => a, // one\n _ => b }; match (y) {}",
+                "match (x) { // first\n    1 => a, // one\n    _ => b,\n};\nmatch (y) {};\n",
+            ),
+            (
+                "let[a,...b]=xs; const {name,\"k\":[_]}=h",
+                "let [a, ...b] = xs;\nconst {name, \"k\": [_]} = h;\n",
+            ),
+            (
+                "throw \"x\"+y; try{f()}catch(e){throw e}finally{g()}",
+                "throw \"x\" + y;\ntry {\n    f()\n} catch (e) {\n    throw e;\n} finally {\n    g()\n};\n",
+            ),
+            (
+                "fn  add(a,b){a+b}; fn g(){} g()",
+                "fn add(a, b) {\n    a + b\n}\nfn g() {}\ng();\n",
+            ),
+            (
+                "(a&b)==c; a<<(1+2); ~(~a)",
+                "(a & b) == c;\na << 1 + 2;\n~~a;\n",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_formats(input, expected, &FormatOptions::default());
+        }
+    }
+
+    #[test]
+    fn test_format_comments() {
+        let input = r#"// header
+
+let x = 5; // trailing
+/* block */ let y = x +
+    /* inside */ 1;
+
+
+let f = fn(a) { // opening
+  // leading
+  a
+
+  /* closing */
+};
+// end
+"#;
+        let expected = r#"// header
+
+let x = 5; // trailing
+/* block */
+let y = x + /* inside */ 1;
+
+let f = fn(a) { // opening
+    // leading
+    a
+
+    /* closing */
+};
+// end
+"#;
+        assert_formats(input, expected, &FormatOptions::default());
+
+        // a line comment inside an expression breaks the groups around it
+        let input = r#"let h = {
+  // key comment
+  "a": 1, /* after a */ "b": 2
+};
+let xs = [1, 2 // two
+];
+f(x, [1, /* one */ 2]); g( // none
+);
+let total = a + // first
+  b * c;
+"#;
+        let expected = r#"let h = {
+    // key comment
+    "a": 1,
+    /* after a */ "b": 2
+};
+let xs = [
+    1,
+    2 // two
+];
+f(x, [1, /* one */ 2]);
+g( // none
+);
+let total = a +
+    // first
+    b * c;
+"#;
+        assert_formats(input, expected, &FormatOptions::default());
+    }
+
+    #[test]
+    fn test_format_width_and_indent() {
+        let options = FormatOptions {
+            width: 30,
+            indent: 2,
+        };
+        let input = r#"
+let result = compute(first_argument, second_argument, third);
+let total = first_value + second_value + third_value;
+map(fn(x) { x * 2 }, items);
+let xs = [first_value, second_value];
+"#;
+        let expected = r#"let result = compute(
+  first_argument,
+  second_argument,
+  third
+);
+let total = first_value +
+  second_value +
+  third_value;
+map(fn(x) {
+  x * 2
+}, items);
+let xs = [
+  first_value,
+  second_value
+];
+"#;
+        assert_formats(input, expected, &options);
+    }
+
+    #[test]
+    fn test_format_parse_error() {
+        assert!(format("let = 5;", &FormatOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,427 @@
+//! Calling Rust from Monkey. A closure whose arguments implement
+//! `FromObject` and whose result implements `IntoObject` can be registered
+//! with `Interpreter::register_function`:
+//!
+//! ```
+//! use monkey_language_rust::{Interpreter, Object};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.register_function("repeat", |s: String, n: i64| {
+//!     if n < 0 {
+//!         return Err(format!("negative count {}", n));
+//!     }
+//!     Ok(s.repeat(n as usize))
+//! });
+//! assert_eq!(
+//!     interpreter.eval(r#"repeat("ab", 2)"#).unwrap(),
+//!     Object::String("abab".to_string())
+//! );
+//! assert_eq!(
+//!     interpreter.eval(r#"repeat("ab", -1)"#).unwrap_err().to_string(),
+//!     "repeat: negative count -1 at 0..16"
+//! );
+//! ```
+//!
+//! Arguments are checked before the closure runs, so a script passing the
+//! wrong number or type of arguments gets a runtime error naming the
+//! function and argument instead of a panic.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::hash::Hash;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+
+use crate::object::{Builtin, HashKey, Object};
+
+/// Conversion of a Monkey value into a Rust argument.
+pub trait FromObject: Sized {
+    fn from_object(object: Object) -> Result<Self, String>;
+}
+
+/// Conversion of a Rust result back into a Monkey value. An `Err` becomes a
+/// runtime error in the script.
+pub trait IntoObject {
+    fn into_object(self) -> Result<Object, String>;
+}
+
+fn expected(type_name: &str, object: &Object) -> String {
+    format!("expected {}, got {}", type_name, object.type_name())
+}
+
+impl FromObject for Object {
+    fn from_object(object: Object) -> Result<Object, String> {
+        Ok(object)
+    }
+}
+
+impl FromObject for i64 {
+    fn from_object(object: Object) -> Result<i64, String> {
+        match object {
+            Object::Integer(value) => Ok(value),
+            Object::BigInteger(value) => Err(format!("integer {} out of range", value)),
+            other => Err(expected("INTEGER", &other)),
+        }
+    }
+}
+
+impl FromObject for BigInt {
+    fn from_object(object: Object) -> Result<BigInt, String> {
+        match object {
+            Object::Integer(value) => Ok(BigInt::from(value)),
+            Object::BigInteger(value) => Ok(BigInt::clone(&value)),
+            other => Err(expected("INTEGER", &other)),
+        }
+    }
+}
+
+/// Integers are converted to the nearest float.
+impl FromObject for f64 {
+    fn from_object(object: Object) -> Result<f64, String> {
+        match object.to_f64() {
+            Some(value) => Ok(value),
+            None => Err(expected("FLOAT", &object)),
+        }
+    }
+}
+
+impl FromObject for bool {
+    fn from_object(object: Object) -> Result<bool, String> {
+        match object {
+            Object::Boolean(value) => Ok(value),
+            other => Err(expected("BOOLEAN", &other)),
+        }
+    }
+}
+
+impl FromObject for String {
+    fn from_object(object: Object) -> Result<String, String> {
+        match object {
+            Object::String(value) => Ok(value),
+            other => Err(expected("STRING", &other)),
+        }
+    }
+}
+
+/// `null` is `None`, anything else must convert to `T`.
+impl<T: FromObject> FromObject for Option<T> {
+    fn from_object(object: Object) -> Result<Option<T>, String> {
+        match object {
+            Object::Null => Ok(None),
+            other => T::from_object(other).map(Some),
+        }
+    }
+}
+
+impl<T: FromObject> FromObject for Vec<T> {
+    fn from_object(object: Object) -> Result<Vec<T>, String> {
+        let elements = match object {
+            Object::Array(elements) => elements,
+            other => return Err(expected("ARRAY", &other)),
+        };
+        let elements = Rc::try_unwrap(elements).unwrap_or_else(|elements| (*elements).clone());
+        elements
+            .into_iter()
+            .enumerate()
+            .map(|(i, element)| {
+                T::from_object(element).map_err(|e| format!("element {}: {}", i, e))
+            })
+            .collect()
+    }
+}
+
+fn hash_pairs<K: FromObject, V: FromObject>(object: Object) -> Result<Vec<(K, V)>, String> {
+    let pairs = match object {
+        Object::Hash(pairs) => pairs,
+        other => return Err(expected("HASH", &other)),
+    };
+    let pairs = Rc::try_unwrap(pairs).unwrap_or_else(|pairs| (*pairs).clone());
+    pairs
+        .into_iter()
+        .map(|(key, value)| {
+            let context = Object::from(key.clone());
+            let key =
+                K::from_object(Object::from(key)).map_err(|e| format!("key {}: {}", context, e))?;
+            let value =
+                V::from_object(value).map_err(|e| format!("value of {}: {}", context, e))?;
+            Ok((key, value))
+        })
+        .collect()
+}
+
+impl<K: FromObject + Eq + Hash, V: FromObject> FromObject for HashMap<K, V> {
+    fn from_object(object: Object) -> Result<HashMap<K, V>, String> {
+        Ok(hash_pairs(object)?.into_iter().collect())
+    }
+}
+
+impl<K: FromObject + Ord, V: FromObject> FromObject for BTreeMap<K, V> {
+    fn from_object(object: Object) -> Result<BTreeMap<K, V>, String> {
+        Ok(hash_pairs(object)?.into_iter().collect())
+    }
+}
+
+impl IntoObject for Object {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(self)
+    }
+}
+
+impl IntoObject for () {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(Object::Null)
+    }
+}
+
+impl IntoObject for i64 {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(Object::Integer(self))
+    }
+}
+
+impl IntoObject for BigInt {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(Object::from(self))
+    }
+}
+
+impl IntoObject for f64 {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(Object::Float(self))
+    }
+}
+
+impl IntoObject for bool {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(Object::Boolean(self))
+    }
+}
+
+impl IntoObject for String {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(Object::String(self))
+    }
+}
+
+impl IntoObject for &str {
+    fn into_object(self) -> Result<Object, String> {
+        Ok(Object::String(self.to_string()))
+    }
+}
+
+impl<T: IntoObject> IntoObject for Option<T> {
+    fn into_object(self) -> Result<Object, String> {
+        match self {
+            Some(value) => value.into_object(),
+            None => Ok(Object::Null),
+        }
+    }
+}
+
+impl<T: IntoObject> IntoObject for Vec<T> {
+    fn into_object(self) -> Result<Object, String> {
+        let elements = self
+            .into_iter()
+            .map(IntoObject::into_object)
+            .collect::<Result<Vec<Object>, String>>()?;
+        Ok(Object::Array(Rc::new(elements)))
+    }
+}
+
+fn hash_from_pairs<K: Into<HashKey>, V: IntoObject>(
+    pairs: impl Iterator<Item = (K, V)>,
+) -> Result<Object, String> {
+    let pairs = pairs
+        .map(|(key, value)| Ok((key.into(), value.into_object()?)))
+        .collect::<Result<BTreeMap<HashKey, Object>, String>>()?;
+    Ok(Object::Hash(Rc::new(pairs)))
+}
+
+impl<K: Into<HashKey>, V: IntoObject> IntoObject for HashMap<K, V> {
+    fn into_object(self) -> Result<Object, String> {
+        hash_from_pairs(self.into_iter())
+    }
+}
+
+impl<K: Into<HashKey>, V: IntoObject> IntoObject for BTreeMap<K, V> {
+    fn into_object(self) -> Result<Object, String> {
+        hash_from_pairs(self.into_iter())
+    }
+}
+
+impl<T: IntoObject, E: Display> IntoObject for Result<T, E> {
+    fn into_object(self) -> Result<Object, String> {
+        match self {
+            Ok(value) => value.into_object(),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// A Rust closure callable from Monkey. `Args` is the tuple of its argument
+/// types; it is implemented for closures of up to six arguments.
+pub trait HostFunction<Args>: 'static {
+    fn call(&self, arguments: Vec<Object>) -> Result<Object, String>;
+}
+
+macro_rules! count {
+    () => { 0 };
+    ($head:ident $($tail:ident)*) => { 1 + count!($($tail)*) };
+}
+
+macro_rules! host_function {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> HostFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> R + 'static,
+            R: IntoObject,
+            $($arg: FromObject,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, arguments: Vec<Object>) -> Result<Object, String> {
+                let expected = count!($($arg)*);
+                if arguments.len() != expected {
+                    return Err(format!(
+                        "wrong number of arguments: expected {}, got {}",
+                        expected,
+                        arguments.len()
+                    ));
+                }
+                let mut arguments = arguments.into_iter().enumerate();
+                $(
+                    let (i, argument) = arguments.next().unwrap();
+                    let $arg = $arg::from_object(argument)
+                        .map_err(|e| format!("argument {}: {}", i + 1, e))?;
+                )*
+                self($($arg),*).into_object()
+            }
+        }
+    };
+}
+
+host_function!();
+host_function!(A);
+host_function!(A, B);
+host_function!(A, B, C);
+host_function!(A, B, C, D);
+host_function!(A, B, C, D, E);
+host_function!(A, B, C, D, E, G);
+
+impl Builtin {
+    /// A builtin that converts its arguments and result with `FromObject`
+    /// and `IntoObject`.
+    pub fn from_host<Args>(name: &str, function: impl HostFunction<Args>) -> Builtin {
+        Builtin::new(name, move |arguments| function.call(arguments))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interpreter;
+    use pretty_assertions::assert_eq;
+
+    fn eval(interpreter: &mut Interpreter, input: &str) -> Result<Object, String> {
+        interpreter.eval(input).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_typed_arguments() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_function("add", |a: i64, b: i64| a + b);
+        interpreter.register_function("not", |b: bool| !b);
+        interpreter.register_function("shout", |s: String| s.to_uppercase());
+        interpreter.register_function("sum", |xs: Vec<i64>| xs.iter().sum::<i64>());
+        interpreter.register_function("keys", |h: BTreeMap<String, i64>| {
+            h.into_keys().collect::<Vec<_>>()
+        });
+        interpreter.register_function("or_zero", |x: Option<i64>| x.unwrap_or(0));
+        interpreter.register_function("answer", || 42);
+        interpreter.register_function("double", |n: BigInt| n * 2);
+
+        let tests = vec![
+            ("add(1, 2)", Object::Integer(3)),
+            ("not(true)", Object::Boolean(false)),
+            (r#"shout("hi")"#, Object::String("HI".to_string())),
+            ("sum([1, 2, 3])", Object::Integer(6)),
+            ("or_zero(if (false) { 1 })", Object::Integer(0)),
+            ("answer()", Object::Integer(42)),
+            ("double(21)", Object::Integer(42)),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(eval(&mut interpreter, input), Ok(expected), "{}", input);
+        }
+        assert_eq!(
+            eval(&mut interpreter, r#"keys({"b": 2, "a": 1})"#)
+                .unwrap()
+                .to_string(),
+            r#"["a", "b"]"#
+        );
+    }
+
+    #[test]
+    fn test_typed_results() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_function("pair", |a: i64, b: String| {
+            let mut h = HashMap::new();
+            h.insert("a", Object::Integer(a));
+            h.insert("b", Object::String(b));
+            h
+        });
+        interpreter.register_function("nothing", || ());
+        interpreter.register_function("range", |n: i64| (0..n).collect::<Vec<i64>>());
+
+        assert_eq!(
+            eval(&mut interpreter, r#"pair(1, "x")["b"]"#),
+            Ok(Object::String("x".to_string()))
+        );
+        assert_eq!(eval(&mut interpreter, "nothing()"), Ok(Object::Null));
+        assert_eq!(
+            eval(&mut interpreter, "range(3)").unwrap().to_string(),
+            "[0, 1, 2]"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut interpreter = Interpreter::new();
+        interpreter.register_function("add", |a: i64, b: i64| a + b);
+        interpreter.register_function("sum", |xs: Vec<i64>| xs.iter().sum::<i64>());
+        interpreter.register_function("lookup", |h: HashMap<String, i64>, k: String| {
+            h.get(&k).copied().ok_or(format!("no key {:?}", k))
+        });
+
+        let tests = vec![
+            (
+                "add(1)",
+                "add: wrong number of arguments: expected 2, got 1 at 0..6",
+            ),
+            (
+                "add(1, true)",
+                "add: argument 2: expected INTEGER, got BOOLEAN at 0..12",
+            ),
+            (
+                r#"sum([1, "2"])"#,
+                "sum: argument 1: element 1: expected INTEGER, got STRING at 0..13",
+            ),
+            (
+                r#"lookup({1: 2}, "a")"#,
+                "lookup: argument 1: key 1: expected STRING, got INTEGER at 0..19",
+            ),
+            (r#"lookup({"a": 1}, "b")"#, r#"lookup: no key "b" at 0..21"#),
+            (
+                "add(1, 9223372036854775808)",
+                "add: argument 2: integer 9223372036854775808 out of range at 0..27",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                eval(&mut interpreter, input),
+                Err(expected.to_string()),
+                "{}",
+                input
+            );
+        }
+    }
+}
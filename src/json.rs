@@ -0,0 +1,1146 @@
+//! Stable JSON form of the ast for tools outside Rust.
+//!
+//! A document looks like `{"version": 1, "program": {...}}`. Every node is an
+//! object with a `"kind"` naming the node type (the ast struct name), a
+//! `"span"` of `{"start", "end"}` character offsets into the source, and one
+//! field per child. Integer literal values are decimal strings so they
+//! survive JavaScript's 53-bit numbers. Fields are only ever added within a
+//! version; anything else bumps `JSON_VERSION`.
+
+use num_bigint::BigInt;
+use serde_json::{Map, Value};
+
+use crate::ast::{
+    ArrayLiteral, ArrayPattern, AssignExpression, BindingKind, BlockStatement, Boolean,
+    BreakStatement, CallExpression, CatchClause, ContinueStatement, DestructureStatement,
+    Expression, ExpressionStatement, FloatLiteral, ForStatement, FunctionLiteral,
+    FunctionStatement, HashLiteral, HashPattern, Identifier, IfExpression, IndexExpression,
+    InfixExpression, IntegerLiteral, LetStatement, MatchArm, MatchExpression, Pattern,
+    PrefixExpression, Program, ReturnStatement, Statement, StringLiteral, ThrowStatement,
+    TryExpression, WhileStatement,
+};
+use crate::lexer::Lexer;
+use crate::token::{Span, Token};
+
+pub const JSON_VERSION: u64 = 1;
+
+// `json!` for an object, except that the values are moved in: `json!` puts in
+// a serialized copy of each, which for a child node copies its whole tree.
+macro_rules! json_object {
+    ({ $($key:literal: $value:expr),* $(,)? }) => {{
+        let mut object = Map::new();
+        $(object.insert($key.to_string(), Value::from($value));)*
+        Value::Object(object)
+    }};
+}
+
+pub fn to_json(program: &Program) -> Value {
+    let statements: Vec<Value> = program.statements.iter().map(statement_to_json).collect();
+    json_object!({
+        "version": JSON_VERSION,
+        "program": json_object!({
+            "kind": "Program",
+            "statements": statements,
+        }),
+    })
+}
+
+pub fn to_json_string(program: &Program) -> String {
+    serde_json::to_string_pretty(&to_json(program)).unwrap()
+}
+
+fn span_to_json(span: Span) -> Value {
+    json_object!({ "start": span.start, "end": span.end })
+}
+
+// Each node kind is converted by a function of its own, so only the frame for
+// the kind at hand stays on the stack while its children are converted.
+fn statement_to_json(statement: &Statement) -> Value {
+    match statement {
+        Statement::LetStatement(let_statement) => let_statement_to_json(let_statement),
+        Statement::DestructureStatement(destructure) => destructure_statement_to_json(destructure),
+        Statement::ReturnStatement(return_statement) => return_statement_to_json(return_statement),
+        Statement::ThrowStatement(throw_statement) => throw_statement_to_json(throw_statement),
+        Statement::FunctionStatement(declaration) => function_statement_to_json(declaration),
+        Statement::ExpressionStatement(expression_statement) => {
+            expression_statement_to_json(expression_statement)
+        }
+        Statement::WhileStatement(while_statement) => while_statement_to_json(while_statement),
+        Statement::ForStatement(for_statement) => for_statement_to_json(for_statement),
+        Statement::BreakStatement(break_statement) => json_object!({
+            "kind": "BreakStatement",
+            "span": span_to_json(break_statement.span),
+        }),
+        Statement::ContinueStatement(continue_statement) => json_object!({
+            "kind": "ContinueStatement",
+            "span": span_to_json(continue_statement.span),
+        }),
+    }
+}
+
+fn let_statement_to_json(let_statement: &LetStatement) -> Value {
+    json_object!({
+        "kind": "LetStatement",
+        "span": span_to_json(let_statement.span),
+        "binding": let_statement.kind.keyword(),
+        "name": identifier_to_json(&let_statement.name),
+        "value": expression_to_json(&let_statement.value),
+    })
+}
+
+fn destructure_statement_to_json(destructure: &DestructureStatement) -> Value {
+    json_object!({
+        "kind": "DestructureStatement",
+        "span": span_to_json(destructure.span),
+        "binding": destructure.kind.keyword(),
+        "pattern": pattern_to_json(&destructure.pattern),
+        "value": expression_to_json(&destructure.value),
+    })
+}
+
+fn return_statement_to_json(return_statement: &ReturnStatement) -> Value {
+    json_object!({
+        "kind": "ReturnStatement",
+        "span": span_to_json(return_statement.span),
+        "returnValue": expression_to_json(&return_statement.return_value),
+    })
+}
+
+fn throw_statement_to_json(throw_statement: &ThrowStatement) -> Value {
+    json_object!({
+        "kind": "ThrowStatement",
+        "span": span_to_json(throw_statement.span),
+        "value": expression_to_json(&throw_statement.value),
+    })
+}
+
+fn function_statement_to_json(declaration: &FunctionStatement) -> Value {
+    json_object!({
+        "kind": "FunctionStatement",
+        "span": span_to_json(declaration.span),
+        "name": identifier_to_json(&declaration.name),
+        "function": function_literal_to_json(&declaration.function),
+    })
+}
+
+fn expression_statement_to_json(expression_statement: &ExpressionStatement) -> Value {
+    json_object!({
+        "kind": "ExpressionStatement",
+        "span": span_to_json(expression_statement.span),
+        "expression": expression_to_json(&expression_statement.expression),
+    })
+}
+
+fn while_statement_to_json(while_statement: &WhileStatement) -> Value {
+    json_object!({
+        "kind": "WhileStatement",
+        "span": span_to_json(while_statement.span),
+        "condition": expression_to_json(&while_statement.condition),
+        "body": block_to_json(&while_statement.body),
+    })
+}
+
+fn for_statement_to_json(for_statement: &ForStatement) -> Value {
+    json_object!({
+        "kind": "ForStatement",
+        "span": span_to_json(for_statement.span),
+        "key": for_statement.key.as_ref().map(identifier_to_json),
+        "value": identifier_to_json(&for_statement.value),
+        "iterable": expression_to_json(&for_statement.iterable),
+        "body": block_to_json(&for_statement.body),
+    })
+}
+
+fn block_to_json(block: &BlockStatement) -> Value {
+    let statements: Vec<Value> = block.statements.iter().map(statement_to_json).collect();
+    json_object!({
+        "kind": "BlockStatement",
+        "span": span_to_json(block.span),
+        "statements": statements,
+    })
+}
+
+fn identifier_to_json(identifier: &Identifier) -> Value {
+    json_object!({
+        "kind": "Identifier",
+        "span": span_to_json(identifier.span),
+        "value": identifier.value.as_str(),
+    })
+}
+
+fn expression_to_json(expression: &Expression) -> Value {
+    crate::with_stack(|| expression_kind_to_json(expression))
+}
+
+fn expression_kind_to_json(expression: &Expression) -> Value {
+    match expression {
+        Expression::Identifier(identifier) => identifier_to_json(identifier),
+        Expression::IntegerLiteral(integer_literal) => literal_to_json(
+            "IntegerLiteral",
+            integer_literal.span,
+            integer_literal.value.to_string(),
+        ),
+        Expression::FloatLiteral(float_literal) => {
+            literal_to_json("FloatLiteral", float_literal.span, float_literal.string())
+        }
+        Expression::Boolean(boolean) => literal_to_json("Boolean", boolean.span, boolean.value),
+        Expression::PrefixExpression(prefix) => prefix_expression_to_json(prefix),
+        Expression::AssignExpression(assign) => assign_expression_to_json(assign),
+        Expression::InfixExpression(infix) => infix_expression_to_json(infix),
+        Expression::IfExpression(if_expression) => if_expression_to_json(if_expression),
+        Expression::TryExpression(try_expression) => try_expression_to_json(try_expression),
+        Expression::FunctionLiteral(function) => function_literal_to_json(function),
+        Expression::CallExpression(call) => call_expression_to_json(call),
+        Expression::StringLiteral(string_literal) => literal_to_json(
+            "StringLiteral",
+            string_literal.span,
+            string_literal.value.as_str(),
+        ),
+        Expression::ArrayLiteral(array) => array_literal_to_json(array),
+        Expression::HashLiteral(hash) => hash_literal_to_json(hash),
+        Expression::IndexExpression(index) => index_expression_to_json(index),
+        Expression::MatchExpression(match_expression) => match_expression_to_json(match_expression),
+    }
+}
+
+fn literal_to_json(kind: &str, span: Span, value: impl Into<Value>) -> Value {
+    json_object!({
+        "kind": kind,
+        "span": span_to_json(span),
+        "value": value.into(),
+    })
+}
+
+fn prefix_expression_to_json(prefix: &PrefixExpression) -> Value {
+    json_object!({
+        "kind": "PrefixExpression",
+        "span": span_to_json(prefix.span),
+        "operator": prefix.operator.as_str(),
+        "right": expression_to_json(&prefix.right),
+    })
+}
+
+fn assign_expression_to_json(assign: &AssignExpression) -> Value {
+    json_object!({
+        "kind": "AssignExpression",
+        "span": span_to_json(assign.span),
+        "target": expression_to_json(&assign.target),
+        "operator": assign.operator.as_str(),
+        "value": expression_to_json(&assign.value),
+    })
+}
+
+fn infix_expression_to_json(infix: &InfixExpression) -> Value {
+    json_object!({
+        "kind": "InfixExpression",
+        "span": span_to_json(infix.span),
+        "left": expression_to_json(&infix.left),
+        "operator": infix.operator.as_str(),
+        "right": expression_to_json(&infix.right),
+    })
+}
+
+fn if_expression_to_json(if_expression: &IfExpression) -> Value {
+    json_object!({
+        "kind": "IfExpression",
+        "span": span_to_json(if_expression.span),
+        "condition": expression_to_json(&if_expression.condition),
+        "consequence": block_to_json(&if_expression.consequence),
+        "alternative": if_expression.alternative.as_ref().map(block_to_json),
+    })
+}
+
+fn try_expression_to_json(try_expression: &TryExpression) -> Value {
+    json_object!({
+        "kind": "TryExpression",
+        "span": span_to_json(try_expression.span),
+        "body": block_to_json(&try_expression.body),
+        "catch": try_expression.catch.as_deref().map(catch_clause_to_json),
+        "finally": try_expression.finally.as_ref().map(block_to_json),
+    })
+}
+
+fn catch_clause_to_json(catch: &CatchClause) -> Value {
+    json_object!({
+        "kind": "CatchClause",
+        "span": span_to_json(catch.span),
+        "parameter": identifier_to_json(&catch.parameter),
+        "body": block_to_json(&catch.body),
+    })
+}
+
+fn function_literal_to_json(function: &FunctionLiteral) -> Value {
+    let parameters: Vec<Value> = function.parameters.iter().map(identifier_to_json).collect();
+    json_object!({
+        "kind": "FunctionLiteral",
+        "span": span_to_json(function.span),
+        "name": function.name.as_deref(),
+        "parameters": parameters,
+        "body": block_to_json(&function.body),
+    })
+}
+
+fn call_expression_to_json(call: &CallExpression) -> Value {
+    let arguments: Vec<Value> = call.arguments.iter().map(expression_to_json).collect();
+    json_object!({
+        "kind": "CallExpression",
+        "span": span_to_json(call.span),
+        "function": expression_to_json(&call.function),
+        "arguments": arguments,
+    })
+}
+
+fn array_literal_to_json(array: &ArrayLiteral) -> Value {
+    let elements: Vec<Value> = array.elements.iter().map(expression_to_json).collect();
+    json_object!({
+        "kind": "ArrayLiteral",
+        "span": span_to_json(array.span),
+        "elements": elements,
+    })
+}
+
+fn hash_literal_to_json(hash: &HashLiteral) -> Value {
+    let pairs: Vec<Value> = hash
+        .pairs
+        .iter()
+        .map(|(key, value)| {
+            json_object!({
+                "key": expression_to_json(key),
+                "value": expression_to_json(value),
+            })
+        })
+        .collect();
+    json_object!({
+        "kind": "HashLiteral",
+        "span": span_to_json(hash.span),
+        "pairs": pairs,
+    })
+}
+
+fn index_expression_to_json(index: &IndexExpression) -> Value {
+    json_object!({
+        "kind": "IndexExpression",
+        "span": span_to_json(index.span),
+        "left": expression_to_json(&index.left),
+        "index": expression_to_json(&index.index),
+    })
+}
+
+fn match_expression_to_json(match_expression: &MatchExpression) -> Value {
+    let arms: Vec<Value> = match_expression
+        .arms
+        .iter()
+        .map(match_arm_to_json)
+        .collect();
+    json_object!({
+        "kind": "MatchExpression",
+        "span": span_to_json(match_expression.span),
+        "subject": expression_to_json(&match_expression.subject),
+        "arms": arms,
+    })
+}
+
+fn match_arm_to_json(arm: &MatchArm) -> Value {
+    json_object!({
+        "kind": "MatchArm",
+        "span": span_to_json(arm.span),
+        "pattern": pattern_to_json(&arm.pattern),
+        "guard": arm.guard.as_ref().map(expression_to_json),
+        "body": expression_to_json(&arm.body),
+    })
+}
+
+fn pattern_to_json(pattern: &Pattern) -> Value {
+    crate::with_stack(|| pattern_kind_to_json(pattern))
+}
+
+fn pattern_kind_to_json(pattern: &Pattern) -> Value {
+    match pattern {
+        Pattern::Literal(literal) => json_object!({
+            "kind": "LiteralPattern",
+            "span": span_to_json(literal.span()),
+            "value": expression_to_json(literal),
+        }),
+        Pattern::Wildcard(span) => json_object!({
+            "kind": "WildcardPattern",
+            "span": span_to_json(*span),
+        }),
+        Pattern::Binding(identifier) => json_object!({
+            "kind": "BindingPattern",
+            "span": span_to_json(identifier.span),
+            "name": identifier_to_json(identifier),
+        }),
+        Pattern::Array(array) => array_pattern_to_json(array),
+        Pattern::Hash(hash) => hash_pattern_to_json(hash),
+    }
+}
+
+fn array_pattern_to_json(array: &ArrayPattern) -> Value {
+    let elements: Vec<Value> = array.elements.iter().map(pattern_to_json).collect();
+    json_object!({
+        "kind": "ArrayPattern",
+        "span": span_to_json(array.span),
+        "elements": elements,
+        "rest": array.rest.as_deref().map(pattern_to_json),
+    })
+}
+
+fn hash_pattern_to_json(hash: &HashPattern) -> Value {
+    let pairs: Vec<Value> = hash
+        .pairs
+        .iter()
+        .map(|(key, pattern)| {
+            json_object!({
+                "key": expression_to_json(key),
+                "value": pattern_to_json(pattern),
+            })
+        })
+        .collect();
+    json_object!({
+        "kind": "HashPattern",
+        "span": span_to_json(hash.span),
+        "pairs": pairs,
+    })
+}
+
+/// Rebuilds the ast from `to_json` output. Tokens are not part of the JSON
+/// and are recreated from the node, so an expression statement that started
+/// with `(` comes back with the token of the expression inside instead.
+pub fn from_json(value: &Value) -> Result<Program, String> {
+    let document = object(value, "document")?;
+    let version = field(document, "version", "document")?
+        .as_u64()
+        .ok_or("document: \"version\" is not a number")?;
+    if version != JSON_VERSION {
+        return Err(format!(
+            "unsupported ast version {}, expected {}",
+            version, JSON_VERSION
+        ));
+    }
+
+    let program = node(field(document, "program", "document")?, "Program")?;
+    Ok(Program {
+        statements: list(program, "statements", "Program", statement_from_json)?,
+    })
+}
+
+pub fn from_json_str(input: &str) -> Result<Program, String> {
+    let value: Value = serde_json::from_str(input).map_err(|e| e.to_string())?;
+    from_json(&value)
+}
+
+fn object<'a>(value: &'a Value, context: &str) -> Result<&'a Map<String, Value>, String> {
+    value
+        .as_object()
+        .ok_or_else(|| format!("{}: expected an object, got {}", context, value))
+}
+
+fn field<'a>(
+    object: &'a Map<String, Value>,
+    name: &str,
+    context: &str,
+) -> Result<&'a Value, String> {
+    object
+        .get(name)
+        .ok_or_else(|| format!("{}: missing field {:?}", context, name))
+}
+
+fn string_field(object: &Map<String, Value>, name: &str, context: &str) -> Result<String, String> {
+    field(object, name, context)?
+        .as_str()
+        .map(|s| s.to_string())
+        .ok_or_else(|| format!("{}: {:?} is not a string", context, name))
+}
+
+fn kind_of(value: &Value) -> Result<String, String> {
+    string_field(object(value, "node")?, "kind", "node")
+}
+
+// `value` as an object of the given kind
+fn node<'a>(value: &'a Value, kind: &str) -> Result<&'a Map<String, Value>, String> {
+    let found = kind_of(value)?;
+    if found != kind {
+        return Err(format!("expected a {} node, got {}", kind, found));
+    }
+    object(value, kind)
+}
+
+fn list<T>(
+    object: &Map<String, Value>,
+    name: &str,
+    context: &str,
+    from_json: fn(&Value) -> Result<T, String>,
+) -> Result<Vec<T>, String> {
+    let values = field(object, name, context)?
+        .as_array()
+        .ok_or_else(|| format!("{}: {:?} is not an array", context, name))?;
+    // a loop rather than `collect`, whose adapters take up a lot of stack
+    // for every level of the ast in debug builds
+    let mut items = Vec::with_capacity(values.len());
+    for value in values {
+        items.push(from_json(value)?);
+    }
+    Ok(items)
+}
+
+fn span_from_json(object: &Map<String, Value>, context: &str) -> Result<Span, String> {
+    let span = self::object(field(object, "span", context)?, context)?;
+    let offset = |name| {
+        field(span, name, context)?
+            .as_u64()
+            .map(|offset| offset as usize)
+            .ok_or_else(|| format!("{}: span {:?} is not a number", context, name))
+    };
+    Ok(Span::new(offset("start")?, offset("end")?))
+}
+
+fn operator_token(operator: &str) -> Result<Token, String> {
+    let mut lexer = Lexer::new(operator);
+    let token = lexer.next_token();
+    match token {
+        Token::IDENT(_) | Token::INT(_) | Token::FLOAT(_) | Token::ILLEGAL | Token::EOF => {
+            Err(format!("invalid operator {:?}", operator))
+        }
+        _ if lexer.next_token() != Token::EOF => Err(format!("invalid operator {:?}", operator)),
+        _ => Ok(token),
+    }
+}
+
+// token the parser sees first for an expression statement
+fn first_token(mut expression: &Expression) -> Token {
+    // a loop rather than recursion down the left operands, which nest as deep
+    // as a chain of operators is long
+    loop {
+        let token = match expression {
+            Expression::Identifier(identifier) => &identifier.token,
+            Expression::IntegerLiteral(integer_literal) => &integer_literal.token,
+            Expression::FloatLiteral(float_literal) => &float_literal.token,
+            Expression::Boolean(boolean) => &boolean.token,
+            Expression::PrefixExpression(prefix) => &prefix.token,
+            Expression::InfixExpression(infix) => {
+                expression = &infix.left;
+                continue;
+            }
+            Expression::AssignExpression(assign) => {
+                expression = &assign.target;
+                continue;
+            }
+            Expression::IfExpression(if_expression) => &if_expression.token,
+            Expression::FunctionLiteral(function) => &function.token,
+            Expression::CallExpression(call) => {
+                expression = &call.function;
+                continue;
+            }
+            Expression::StringLiteral(string_literal) => &string_literal.token,
+            Expression::ArrayLiteral(array) => &array.token,
+            Expression::HashLiteral(hash) => &hash.token,
+            Expression::IndexExpression(index) => {
+                expression = &index.left;
+                continue;
+            }
+            Expression::MatchExpression(match_expression) => &match_expression.token,
+            Expression::TryExpression(try_expression) => &try_expression.token,
+        };
+        return token.clone();
+    }
+}
+
+fn statement_from_json(value: &Value) -> Result<Statement, String> {
+    let kind = kind_of(value)?;
+    let object = object(value, &kind)?;
+    let span = span_from_json(object, &kind)?;
+    match kind.as_str() {
+        "LetStatement" => let_statement_from_json(object, span),
+        "DestructureStatement" => destructure_statement_from_json(object, span),
+        "ReturnStatement" => return_statement_from_json(object, span),
+        "ThrowStatement" => throw_statement_from_json(object, span),
+        "FunctionStatement" => function_statement_from_json(object, span),
+        "ExpressionStatement" => expression_statement_from_json(object, span),
+        "WhileStatement" => while_statement_from_json(object, span),
+        "ForStatement" => for_statement_from_json(object, span),
+        "BreakStatement" => Ok(Statement::BreakStatement(BreakStatement {
+            token: Token::BREAK,
+            span,
+        })),
+        "ContinueStatement" => Ok(Statement::ContinueStatement(ContinueStatement {
+            token: Token::CONTINUE,
+            span,
+        })),
+        _ => Err(format!("unknown statement kind {:?}", kind)),
+    }
+}
+
+fn let_statement_from_json(object: &Map<String, Value>, span: Span) -> Result<Statement, String> {
+    let kind = "LetStatement";
+    let binding = binding_from_json(object, kind)?;
+    Ok(Statement::LetStatement(LetStatement {
+        token: binding_token(binding),
+        kind: binding,
+        name: identifier_from_json(field(object, "name", kind)?)?,
+        value: expression_from_json(field(object, "value", kind)?)?,
+        span,
+    }))
+}
+
+fn destructure_statement_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Statement, String> {
+    let kind = "DestructureStatement";
+    let binding = binding_from_json(object, kind)?;
+    Ok(Statement::DestructureStatement(DestructureStatement {
+        token: binding_token(binding),
+        kind: binding,
+        pattern: pattern_from_json(field(object, "pattern", kind)?)?,
+        value: expression_from_json(field(object, "value", kind)?)?,
+        span,
+    }))
+}
+
+fn return_statement_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Statement, String> {
+    Ok(Statement::ReturnStatement(ReturnStatement {
+        token: Token::RETURN,
+        return_value: expression_from_json(field(object, "returnValue", "ReturnStatement")?)?,
+        span,
+    }))
+}
+
+fn throw_statement_from_json(object: &Map<String, Value>, span: Span) -> Result<Statement, String> {
+    Ok(Statement::ThrowStatement(ThrowStatement {
+        token: Token::THROW,
+        value: expression_from_json(field(object, "value", "ThrowStatement")?)?,
+        span,
+    }))
+}
+
+fn function_statement_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Statement, String> {
+    let kind = "FunctionStatement";
+    let value = field(object, "function", kind)?;
+    let function = node(value, "FunctionLiteral")?;
+    let function = function_literal(function, span_from_json(function, "FunctionLiteral")?)?;
+    Ok(Statement::FunctionStatement(FunctionStatement {
+        token: Token::FUNCTION,
+        name: identifier_from_json(field(object, "name", kind)?)?,
+        function,
+        span,
+    }))
+}
+
+fn expression_statement_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Statement, String> {
+    let expression = expression_from_json(field(object, "expression", "ExpressionStatement")?)?;
+    Ok(Statement::ExpressionStatement(ExpressionStatement {
+        token: first_token(&expression),
+        expression,
+        span,
+    }))
+}
+
+fn while_statement_from_json(object: &Map<String, Value>, span: Span) -> Result<Statement, String> {
+    let kind = "WhileStatement";
+    Ok(Statement::WhileStatement(WhileStatement {
+        token: Token::WHILE,
+        condition: expression_from_json(field(object, "condition", kind)?)?,
+        body: block_from_json(field(object, "body", kind)?)?,
+        span,
+    }))
+}
+
+fn for_statement_from_json(object: &Map<String, Value>, span: Span) -> Result<Statement, String> {
+    let kind = "ForStatement";
+    Ok(Statement::ForStatement(ForStatement {
+        token: Token::FOR,
+        key: match field(object, "key", kind)? {
+            Value::Null => None,
+            key => Some(identifier_from_json(key)?),
+        },
+        value: identifier_from_json(field(object, "value", kind)?)?,
+        iterable: expression_from_json(field(object, "iterable", kind)?)?,
+        body: block_from_json(field(object, "body", kind)?)?,
+        span,
+    }))
+}
+
+fn binding_from_json(object: &Map<String, Value>, kind: &str) -> Result<BindingKind, String> {
+    // documents written before `const` existed have no binding
+    if object.get("binding").is_none() {
+        return Ok(BindingKind::Let);
+    }
+    match string_field(object, "binding", kind)?.as_str() {
+        "let" => Ok(BindingKind::Let),
+        "const" => Ok(BindingKind::Const),
+        other => Err(format!("{}: unknown binding {:?}", kind, other)),
+    }
+}
+
+fn binding_token(binding: BindingKind) -> Token {
+    match binding {
+        BindingKind::Let => Token::LET,
+        BindingKind::Const => Token::CONST,
+    }
+}
+
+fn block_from_json(value: &Value) -> Result<BlockStatement, String> {
+    let object = node(value, "BlockStatement")?;
+    Ok(BlockStatement {
+        token: Token::LBRACE,
+        statements: list(object, "statements", "BlockStatement", statement_from_json)?,
+        span: span_from_json(object, "BlockStatement")?,
+    })
+}
+
+fn identifier_from_json(value: &Value) -> Result<Identifier, String> {
+    let object = node(value, "Identifier")?;
+    let name = string_field(object, "value", "Identifier")?;
+    Ok(Identifier {
+        token: Token::IDENT(name.clone()),
+        value: name,
+        span: span_from_json(object, "Identifier")?,
+    })
+}
+
+fn expression_from_json(value: &Value) -> Result<Expression, String> {
+    crate::with_stack(|| expression_kind_from_json(value))
+}
+
+fn expression_kind_from_json(value: &Value) -> Result<Expression, String> {
+    let kind = kind_of(value)?;
+    let object = object(value, &kind)?;
+    let span = span_from_json(object, &kind)?;
+
+    match kind.as_str() {
+        "Identifier" => identifier_from_json(value).map(Expression::Identifier),
+        "IntegerLiteral" => integer_literal_from_json(object, span),
+        "FloatLiteral" => float_literal_from_json(object, span),
+        "Boolean" => boolean_from_json(object, span),
+        "PrefixExpression" => prefix_expression_from_json(object, span),
+        "InfixExpression" => infix_expression_from_json(object, span),
+        "AssignExpression" => assign_expression_from_json(object, span),
+        "IfExpression" => if_expression_from_json(object, span),
+        "TryExpression" => try_expression_from_json(object, span),
+        "FunctionLiteral" => function_literal_from_json(object, span),
+        "CallExpression" => call_expression_from_json(object, span),
+        "StringLiteral" => string_literal_from_json(object, span),
+        "ArrayLiteral" => array_literal_from_json(object, span),
+        "HashLiteral" => hash_literal_from_json(object, span),
+        "IndexExpression" => index_expression_from_json(object, span),
+        "MatchExpression" => match_expression_from_json(object, span),
+        _ => Err(format!("unknown expression kind {:?}", kind)),
+    }
+}
+
+// the expression in field `name` of a `kind` node
+fn child_from_json(
+    object: &Map<String, Value>,
+    name: &str,
+    kind: &str,
+) -> Result<Box<Expression>, String> {
+    Ok(Box::new(expression_from_json(field(object, name, kind)?)?))
+}
+
+fn integer_literal_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    let literal = string_field(object, "value", "IntegerLiteral")?;
+    let value: BigInt = literal
+        .parse()
+        .map_err(|_| format!("IntegerLiteral: invalid value {:?}", literal))?;
+    Ok(Expression::IntegerLiteral(IntegerLiteral {
+        token: Token::INT(value.clone()),
+        value,
+        span,
+    }))
+}
+
+fn float_literal_from_json(object: &Map<String, Value>, span: Span) -> Result<Expression, String> {
+    let literal = string_field(object, "value", "FloatLiteral")?;
+    let value: f64 = literal
+        .parse()
+        .map_err(|_| format!("FloatLiteral: invalid value {:?}", literal))?;
+    Ok(Expression::FloatLiteral(FloatLiteral {
+        token: Token::FLOAT(value),
+        value,
+        span,
+    }))
+}
+
+fn boolean_from_json(object: &Map<String, Value>, span: Span) -> Result<Expression, String> {
+    let value = field(object, "value", "Boolean")?
+        .as_bool()
+        .ok_or("Boolean: \"value\" is not a boolean")?;
+    Ok(Expression::Boolean(Boolean {
+        token: if value { Token::TRUE } else { Token::FALSE },
+        value,
+        span,
+    }))
+}
+
+fn string_literal_from_json(object: &Map<String, Value>, span: Span) -> Result<Expression, String> {
+    let value = string_field(object, "value", "StringLiteral")?;
+    Ok(Expression::StringLiteral(StringLiteral {
+        token: Token::STRING(value.clone()),
+        value,
+        span,
+    }))
+}
+
+fn array_literal_from_json(object: &Map<String, Value>, span: Span) -> Result<Expression, String> {
+    Ok(Expression::ArrayLiteral(ArrayLiteral {
+        token: Token::LBRACKET,
+        elements: list(object, "elements", "ArrayLiteral", expression_from_json)?,
+        span,
+    }))
+}
+
+fn hash_literal_from_json(object: &Map<String, Value>, span: Span) -> Result<Expression, String> {
+    Ok(Expression::HashLiteral(HashLiteral {
+        token: Token::LBRACE,
+        pairs: list(object, "pairs", "HashLiteral", hash_pair_from_json)?,
+        span,
+    }))
+}
+
+fn prefix_expression_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    let kind = "PrefixExpression";
+    let operator = string_field(object, "operator", kind)?;
+    Ok(Expression::PrefixExpression(PrefixExpression {
+        token: operator_token(&operator)?,
+        operator,
+        right: child_from_json(object, "right", kind)?,
+        span,
+    }))
+}
+
+fn infix_expression_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    let kind = "InfixExpression";
+    let operator = string_field(object, "operator", kind)?;
+    Ok(Expression::InfixExpression(InfixExpression {
+        token: operator_token(&operator)?,
+        left: child_from_json(object, "left", kind)?,
+        operator,
+        right: child_from_json(object, "right", kind)?,
+        span,
+    }))
+}
+
+fn assign_expression_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    let kind = "AssignExpression";
+    let operator = string_field(object, "operator", kind)?;
+    Ok(Expression::AssignExpression(AssignExpression {
+        token: operator_token(&operator)?,
+        target: child_from_json(object, "target", kind)?,
+        operator,
+        value: child_from_json(object, "value", kind)?,
+        span,
+    }))
+}
+
+fn if_expression_from_json(object: &Map<String, Value>, span: Span) -> Result<Expression, String> {
+    let kind = "IfExpression";
+    let alternative = match field(object, "alternative", kind)? {
+        Value::Null => None,
+        alternative => Some(block_from_json(alternative)?),
+    };
+    Ok(Expression::IfExpression(IfExpression {
+        token: Token::IF,
+        condition: child_from_json(object, "condition", kind)?,
+        consequence: block_from_json(field(object, "consequence", kind)?)?,
+        alternative,
+        span,
+    }))
+}
+
+fn try_expression_from_json(object: &Map<String, Value>, span: Span) -> Result<Expression, String> {
+    let kind = "TryExpression";
+    let catch = match field(object, "catch", kind)? {
+        Value::Null => None,
+        catch => Some(Box::new(catch_clause_from_json(catch)?)),
+    };
+    let finally = match field(object, "finally", kind)? {
+        Value::Null => None,
+        finally => Some(block_from_json(finally)?),
+    };
+    Ok(Expression::TryExpression(TryExpression {
+        token: Token::TRY,
+        body: block_from_json(field(object, "body", kind)?)?,
+        catch,
+        finally,
+        span,
+    }))
+}
+
+fn function_literal_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    function_literal(object, span).map(Expression::FunctionLiteral)
+}
+
+fn function_literal(object: &Map<String, Value>, span: Span) -> Result<FunctionLiteral, String> {
+    let kind = "FunctionLiteral";
+    Ok(FunctionLiteral {
+        token: Token::FUNCTION,
+        parameters: list(object, "parameters", kind, identifier_from_json)?,
+        body: block_from_json(field(object, "body", kind)?)?,
+        span,
+        name: match object.get("name") {
+            None | Some(Value::Null) => None,
+            Some(_) => Some(string_field(object, "name", kind)?),
+        },
+    })
+}
+
+fn call_expression_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    let kind = "CallExpression";
+    Ok(Expression::CallExpression(CallExpression {
+        token: Token::LPAREN,
+        function: child_from_json(object, "function", kind)?,
+        arguments: list(object, "arguments", kind, expression_from_json)?,
+        span,
+    }))
+}
+
+fn index_expression_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    let kind = "IndexExpression";
+    Ok(Expression::IndexExpression(IndexExpression {
+        token: Token::LBRACKET,
+        left: child_from_json(object, "left", kind)?,
+        index: child_from_json(object, "index", kind)?,
+        span,
+    }))
+}
+
+fn match_expression_from_json(
+    object: &Map<String, Value>,
+    span: Span,
+) -> Result<Expression, String> {
+    let kind = "MatchExpression";
+    Ok(Expression::MatchExpression(MatchExpression {
+        token: Token::MATCH,
+        subject: child_from_json(object, "subject", kind)?,
+        arms: list(object, "arms", kind, match_arm_from_json)?,
+        span,
+    }))
+}
+
+fn catch_clause_from_json(value: &Value) -> Result<CatchClause, String> {
+    let object = node(value, "CatchClause")?;
+    Ok(CatchClause {
+        token: Token::CATCH,
+        parameter: identifier_from_json(field(object, "parameter", "CatchClause")?)?,
+        body: block_from_json(field(object, "body", "CatchClause")?)?,
+        span: span_from_json(object, "CatchClause")?,
+    })
+}
+
+fn match_arm_from_json(value: &Value) -> Result<MatchArm, String> {
+    let object = node(value, "MatchArm")?;
+    Ok(MatchArm {
+        pattern: pattern_from_json(field(object, "pattern", "MatchArm")?)?,
+        guard: match field(object, "guard", "MatchArm")? {
+            Value::Null => None,
+            guard => Some(expression_from_json(guard)?),
+        },
+        body: expression_from_json(field(object, "body", "MatchArm")?)?,
+        span: span_from_json(object, "MatchArm")?,
+    })
+}
+
+fn pattern_from_json(value: &Value) -> Result<Pattern, String> {
+    crate::with_stack(|| pattern_kind_from_json(value))
+}
+
+fn pattern_kind_from_json(value: &Value) -> Result<Pattern, String> {
+    let kind = kind_of(value)?;
+    let object = object(value, &kind)?;
+    let span = span_from_json(object, &kind)?;
+    match kind.as_str() {
+        "LiteralPattern" => Ok(Pattern::Literal(expression_from_json(field(
+            object, "value", &kind,
+        )?)?)),
+        "WildcardPattern" => Ok(Pattern::Wildcard(span)),
+        "BindingPattern" => Ok(Pattern::Binding(identifier_from_json(field(
+            object, "name", &kind,
+        )?)?)),
+        "ArrayPattern" => Ok(Pattern::Array(ArrayPattern {
+            token: Token::LBRACKET,
+            elements: list(object, "elements", &kind, pattern_from_json)?,
+            rest: match object.get("rest") {
+                None | Some(Value::Null) => None,
+                Some(rest) => Some(Box::new(pattern_from_json(rest)?)),
+            },
+            span,
+        })),
+        "HashPattern" => Ok(Pattern::Hash(HashPattern {
+            token: Token::LBRACE,
+            pairs: list(object, "pairs", &kind, hash_pattern_pair_from_json)?,
+            span,
+        })),
+        _ => Err(format!("unknown pattern kind {:?}", kind)),
+    }
+}
+
+fn hash_pattern_pair_from_json(value: &Value) -> Result<(Expression, Pattern), String> {
+    let pair = object(value, "HashPattern pair")?;
+    Ok((
+        expression_from_json(field(pair, "key", "HashPattern pair")?)?,
+        pattern_from_json(field(pair, "value", "HashPattern pair")?)?,
+    ))
+}
+
+fn hash_pair_from_json(value: &Value) -> Result<(Expression, Expression), String> {
+    let pair = object(value, "HashLiteral pair")?;
+    Ok((
+        expression_from_json(field(pair, "key", "HashLiteral pair")?)?,
+        expression_from_json(field(pair, "value", "HashLiteral pair")?)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use serde_json::json;
+    use pretty_assertions::assert_eq;
+
+    fn parse(input: &str) -> Program {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        match parser.parse_program() {
+            Ok(program) => program,
+            Err(e) => panic!("parse_program() returned an error: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_to_json() {
+        let program = parse("let x = -1 + y;");
+
+        let expected = json!({
+            "version": 1,
+            "program": {
+                "kind": "Program",
+                "statements": [{
+                    "kind": "LetStatement",
+                    "span": {"start": 0, "end": 15},
+                    "binding": "let",
+                    "name": {
+                        "kind": "Identifier",
+                        "span": {"start": 4, "end": 5},
+                        "value": "x",
+                    },
+                    "value": {
+                        "kind": "InfixExpression",
+                        "span": {"start": 8, "end": 14},
+                        "left": {
+                            "kind": "PrefixExpression",
+                            "span": {"start": 8, "end": 10},
+                            "operator": "-",
+                            "right": {
+                                "kind": "IntegerLiteral",
+                                "span": {"start": 9, "end": 10},
+                                "value": "1",
+                            },
+                        },
+                        "operator": "+",
+                        "right": {
+                            "kind": "Identifier",
+                            "span": {"start": 13, "end": 14},
+                            "value": "y",
+                        },
+                    },
+                }],
+            },
+        });
+
+        assert_eq!(to_json(&program), expected);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = r#"
+let max = fn(a, b) { if (a > b) { a } else { return b; } };
+max(1, 2 * 3) == !false;
+if (true) { 1 };
+fn() {}();
+let h = {"a\n": [1, 2][0], true: "b"};
+h["a\n"];
+99999999999999999999;
+3.5 * 1e-9;
+while (x) { break; continue; }
+for (x in xs) { x }
+for (k, v in {}) {}
+xs[1..=2];
+xs[0] += x = 1;
+const c = 1;
+match (x) { 1 => a, [_, b] if b => b, {name, -1: [c]} => c }
+let [a, ...b] = c;
+const {d, "e": [...f]} = g;
+throw "x";
+try { f() } catch (e) { throw e } finally {}
+try {} finally { g() };
+fn add(a, b) { a + b }
+let named = fn() { add(1, 2) };
+"#;
+        let program = parse(input);
+
+        let json = to_json_string(&program);
+        assert_eq!(from_json_str(&json).unwrap(), program);
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let tests = vec![
+            (
+                r#"{"version": 2, "program": {}}"#,
+                "unsupported ast version 2, expected 1",
+            ),
+            (
+                r#"{"version": 1, "program": {"kind": "Program"}}"#,
+                "Program: missing field \"statements\"",
+            ),
+            (
+                r#"{"version": 1, "program": {"kind": "Program", "statements": [
+                    {"kind": "GotoStatement", "span": {"start": 0, "end": 0}}
+                ]}}"#,
+                "unknown statement kind \"GotoStatement\"",
+            ),
+            (
+                r#"{"version": 1, "program": {"kind": "Program", "statements": [
+                    {"kind": "ExpressionStatement", "span": {"start": 0, "end": 0},
+                     "expression": {"kind": "PrefixExpression", "span": {"start": 0, "end": 0},
+                                    "operator": "x", "right": null}}
+                ]}}"#,
+                "invalid operator \"x\"",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(from_json_str(input).err().unwrap(), expected);
+        }
+    }
+}
@@ -1,23 +1,45 @@
+use std::fmt;
+
+use num_bigint::BigInt;
+
+use crate::token::Span;
+use crate::token::SyntaxToken;
 use crate::token::Token;
+use crate::token::Trivia;
+use crate::token::TriviaKind;
 use crate::token::KEYWORDS;
 
 use Token::*;
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
 #[derive(Debug)]
 pub struct Lexer {
-    input: String,
+    input: Vec<char>,
     position: usize,
     read_position: usize,
     ch: char,
+    errors: Vec<LexError>,
 }
 
 impl Lexer {
     pub fn new(input: &str) -> Lexer {
         let mut l = Lexer {
-            input: input.to_string(),
+            input: input.chars().collect(),
             position: 0,
             read_position: 0,
             ch: '\0',
+            errors: vec![],
         };
         l.read_char();
         l
@@ -27,7 +49,7 @@ impl Lexer {
         if self.read_position >= self.input.len() {
             self.ch = '\0';
         } else {
-            self.ch = self.input.chars().nth(self.read_position).unwrap();
+            self.ch = self.input[self.read_position];
         }
         self.position = self.read_position;
         self.read_position += 1;
@@ -37,26 +59,57 @@ impl Lexer {
         if self.read_position >= self.input.len() {
             '\0'
         } else {
-            self.input.chars().nth(self.read_position).unwrap()
+            self.input[self.read_position]
         }
     }
 
     pub fn next_token(&mut self) -> Token {
+        self.next_syntax_token().kind
+    }
+
+    /// Like `next_token`, but keeps the token's source text and the
+    /// whitespace and comments in front of it.
+    pub fn next_syntax_token(&mut self) -> SyntaxToken {
+        let leading_trivia = self.read_trivia();
+        let start = self.position.min(self.input.len());
+        let kind = self.read_token();
+        let end = self.position.min(self.input.len());
+        SyntaxToken {
+            kind,
+            text: self.slice(start, end),
+            span: Span::new(start, end),
+            leading_trivia,
+        }
+    }
+
+    fn read_token(&mut self) -> Token {
         let tok: Token;
-        self.skip_whitespace();
         match self.ch {
-            '=' => {
-                if self.peek_char() == '=' {
+            '=' => match self.peek_char() {
+                '=' => {
                     self.read_char();
                     tok = EQ;
-                } else {
-                    tok = ASSIGN;
                 }
-            }
+                '>' => {
+                    self.read_char();
+                    tok = FatArrow;
+                }
+                _ => tok = ASSIGN,
+            },
             ';' => tok = SEMICOLON,
             '(' => tok = LPAREN,
             ')' => tok = RPAREN,
             ',' => tok = COMMA,
+            ':' => tok = COLON,
+            '+' | '-' | '*' | '/' if self.peek_char() == '=' => {
+                tok = match self.ch {
+                    '+' => PlusEQ,
+                    '-' => MinusEQ,
+                    '*' => AsteriskEQ,
+                    _ => SlashEQ,
+                };
+                self.read_char();
+            }
             '+' => tok = PLUS,
             '-' => tok = MINUS,
             '!' => {
@@ -69,10 +122,52 @@ impl Lexer {
             }
             '*' => tok = ASTERISK,
             '/' => tok = SLASH,
-            '<' => tok = LT,
-            '>' => tok = GT,
+            '%' => tok = PERCENT,
+            '<' => match self.peek_char() {
+                '=' => {
+                    self.read_char();
+                    tok = LtEQ;
+                }
+                '<' => {
+                    self.read_char();
+                    tok = LSHIFT;
+                }
+                _ => tok = LT,
+            },
+            '>' => match self.peek_char() {
+                '=' => {
+                    self.read_char();
+                    tok = GtEQ;
+                }
+                '>' => {
+                    self.read_char();
+                    tok = RSHIFT;
+                }
+                _ => tok = GT,
+            },
+            '&' => {
+                if self.peek_char() == '&' {
+                    self.read_char();
+                    tok = AND;
+                } else {
+                    tok = AMPERSAND;
+                }
+            }
+            '|' => {
+                if self.peek_char() == '|' {
+                    self.read_char();
+                    tok = OR;
+                } else {
+                    tok = PIPE;
+                }
+            }
+            '^' => tok = CARET,
+            '~' => tok = TILDE,
             '{' => tok = LBRACE,
             '}' => tok = RBRACE,
+            '[' => tok = LBRACKET,
+            ']' => tok = RBRACKET,
+            '"' => return self.read_string(),
             '\0' => tok = EOF,
             'a'..='z' | 'A'..='Z' | '_' => {
                 let ident = self.read_identifier();
@@ -81,9 +176,32 @@ impl Lexer {
                 }
                 return IDENT(ident);
             }
-            '0'..='9' => {
-                let int = self.read_number();
-                return INT(int);
+            '0'..='9' => return self.read_number(),
+            '.' if self.peek_char() == '.' => {
+                self.read_char();
+                match self.peek_char() {
+                    '=' => {
+                        self.read_char();
+                        tok = DotDotEQ;
+                    }
+                    '.' => {
+                        self.read_char();
+                        tok = ELLIPSIS;
+                    }
+                    _ => tok = DOTDOT,
+                }
+            }
+            '.' if self.peek_char().is_ascii_digit() => {
+                let start = self.position;
+                self.read_char();
+                self.read_number();
+                self.errors.push(LexError {
+                    message: "float literal needs a digit before the decimal point".to_string(),
+                    span: Span::new(start, self.position),
+                });
+                // recover as if the leading zero had been written
+                let literal = format!("0{}", self.slice(start, self.position));
+                return FLOAT(literal.parse().unwrap_or(0.0));
             }
             _ => tok = ILLEGAL,
         }
@@ -91,14 +209,199 @@ impl Lexer {
         tok
     }
 
-    fn read_number(&mut self) -> i64 {
+    // Reads a string literal up to and including its closing quote, with
+    // `\"`, `\\`, `\n`, `\r` and `\t` escapes.
+    fn read_string(&mut self) -> Token {
+        let start = self.position;
+        let mut value = String::new();
+        loop {
+            self.read_char();
+            match self.ch {
+                '"' => break,
+                '\0' => {
+                    self.errors.push(LexError {
+                        message: "unterminated string".to_string(),
+                        span: Span::new(start, self.position),
+                    });
+                    return STRING(value);
+                }
+                '\\' => {
+                    self.read_char();
+                    match self.ch {
+                        '"' => value.push('"'),
+                        '\\' => value.push('\\'),
+                        'n' => value.push('\n'),
+                        'r' => value.push('\r'),
+                        't' => value.push('\t'),
+                        '\0' => continue,
+                        c => {
+                            self.errors.push(LexError {
+                                message: format!("unknown escape sequence \\{}", c),
+                                span: Span::new(self.position - 1, self.position + 1),
+                            });
+                            value.push(c);
+                        }
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+        self.read_char();
+        STRING(value)
+    }
+
+    // Reads an integer, or a float if the digits are followed by a fraction
+    // (`3.14`) or an exponent (`1e-9`). A `.` without a digit after it is
+    // left alone, so `1.` is an integer followed by a dot. Digits may be
+    // separated by `_`.
+    fn read_number(&mut self) -> Token {
         let position = self.position;
-        while self.ch.is_numeric() {
+        if self.ch == '0' {
+            match self.peek_char() {
+                'x' | 'X' => return self.read_radix_integer(16, "hexadecimal"),
+                'o' | 'O' => return self.read_radix_integer(8, "octal"),
+                'b' | 'B' => return self.read_radix_integer(2, "binary"),
+                _ => {}
+            }
+        }
+        self.read_digits();
+        let mut is_float = false;
+        if self.ch == '.' && self.peek_char().is_ascii_digit() {
+            is_float = true;
+            self.read_char();
+            self.read_digits();
+        }
+        if self.ch == 'e' || self.ch == 'E' {
+            is_float = true;
+            self.read_char();
+            if self.ch == '+' || self.ch == '-' {
+                self.read_char();
+            }
+            if !self.ch.is_ascii_digit() {
+                self.errors.push(LexError {
+                    message: "float literal is missing exponent digits".to_string(),
+                    span: Span::new(position, self.position),
+                });
+                return FLOAT(0.0);
+            }
+            self.read_digits();
+        }
+        let end = self.position;
+        // letters right after the digits belong to the literal, so `3x` is
+        // reported rather than read as `3` and `x`
+        if self.ch.is_ascii_alphabetic() {
+            let start = self.position;
+            while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+                self.read_char();
+            }
+            self.errors.push(LexError {
+                message: format!(
+                    "invalid suffix {:?} on number literal",
+                    self.slice(start, self.position)
+                ),
+                span: Span::new(start, self.position),
+            });
+        }
+
+        let literal = self.slice(position, end).replace('_', "");
+        if !is_float {
+            return INT(literal.parse().expect("not a number!"));
+        }
+        let value: f64 = literal.parse().expect("not a float!");
+        if value.is_infinite() {
+            self.errors.push(LexError {
+                message: "float literal out of range".to_string(),
+                span: Span::new(position, end),
+            });
+        }
+        FLOAT(value)
+    }
+
+    // Reads decimal digits and `_` separators, which may not come last.
+    fn read_digits(&mut self) {
+        while self.ch.is_ascii_digit() || self.ch == '_' {
             self.read_char();
         }
-        self.input[position..self.position]
-            .parse()
-            .expect("not a number!")
+        if self.input[self.position - 1] == '_' {
+            self.errors.push(LexError {
+                message: "number literal has a trailing underscore".to_string(),
+                span: Span::new(self.position - 1, self.position),
+            });
+        }
+    }
+
+    // Reads `0x`, `0o` or `0b` and the digits after it. Any letters or
+    // digits that follow belong to the literal, so `0xFG` is one malformed
+    // literal rather than `0xF` and `G`.
+    fn read_radix_integer(&mut self, radix: u32, name: &str) -> Token {
+        let start = self.position;
+        self.read_char();
+        self.read_char();
+        let mut digits = String::new();
+        let mut error = None;
+        while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+            if self.ch.is_digit(radix) {
+                digits.push(self.ch);
+            } else if self.ch != '_' && error.is_none() {
+                error = Some(LexError {
+                    message: format!("invalid digit {:?} in {} literal", self.ch, name),
+                    span: Span::new(self.position, self.position + 1),
+                });
+            }
+            self.read_char();
+        }
+
+        if error.is_none() && digits.is_empty() {
+            error = Some(LexError {
+                message: format!("{} literal has no digits", name),
+                span: Span::new(start, self.position),
+            });
+        } else if error.is_none() && self.input[self.position - 1] == '_' {
+            error = Some(LexError {
+                message: "number literal has a trailing underscore".to_string(),
+                span: Span::new(self.position - 1, self.position),
+            });
+        }
+        if let Some(error) = error {
+            self.errors.push(error);
+            return INT(BigInt::default());
+        }
+        INT(BigInt::parse_bytes(digits.as_bytes(), radix).expect("not a number!"))
+    }
+
+    fn slice(&self, start: usize, end: usize) -> String {
+        let end = end.min(self.input.len());
+        self.input[start.min(end)..end].iter().collect()
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    fn read_trivia(&mut self) -> Vec<Trivia> {
+        let mut trivia = vec![];
+        loop {
+            let start = self.position;
+            let kind = match self.ch {
+                ' ' | '\t' | '\n' | '\r' => {
+                    self.skip_whitespace();
+                    TriviaKind::Whitespace
+                }
+                '/' if self.peek_char() == '/' => {
+                    self.skip_line_comment();
+                    TriviaKind::LineComment
+                }
+                '/' if self.peek_char() == '*' => {
+                    self.skip_block_comment();
+                    TriviaKind::BlockComment
+                }
+                _ => return trivia,
+            };
+            trivia.push(Trivia {
+                kind,
+                text: self.slice(start, self.position),
+            });
+        }
     }
 
     fn skip_whitespace(&mut self) {
@@ -107,14 +410,52 @@ impl Lexer {
         }
     }
 
+    fn skip_line_comment(&mut self) {
+        while self.ch != '\n' && self.ch != '\0' {
+            self.read_char();
+        }
+    }
+
+    // block comments nest, so `/* a /* b */ c */` is a single comment
+    fn skip_block_comment(&mut self) {
+        let start = self.position;
+        let mut depth = 0;
+        loop {
+            match (self.ch, self.peek_char()) {
+                ('/', '*') => {
+                    depth += 1;
+                    self.read_char();
+                    self.read_char();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.read_char();
+                    self.read_char();
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                ('\0', _) => {
+                    self.errors.push(LexError {
+                        message: "unterminated block comment".to_string(),
+                        span: Span::new(start, start + 2),
+                    });
+                    return;
+                }
+                _ => self.read_char(),
+            }
+        }
+    }
+
     fn read_identifier(&mut self) -> String {
         let position = self.position;
         while self.ch.is_alphanumeric() || self.ch == '_' {
             self.read_char();
         }
-        self.input[position..self.position].to_string()
+        self.slice(position, self.position)
     }
 
+    #[cfg(test)]
     fn print_current_position(&self) {
         let mut line = 0;
         let mut line_start = 0;
@@ -122,12 +463,12 @@ impl Lexer {
         let mut has_position = false;
         let mut column = 0;
         let mut last_column = 0;
-        for (i, c) in self.input.chars().enumerate() {
+        for (i, c) in self.input.iter().enumerate() {
             if i == self.position {
                 has_position = true;
                 last_column = column;
             }
-            if c == '\n' {
+            if *c == '\n' {
                 if has_position {
                     line_end = i;
                     break;
@@ -141,9 +482,7 @@ impl Lexer {
         }
         println!("lexer: {:#?}", self);
         println!("input: ({}:{})", line, column);
-        unsafe {
-            println!("{}", self.input.get_unchecked(line_start..line_end));
-        }
+        println!("{}", self.slice(line_start, line_end));
         println!("{}^", " ".repeat(last_column));
         println!("{}|", " ".repeat(last_column));
     }
@@ -167,6 +506,411 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_two_character_operators() {
+        let input = "a <= b >= c % d && e || f & g | h ^ ~i << j >> k";
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            IDENT("a".to_string()),
+            LtEQ,
+            IDENT("b".to_string()),
+            GtEQ,
+            IDENT("c".to_string()),
+            PERCENT,
+            IDENT("d".to_string()),
+            AND,
+            IDENT("e".to_string()),
+            OR,
+            IDENT("f".to_string()),
+            AMPERSAND,
+            IDENT("g".to_string()),
+            PIPE,
+            IDENT("h".to_string()),
+            CARET,
+            TILDE,
+            IDENT("i".to_string()),
+            LSHIFT,
+            IDENT("j".to_string()),
+            RSHIFT,
+            IDENT("k".to_string()),
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+    }
+
+    #[test]
+    fn test_loop_keywords() {
+        let mut lexer = Lexer::new("while (x) { break; continue } for (k in h)");
+        let expected = vec![
+            WHILE,
+            LPAREN,
+            IDENT("x".to_string()),
+            RPAREN,
+            LBRACE,
+            BREAK,
+            SEMICOLON,
+            CONTINUE,
+            RBRACE,
+            FOR,
+            LPAREN,
+            IDENT("k".to_string()),
+            IN,
+            IDENT("h".to_string()),
+            RPAREN,
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+    }
+
+    #[test]
+    fn test_assignment_operators() {
+        let mut lexer = Lexer::new("const a = b += c -= d *= e /= f // g /= h");
+        let expected = vec![
+            CONST,
+            IDENT("a".to_string()),
+            ASSIGN,
+            IDENT("b".to_string()),
+            PlusEQ,
+            IDENT("c".to_string()),
+            MinusEQ,
+            IDENT("d".to_string()),
+            AsteriskEQ,
+            IDENT("e".to_string()),
+            SlashEQ,
+            IDENT("f".to_string()),
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+    }
+
+    #[test]
+    fn test_match() {
+        let mut lexer = Lexer::new("match (x) { _ => a == b, y => c }");
+        let expected = vec![
+            MATCH,
+            LPAREN,
+            IDENT("x".to_string()),
+            RPAREN,
+            LBRACE,
+            IDENT("_".to_string()),
+            FatArrow,
+            IDENT("a".to_string()),
+            EQ,
+            IDENT("b".to_string()),
+            COMMA,
+            IDENT("y".to_string()),
+            FatArrow,
+            IDENT("c".to_string()),
+            RBRACE,
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+    }
+
+    #[test]
+    fn test_error_keywords() {
+        let mut lexer = Lexer::new("try catch finally throw tries");
+        let expected = vec![TRY, CATCH, FINALLY, THROW, IDENT("tries".to_string()), EOF];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        let mut lexer = Lexer::new("1..3 a..=b 1.5..2 [...r]");
+        let expected = vec![
+            INT(1.into()),
+            DOTDOT,
+            INT(3.into()),
+            IDENT("a".to_string()),
+            DotDotEQ,
+            IDENT("b".to_string()),
+            FLOAT(1.5),
+            DOTDOT,
+            INT(2.into()),
+            LBRACKET,
+            ELLIPSIS,
+            IDENT("r".to_string()),
+            RBRACKET,
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+    }
+
+    #[test]
+    fn test_floats() {
+        let input = "3.25 1e-9 2.5E+3 7e2 1. 1.x";
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            FLOAT(3.25),
+            FLOAT(1e-9),
+            FLOAT(2500.0),
+            FLOAT(700.0),
+            INT(1.into()),
+            ILLEGAL,
+            INT(1.into()),
+            ILLEGAL,
+            IDENT("x".to_string()),
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+        assert!(lexer.errors().is_empty());
+    }
+
+    #[test]
+    fn test_float_errors() {
+        let tests = vec![
+            (
+                ".5",
+                FLOAT(0.5),
+                "float literal needs a digit before the decimal point at 0..2",
+            ),
+            (
+                "1e",
+                FLOAT(0.0),
+                "float literal is missing exponent digits at 0..2",
+            ),
+            (
+                "2e+x",
+                FLOAT(0.0),
+                "float literal is missing exponent digits at 0..3",
+            ),
+            (
+                "1e999",
+                FLOAT(f64::INFINITY),
+                "float literal out of range at 0..5",
+            ),
+        ];
+        for (input, token, error) in tests {
+            let mut lexer = Lexer::new(input);
+            assert_eq!(lexer.next_token(), token, "{}", input);
+            let errors: Vec<String> = lexer.errors().iter().map(|e| e.to_string()).collect();
+            assert_eq!(errors, vec![error.to_string()], "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_integer_literal_forms() {
+        let input = "0xFF 0Xff 0o17 0b1010 1_000_000 0x_dead_beef 1_0.2_5 007";
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            INT(255.into()),
+            INT(255.into()),
+            INT(15.into()),
+            INT(10.into()),
+            INT(1_000_000.into()),
+            INT(0xdead_beef_u32.into()),
+            FLOAT(10.25),
+            INT(7.into()),
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+        assert!(lexer.errors().is_empty());
+    }
+
+    #[test]
+    fn test_integer_literal_errors() {
+        let tests = vec![
+            ("0xZZ", "invalid digit 'Z' in hexadecimal literal at 2..3"),
+            ("0o19", "invalid digit '9' in octal literal at 3..4"),
+            ("0b102", "invalid digit '2' in binary literal at 4..5"),
+            ("0x", "hexadecimal literal has no digits at 0..2"),
+            ("0b__", "binary literal has no digits at 0..4"),
+            ("0xF_", "number literal has a trailing underscore at 3..4"),
+            ("1_000_", "number literal has a trailing underscore at 5..6"),
+            ("1_.5", "number literal has a trailing underscore at 1..2"),
+            ("3x", "invalid suffix \"x\" on number literal at 1..2"),
+            ("10abc", "invalid suffix \"abc\" on number literal at 2..5"),
+            ("1.5f32", "invalid suffix \"f32\" on number literal at 3..6"),
+            ("2e3q", "invalid suffix \"q\" on number literal at 3..4"),
+        ];
+        for (input, error) in tests {
+            let mut lexer = Lexer::new(input);
+            lexer.next_token();
+            assert_eq!(lexer.next_token(), EOF, "{}", input);
+            let errors: Vec<String> = lexer.errors().iter().map(|e| e.to_string()).collect();
+            assert_eq!(errors, vec![error.to_string()], "{}", input);
+        }
+
+        // non-ASCII numerals are not digits
+        let mut lexer = Lexer::new("1٣");
+        assert_eq!(lexer.next_token(), INT(1.into()));
+        assert_eq!(lexer.next_token(), ILLEGAL);
+    }
+
+    #[test]
+    fn test_big_integers() {
+        let mut lexer = Lexer::new("18446744073709551616");
+        assert_eq!(
+            lexer.next_token(),
+            INT("18446744073709551616".parse().unwrap())
+        );
+        assert_eq!(lexer.next_token(), EOF);
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = r#"
+        // a line comment
+        let x = 5; // trailing comment
+        /* a block
+           comment */
+        x / /* nested /* block */ comment */ 2;
+        "#;
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            LET,
+            IDENT("x".to_string()),
+            ASSIGN,
+            INT(5.into()),
+            SEMICOLON,
+            IDENT("x".to_string()),
+            SLASH,
+            INT(2.into()),
+            SEMICOLON,
+            EOF,
+        ];
+        for expected_token in expected {
+            let token = lexer.next_token();
+            assert_eq!(token, expected_token);
+        }
+        assert_eq!(lexer.errors(), &[]);
+    }
+
+    #[test]
+    fn test_strings() {
+        let input = r#""foobar" "foo bar" "" "a\"b\\c\nd" [1, 2]; {"k": 1}"#;
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            STRING("foobar".to_string()),
+            STRING("foo bar".to_string()),
+            STRING("".to_string()),
+            STRING("a\"b\\c\nd".to_string()),
+            LBRACKET,
+            INT(1.into()),
+            COMMA,
+            INT(2.into()),
+            RBRACKET,
+            SEMICOLON,
+            LBRACE,
+            STRING("k".to_string()),
+            COLON,
+            INT(1.into()),
+            RBRACE,
+            EOF,
+        ];
+        for expected_token in expected {
+            assert_eq!(lexer.next_token(), expected_token);
+        }
+        assert_eq!(lexer.errors(), &[]);
+    }
+
+    #[test]
+    fn test_string_errors() {
+        let mut lexer = Lexer::new(r#""a\qb" "open"#);
+        assert_eq!(lexer.next_token(), STRING("aqb".to_string()));
+        assert_eq!(lexer.next_token(), STRING("open".to_string()));
+        assert_eq!(lexer.next_token(), EOF);
+        assert_eq!(
+            lexer.errors(),
+            &[
+                LexError {
+                    message: "unknown escape sequence \\q".to_string(),
+                    span: Span::new(2, 4),
+                },
+                LexError {
+                    message: "unterminated string".to_string(),
+                    span: Span::new(7, 12),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let input = "let x = 5; /* never /* closed */";
+        let mut lexer = Lexer::new(input);
+        let expected = vec![
+            LET,
+            IDENT("x".to_string()),
+            ASSIGN,
+            INT(5.into()),
+            SEMICOLON,
+            EOF,
+        ];
+        for expected_token in expected {
+            let token = lexer.next_token();
+            assert_eq!(token, expected_token);
+        }
+        assert_eq!(
+            lexer.errors(),
+            &[LexError {
+                message: "unterminated block comment".to_string(),
+                span: Span::new(11, 13),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_syntax_tokens_keep_trivia() {
+        let input = "let é = 5; // done\n";
+        let mut lexer = Lexer::new(input);
+
+        let token = lexer.next_syntax_token();
+        assert_eq!(token.kind, LET);
+        assert_eq!(token.span, Span::new(0, 3));
+        assert_eq!(token.leading_trivia, vec![]);
+
+        let token = lexer.next_syntax_token();
+        assert_eq!(token.kind, ILLEGAL);
+        assert_eq!(token.text, "é");
+        assert_eq!(token.full_text(), " é");
+
+        let mut tokens = vec![];
+        loop {
+            let token = lexer.next_syntax_token();
+            tokens.push(token.clone());
+            if token.kind == EOF {
+                break;
+            }
+        }
+        let eof = tokens.last().unwrap();
+        assert_eq!(eof.text, "");
+        assert_eq!(eof.span, Span::new(19, 19));
+        assert_eq!(
+            eof.leading_trivia,
+            vec![
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: " ".to_string(),
+                },
+                Trivia {
+                    kind: TriviaKind::LineComment,
+                    text: "// done".to_string(),
+                },
+                Trivia {
+                    kind: TriviaKind::Whitespace,
+                    text: "\n".to_string(),
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_next_token() {
         let input = r#"
@@ -176,7 +920,7 @@ mod test {
             x + y;
         };
         let result = add(five, ten);
-        !-/*5;
+        !-/ *5;
         5 < 10 > 5;
 
         if (5 < 10) {
@@ -194,12 +938,12 @@ mod test {
             LET,
             IDENT("five".to_string()),
             ASSIGN,
-            INT(5),
+            INT(5.into()),
             SEMICOLON,
             LET,
             IDENT("ten".to_string()),
             ASSIGN,
-            INT(10),
+            INT(10.into()),
             SEMICOLON,
             LET,
             IDENT("add".to_string()),
@@ -231,19 +975,19 @@ mod test {
             MINUS,
             SLASH,
             ASTERISK,
-            INT(5),
+            INT(5.into()),
             SEMICOLON,
-            INT(5),
+            INT(5.into()),
             LT,
-            INT(10),
+            INT(10.into()),
             GT,
-            INT(5),
+            INT(5.into()),
             SEMICOLON,
             IF,
             LPAREN,
-            INT(5),
+            INT(5.into()),
             LT,
-            INT(10),
+            INT(10.into()),
             RPAREN,
             LBRACE,
             RETURN,
@@ -256,13 +1000,13 @@ mod test {
             FALSE,
             SEMICOLON,
             RBRACE,
-            INT(10),
+            INT(10.into()),
             EQ,
-            INT(10),
+            INT(10.into()),
             SEMICOLON,
-            INT(10),
+            INT(10.into()),
             NotEQ,
-            INT(9),
+            INT(9.into()),
             SEMICOLON,
             EOF,
         ];
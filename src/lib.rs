@@ -0,0 +1,358 @@
+//! The Monkey programming language as a library, for embedding scripts in
+//! a Rust program:
+//!
+//! ```
+//! use monkey_language_rust::{Interpreter, Object};
+//!
+//! let mut interpreter = Interpreter::new();
+//! interpreter.eval("let double = fn(x) { x * 2 };").unwrap();
+//! assert_eq!(interpreter.eval("double(21)").unwrap(), Object::Integer(42));
+//! ```
+
+// token and precedence names follow the book's ALL-CAPS convention
+#![allow(clippy::upper_case_acronyms, clippy::enum_variant_names)]
+
+pub mod ast;
+pub(crate) mod builtins;
+pub mod cst;
+pub(crate) mod environment;
+pub(crate) mod evaluator;
+pub mod formatter;
+pub(crate) mod host;
+pub mod json;
+pub(crate) mod lexer;
+pub(crate) mod object;
+pub(crate) mod parser;
+pub mod sexp;
+pub(crate) mod token;
+pub mod visit;
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::ast::Program;
+use crate::environment::Environment;
+pub use crate::evaluator::{CancellationToken, ErrorKind, Frame, Limits, RuntimeError};
+pub use crate::host::{FromObject, HostFunction, IntoObject};
+pub use crate::object::{Builtin, HashKey, Object};
+pub use crate::parser::ParseError;
+pub use crate::token::{Span, Token};
+
+// Stack kept free for the deepest frame of a recursive pass, and the size of
+// each segment added when less than that is left.
+const STACK_RED_ZONE: usize = 64 << 10;
+const STACK_SEGMENT: usize = 1 << 20;
+
+// Runs `f` on a new stack segment if the current one is nearly used up.
+// The passes that recurse through a program call it once per level, so a
+// chain like `a + b + c + ...`, whose left operands nest as deep as it is
+// long, cannot overflow the stack however long it is.
+pub(crate) fn with_stack<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(STACK_RED_ZONE, STACK_SEGMENT, f)
+}
+
+/// Parses `source`, returning every syntax error if there is any.
+pub fn parse(source: &str) -> Result<Program, Vec<ParseError>> {
+    parse_with_limits(source, &Limits::default())
+}
+
+/// Like `parse`, but nesting as deep as `limits.max_nesting` allows.
+pub fn parse_with_limits(source: &str, limits: &Limits) -> Result<Program, Vec<ParseError>> {
+    let mut parser = parser::Parser::new(lexer::Lexer::new(source));
+    parser.set_max_nesting(limits.max_nesting);
+    match parser.parse_program() {
+        Ok(program) => Ok(program),
+        Err(_) => Err(parser.errors().to_vec()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Parse(Vec<ParseError>),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            Error::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<Vec<ParseError>> for Error {
+    fn from(errors: Vec<ParseError>) -> Error {
+        Error::Parse(errors)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Error {
+        Error::Runtime(error)
+    }
+}
+
+/// Evaluates Monkey source. Bindings made by one call to `eval` stay
+/// visible to the following ones.
+#[derive(Debug)]
+pub struct Interpreter {
+    env: Rc<RefCell<Environment>>,
+    limits: Limits,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        let mut interpreter = Interpreter {
+            env: Rc::new(RefCell::new(Environment::new())),
+            limits: Limits::default(),
+            cancellation: None,
+        };
+        for builtin in builtins::builtins().into_iter().chain(builtins::math()) {
+            interpreter.register_builtin(builtin);
+        }
+        interpreter
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::default()
+    }
+
+    /// Makes `function` callable from scripts as `name`, converting its
+    /// arguments with `FromObject` and its result with `IntoObject`.
+    /// Replaces any existing binding of `name`.
+    pub fn register_function<Args>(&mut self, name: &str, function: impl HostFunction<Args>) {
+        self.register_builtin(Builtin::from_host(name, function));
+    }
+
+    /// Limits applied to each later call to `eval`; every call gets the
+    /// full budget.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Checked by each later call to `eval`. Cancelling the token stops the
+    /// running script with `ErrorKind::Cancelled`, and every later one
+    /// until the token is replaced.
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    pub fn register_builtin(&mut self, builtin: Builtin) {
+        let name = builtin.name.clone();
+        self.env
+            .borrow_mut()
+            .set(name, Object::Builtin(Rc::new(builtin)));
+    }
+
+    /// Parses and evaluates `source`, returning the value of its last
+    /// statement.
+    pub fn eval(&mut self, source: &str) -> Result<Object, Error> {
+        let program = parse_with_limits(source, &self.limits)?;
+        Ok(self.eval_program(&program)?)
+    }
+
+    /// Like `eval`, but gives up with `ErrorKind::Cancelled` once `timeout`
+    /// has passed.
+    pub fn eval_with_timeout(&mut self, source: &str, timeout: Duration) -> Result<Object, Error> {
+        let program = parse_with_limits(source, &self.limits)?;
+        let token = match &self.cancellation {
+            Some(token) => token.timeout(timeout),
+            None => CancellationToken::with_timeout(timeout),
+        };
+        Ok(self.run(&program, Some(token))?)
+    }
+
+    pub fn eval_program(&mut self, program: &Program) -> Result<Object, RuntimeError> {
+        self.run(program, self.cancellation.clone())
+    }
+
+    fn run(
+        &mut self,
+        program: &Program,
+        cancellation: Option<CancellationToken>,
+    ) -> Result<Object, RuntimeError> {
+        let mut evaluator = evaluator::Evaluator::new(self.limits);
+        if let Some(token) = cancellation {
+            evaluator.set_cancellation_token(token);
+        }
+        evaluator.eval_program(program, &self.env)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Span;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("let x = 5;").unwrap().string(), "let x = 5;");
+        assert_eq!(
+            parse("let = 5; let x 5;").err().unwrap(),
+            vec![
+                ParseError::new(
+                    "expected next token to be IDENT, got ASSIGN".to_string(),
+                    Span::new(4, 5)
+                ),
+                ParseError::new(
+                    "expected next token to be ASSIGN, got INT(5)".to_string(),
+                    Span::new(15, 16)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interpreter_keeps_bindings() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.eval("let x = 2;"), Ok(Object::Null));
+        assert_eq!(
+            interpreter.eval("let f = fn(y) { x * y };"),
+            Ok(Object::Null)
+        );
+        assert_eq!(interpreter.eval("f(21)"), Ok(Object::Integer(42)));
+    }
+
+    #[test]
+    fn test_interpreter_errors() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(
+            interpreter.eval("let x = ;").unwrap_err().to_string(),
+            "no prefix parse function for SEMICOLON at 8..9"
+        );
+        assert_eq!(
+            interpreter.eval("x").unwrap_err(),
+            Error::Runtime(RuntimeError::new(
+                "identifier not found: x".to_string(),
+                Span::new(0, 1)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_interpreter_constants() {
+        let mut interpreter = Interpreter::new();
+        assert_eq!(interpreter.eval("const answer = 42;"), Ok(Object::Null));
+        assert_eq!(
+            interpreter.eval("answer = 0").unwrap_err().to_string(),
+            "cannot assign to constant answer at 0..6"
+        );
+        assert_eq!(
+            interpreter
+                .eval("const answer = 0")
+                .unwrap_err()
+                .to_string(),
+            "cannot redeclare constant answer at 6..12"
+        );
+        assert_eq!(interpreter.eval("answer"), Ok(Object::Integer(42)));
+    }
+
+    #[test]
+    fn test_interpreter_limits() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(Limits {
+            max_steps: Some(10),
+            ..Limits::default()
+        });
+        let error = interpreter.eval("let f = fn() { f() }; f();").unwrap_err();
+        match error {
+            Error::Runtime(error) => assert_eq!(error.kind, ErrorKind::StepLimit),
+            other => panic!("expected a runtime error, got {}", other),
+        }
+        // each call to eval starts with a fresh budget
+        assert_eq!(interpreter.eval("1 + 1"), Ok(Object::Integer(2)));
+
+        interpreter.set_limits(Limits {
+            max_memory: Some(1 << 20),
+            ..Limits::default()
+        });
+        let pushes =
+            "let xs = []; let n = 0; while (n < 2000) { xs = push(xs, n); n += 1 }; len(xs)";
+        assert_eq!(interpreter.eval(pushes), Ok(Object::Integer(2000)));
+    }
+
+    // Deep recursion and long chains of operators go on to new stack segments
+    // rather than overflow the 2 MiB stack threads get by default, in debug
+    // builds too, and deep nesting is reported as an error.
+    #[test]
+    fn test_nesting_fits_thread_stack() {
+        let thread = std::thread::Builder::new().stack_size(2 << 20);
+        let run = thread.spawn(|| {
+            let recursive = "let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } }; f(2000)";
+            assert_eq!(Interpreter::new().eval(recursive), Ok(Object::Integer(2000)));
+
+            let wrapped = "let f = fn(n) { for (i in [1]) { while (true) { return match (n) {
+                x if x >= 0 => try { let y = 0; y += f(n + 1); y } catch (e) { 0 }
+            } } } }; f(0)";
+            let mut interpreter = Interpreter::new();
+            interpreter.set_limits(Limits {
+                max_depth: Some(1000),
+                ..Limits::default()
+            });
+            match interpreter.eval(wrapped) {
+                Err(Error::Runtime(error)) => assert_eq!(error.kind, ErrorKind::DepthLimit),
+                other => panic!("expected a depth error, got {:?}", other),
+            }
+
+            let arrays = format!("{}1{}", "[".repeat(5000), "]".repeat(5000));
+            assert!(parse(&arrays).is_err());
+
+            let deepest = format!("{}1{}", "for (i in [1]) { ".repeat(98), "}".repeat(98));
+            let program = parse(&deepest).unwrap();
+            assert_eq!(Interpreter::new().eval_program(&program), Ok(Object::Null));
+            assert!(formatter::format(&deepest, &formatter::FormatOptions::default()).is_ok());
+            assert_eq!(json::from_json(&json::to_json(&program)), Ok(program));
+
+            // operator chains are not limited, and nest as deep as they are long
+            let chain = format!("{}1", "1 + ".repeat(10_000));
+            let program = parse(&chain).unwrap();
+            assert_eq!(
+                Interpreter::new().eval_program(&program),
+                Ok(Object::Integer(10_001))
+            );
+            assert_eq!(program.clone(), program);
+            assert_eq!(sexp::to_sexp(&program).matches('+').count(), 10_000);
+            let (tree, _) = cst::parse(&chain);
+            assert_eq!(tree.text(), chain);
+            assert_eq!(Program::try_from(&tree), Ok(program));
+        });
+        run.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_interpreter_cancellation() {
+        let looping = "let f = fn() { f() }; f();";
+        let mut interpreter = Interpreter::new();
+        interpreter.set_limits(Limits {
+            max_depth: None,
+            ..Limits::default()
+        });
+        let error = interpreter
+            .eval_with_timeout(looping, Duration::ZERO)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "cancelled at 22..25");
+
+        let token = CancellationToken::new();
+        interpreter.set_cancellation_token(Some(token.clone()));
+        assert_eq!(interpreter.eval("1 + 1"), Ok(Object::Integer(2)));
+        token.cancel();
+        let error = interpreter.eval(looping).unwrap_err();
+        assert_eq!(error.to_string(), "cancelled at 22..25");
+    }
+}
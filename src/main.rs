@@ -1,12 +1,153 @@
-mod token;
-mod lexer;
 mod repl;
-mod ast;
-mod parser;
+
+use std::io::Read;
+use std::process;
+
+use monkey_language_rust::formatter::{self, FormatOptions};
+use monkey_language_rust::json;
+
+const FMT_USAGE: &str = "usage: fmt [--check] [--width N] [--indent N] [FILE...]";
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("fmt") => process::exit(fmt(&args[1..])),
+        Some("--ast-json") => process::exit(dump_ast_json(args.get(1))),
+        _ => {}
+    }
+
     let user = std::env::var("USER").unwrap();
     println!("Hello {}! This is the Monkey programming language!", user);
     println!("Feel free to type in commands");
     repl::start();
 }
+
+fn read_input(file: Option<&String>) -> Result<String, String> {
+    match file {
+        Some(file) => std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e)),
+        None => {
+            let mut input = String::new();
+            match std::io::stdin().read_to_string(&mut input) {
+                Ok(_) => Ok(input),
+                Err(e) => Err(format!("<stdin>: {}", e)),
+            }
+        }
+    }
+}
+
+/// Prints the ast of a file, or of stdin, in the format of `json::to_json`.
+fn dump_ast_json(file: Option<&String>) -> i32 {
+    let input = match read_input(file) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    match monkey_language_rust::parse(&input) {
+        Ok(program) => {
+            println!("{}", json::to_json_string(&program));
+            0
+        }
+        Err(errors) => {
+            for error in errors {
+                eprintln!("{}", error);
+            }
+            1
+        }
+    }
+}
+
+/// Formats the given files in place, or stdin to stdout when no file is
+/// given. With `--check` nothing is written and the exit code is 1 if any
+/// input is not already formatted.
+fn fmt(args: &[String]) -> i32 {
+    let mut options = FormatOptions::default();
+    let mut check = false;
+    let mut files = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--width" | "--indent" => {
+                let value = match args.next().and_then(|value| value.parse().ok()) {
+                    Some(value) => value,
+                    None => {
+                        eprintln!("{} expects a number\n{}", arg, FMT_USAGE);
+                        return 2;
+                    }
+                };
+                if arg == "--width" {
+                    options.width = value;
+                } else {
+                    options.indent = value;
+                }
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("unknown option {}\n{}", arg, FMT_USAGE);
+                return 2;
+            }
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    if files.is_empty() {
+        let input = match read_input(None) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 2;
+            }
+        };
+        return match formatter::format(&input, &options) {
+            Ok(formatted) if check => {
+                if formatted == input {
+                    0
+                } else {
+                    println!("<stdin> is not formatted");
+                    1
+                }
+            }
+            Ok(formatted) => {
+                print!("{}", formatted);
+                0
+            }
+            Err(e) => {
+                eprintln!("<stdin>: {}", e);
+                2
+            }
+        };
+    }
+
+    let mut status = 0;
+    for file in files {
+        let input = match read_input(Some(&file)) {
+            Ok(input) => input,
+            Err(e) => {
+                eprintln!("{}", e);
+                status = 2;
+                continue;
+            }
+        };
+        let formatted = match formatter::format(&input, &options) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                eprintln!("{}: {}", file, e);
+                status = 2;
+                continue;
+            }
+        };
+        if formatted == input {
+            continue;
+        }
+        if check {
+            println!("{} is not formatted", file);
+            status = status.max(1);
+        } else if let Err(e) = std::fs::write(&file, formatted) {
+            eprintln!("{}: {}", file, e);
+            status = 2;
+        }
+    }
+    status
+}
@@ -0,0 +1,310 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
+
+use crate::ast::{quote, BlockStatement, Identifier};
+use crate::environment::Environment;
+use crate::evaluator::Frame;
+use crate::token::Span;
+
+#[derive(Debug, Clone)]
+pub enum Object {
+    Integer(i64),
+    /// An integer outside the range of `i64`. Results that fit are always
+    /// stored as `Integer`, so the two never hold the same value.
+    BigInteger(Rc<BigInt>),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    Null,
+    Array(Rc<Vec<Object>>),
+    Hash(Rc<BTreeMap<HashKey, Object>>),
+    /// The integers from `start` up to `end`, made by `start..end`, or
+    /// `start..=end` when `end` itself is included.
+    Range {
+        start: i64,
+        end: i64,
+        inclusive: bool,
+    },
+    Function(Rc<Function>),
+    Builtin(Rc<Builtin>),
+    /// An error caught by `catch (e)`.
+    Error(Rc<ErrorObject>),
+}
+
+impl Object {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Integer(_) | Object::BigInteger(_) => "INTEGER",
+            Object::Float(_) => "FLOAT",
+            Object::Boolean(_) => "BOOLEAN",
+            Object::String(_) => "STRING",
+            Object::Null => "NULL",
+            Object::Array(_) => "ARRAY",
+            Object::Hash(_) => "HASH",
+            Object::Range { .. } => "RANGE",
+            Object::Function(_) => "FUNCTION",
+            Object::Builtin(_) => "BUILTIN",
+            Object::Error(_) => "ERROR",
+        }
+    }
+
+    /// The key this object is stored under in a hash, if it can be one.
+    pub fn hash_key(&self) -> Option<HashKey> {
+        match self {
+            Object::Integer(value) => Some(HashKey::Integer(BigInt::from(*value))),
+            Object::BigInteger(value) => Some(HashKey::Integer(BigInt::clone(value))),
+            Object::Boolean(value) => Some(HashKey::Boolean(*value)),
+            Object::String(value) => Some(HashKey::String(value.clone())),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer or float as a float, rounding integers that
+    /// have no exact float.
+    pub fn to_f64(&self) -> Option<f64> {
+        match self {
+            Object::Integer(value) => Some(*value as f64),
+            Object::BigInteger(value) => value.to_f64(),
+            Object::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // like `Display`, but strings are quoted so they stand out inside arrays
+    // and hashes
+    pub(crate) fn inspect(&self) -> String {
+        match self {
+            Object::String(value) => quote(value),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Everything except `false` and `null` counts as true in a condition.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Object::Boolean(false) | Object::Null)
+    }
+}
+
+// functions are compared by identity, their environment may refer back to
+// the function itself
+impl PartialEq for Object {
+    fn eq(&self, other: &Object) -> bool {
+        match (self, other) {
+            (Object::Integer(a), Object::Integer(b)) => a == b,
+            (Object::BigInteger(a), Object::BigInteger(b)) => a == b,
+            (Object::Float(a), Object::Float(b)) => a == b,
+            (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::String(a), Object::String(b)) => a == b,
+            (Object::Null, Object::Null) => true,
+            (Object::Array(a), Object::Array(b)) => a == b,
+            (Object::Hash(a), Object::Hash(b)) => a == b,
+            (
+                &Object::Range {
+                    start,
+                    end,
+                    inclusive,
+                },
+                &Object::Range {
+                    start: other_start,
+                    end: other_end,
+                    inclusive: other_inclusive,
+                },
+            ) => {
+                start == other_start
+                    && range_end(end, inclusive) == range_end(other_end, other_inclusive)
+            }
+            (Object::Function(a), Object::Function(b)) => Rc::ptr_eq(a, b),
+            (Object::Builtin(a), Object::Builtin(b)) => Rc::ptr_eq(a, b),
+            (Object::Error(a), Object::Error(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// The end of a range as a bound that is not included, which for
+/// `a..=i64::MAX` is past the largest `i64`.
+pub fn range_end(end: i64, inclusive: bool) -> i128 {
+    i128::from(end) + i128::from(inclusive)
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Integer(value) => write!(f, "{}", value),
+            Object::BigInteger(value) => write!(f, "{}", value),
+            // `{:?}` keeps the `.0` of whole numbers, so floats never look
+            // like integers
+            Object::Float(value) => write!(f, "{:?}", value),
+            Object::Boolean(value) => write!(f, "{}", value),
+            Object::String(value) => write!(f, "{}", value),
+            Object::Null => write!(f, "null"),
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.inspect()).collect();
+                write!(f, "[{}]", elements.join(", "))
+            }
+            Object::Hash(pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}: {}",
+                            Object::from(key.clone()).inspect(),
+                            value.inspect()
+                        )
+                    })
+                    .collect();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+            Object::Range {
+                start,
+                end,
+                inclusive: false,
+            } => write!(f, "{}..{}", start, end),
+            Object::Range {
+                start,
+                end,
+                inclusive: true,
+            } => write!(f, "{}..={}", start, end),
+            Object::Function(function) => {
+                let parameters: Vec<String> =
+                    function.parameters.iter().map(|p| p.string()).collect();
+                write!(
+                    f,
+                    "fn({}) {}",
+                    parameters.join(", "),
+                    function.body.string()
+                )
+            }
+            Object::Builtin(builtin) => write!(f, "builtin {}", builtin.name),
+            Object::Error(error) => write!(f, "error: {}", error.message),
+        }
+    }
+}
+
+impl From<BigInt> for Object {
+    fn from(value: BigInt) -> Object {
+        match i64::try_from(&value) {
+            Ok(value) => Object::Integer(value),
+            Err(_) => Object::BigInteger(Rc::new(value)),
+        }
+    }
+}
+
+impl From<&BigInt> for Object {
+    fn from(value: &BigInt) -> Object {
+        match i64::try_from(value) {
+            Ok(value) => Object::Integer(value),
+            Err(_) => Object::BigInteger(Rc::new(value.clone())),
+        }
+    }
+}
+
+pub struct Function {
+    /// From the function literal, for stack traces.
+    pub name: Option<String>,
+    pub parameters: Vec<Identifier>,
+    pub body: BlockStatement,
+    pub env: Rc<RefCell<Environment>>,
+}
+
+// the environment is left out, it usually contains the function itself
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("parameters", &self.parameters)
+            .field("body", &self.body)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What `catch (e)` binds. Scripts read it as `e["message"]`, `e["value"]`
+/// and `e["stack"]`.
+#[derive(Debug)]
+pub struct ErrorObject {
+    pub message: String,
+    /// What was thrown, or null for an error raised by the interpreter.
+    pub value: Object,
+    /// Where the error was raised.
+    pub span: Span,
+    /// The function calls the error unwound through, innermost first.
+    pub stack: Vec<Frame>,
+}
+
+/// The hashable subset of objects. Hashes are ordered by key, integers
+/// first, then booleans, then strings.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashKey {
+    Integer(BigInt),
+    Boolean(bool),
+    String(String),
+}
+
+impl From<HashKey> for Object {
+    fn from(key: HashKey) -> Object {
+        match key {
+            HashKey::Integer(value) => Object::from(value),
+            HashKey::Boolean(value) => Object::Boolean(value),
+            HashKey::String(value) => Object::String(value),
+        }
+    }
+}
+
+impl From<i64> for HashKey {
+    fn from(value: i64) -> HashKey {
+        HashKey::Integer(BigInt::from(value))
+    }
+}
+
+impl From<bool> for HashKey {
+    fn from(value: bool) -> HashKey {
+        HashKey::Boolean(value)
+    }
+}
+
+impl From<String> for HashKey {
+    fn from(value: String) -> HashKey {
+        HashKey::String(value)
+    }
+}
+
+impl From<&str> for HashKey {
+    fn from(value: &str) -> HashKey {
+        HashKey::String(value.to_string())
+    }
+}
+
+pub type BuiltinFunction = dyn Fn(Vec<Object>) -> Result<Object, String>;
+
+/// A function implemented in Rust. An `Err` becomes a runtime error at the
+/// call site, prefixed with the function's name.
+pub struct Builtin {
+    pub name: String,
+    pub function: Box<BuiltinFunction>,
+}
+
+impl Builtin {
+    /// A builtin taking its arguments as they are; see `host` for typed
+    /// arguments.
+    pub fn new(
+        name: &str,
+        function: impl Fn(Vec<Object>) -> Result<Object, String> + 'static,
+    ) -> Builtin {
+        Builtin {
+            name: name.to_string(),
+            function: Box::new(function),
+        }
+    }
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Builtin")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
@@ -1,15 +1,61 @@
 use crate::ast::{
-    Expression, ExpressionStatement, Identifier, InfixExpression, IntegerLiteral, LetStatement,
-    PrefixExpression, Program, ReturnStatement, Statement,
+    ArrayLiteral, ArrayPattern, AssignExpression, BindingKind, BlockStatement, Boolean,
+    BreakStatement, CallExpression, CatchClause, ContinueStatement, DestructureStatement,
+    Expression, ExpressionStatement, FloatLiteral, ForStatement, FunctionLiteral,
+    FunctionStatement, HashLiteral, HashPattern, Identifier, IfExpression, IndexExpression,
+    InfixExpression, IntegerLiteral, LetStatement, MatchArm, MatchExpression, Pattern,
+    PrefixExpression, Program, ReturnStatement, Statement, StringLiteral, ThrowStatement,
+    TryExpression, WhileStatement,
 };
+use crate::cst::{SyntaxKind, SyntaxNode, TreeBuilder};
 use crate::lexer::Lexer;
-use crate::token::Token;
+use crate::token::{Span, SyntaxToken, Token};
+use crate::visit::{self, Visitor};
+use std::collections::HashMap;
+use std::fmt;
+
+/// How deeply statements, expressions and patterns may nest inside one
+/// another by default: parentheses, prefix operators, the operands on the
+/// right of an operator, blocks and so on. The operators of a chain like
+/// `a + b + c` are side by side rather than nested, so any number of them
+/// take only one level.
+pub const MAX_NESTING_DEPTH: usize = 100;
+
+/// A syntax error and the span of the token it was reported at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl ParseError {
+    pub fn new(message: String, span: Span) -> ParseError {
+        ParseError { message, span }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+impl std::error::Error for ParseError {}
 
 pub struct Parser {
     lexer: Lexer,
     cur_token: Token,
     peek_token: Token,
-    errors: Vec<String>,
+    peek_syntax_token: Option<SyntaxToken>,
+    cur_span: Span,
+    errors: Vec<ParseError>,
+    builder: TreeBuilder,
+    // loops around the current position, not counting those outside the
+    // enclosing function
+    loop_depth: usize,
+    // statements, expressions and patterns around the current position
+    depth: usize,
+    max_depth: Option<usize>,
 }
 
 impl Parser {
@@ -18,7 +64,13 @@ impl Parser {
             lexer,
             cur_token: Token::ILLEGAL,
             peek_token: Token::ILLEGAL,
+            peek_syntax_token: None,
+            cur_span: Span::default(),
             errors: vec![],
+            builder: TreeBuilder::new(),
+            loop_depth: 0,
+            depth: 0,
+            max_depth: Some(MAX_NESTING_DEPTH),
         };
         parser.next_token();
         parser.next_token();
@@ -26,19 +78,46 @@ impl Parser {
     }
 
     fn next_token(&mut self) {
+        if self.cur_token == Token::EOF {
+            return;
+        }
+        let peek = self.lexer.next_syntax_token();
         self.cur_token = self.peek_token.clone();
-        self.peek_token = self.lexer.next_token();
+        self.peek_token = peek.kind.clone();
+        if let Some(cur) = self.peek_syntax_token.replace(peek) {
+            self.cur_span = cur.span;
+            self.builder.token(cur);
+        }
+    }
+
+    /// How deeply the program may nest, `MAX_NESTING_DEPTH` unless changed.
+    /// `None` lifts the limit.
+    pub fn set_max_nesting(&mut self, max: Option<usize>) {
+        self.max_depth = max;
+    }
+
+    /// Errors collected by `parse_program`, lexer errors last.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Concrete syntax tree of everything consumed so far; call after
+    /// `parse_program` to get the whole input.
+    pub fn syntax_tree(self) -> SyntaxNode {
+        self.builder.finish()
     }
 
     pub fn parse_program(&mut self) -> Result<Program, String> {
         let mut program = Program::new();
 
         while self.cur_token != Token::EOF {
+            let checkpoint = self.builder.checkpoint();
             let statement = match self.parse_statement() {
                 Ok(statement) => statement,
                 Err(e) => {
                     self.errors.push(e);
                     self.skip_statement();
+                    self.builder.wrap(checkpoint, SyntaxKind::Error);
                     self.next_token();
                     continue;
                 }
@@ -46,30 +125,101 @@ impl Parser {
             program.statements.push(statement);
             self.next_token();
         }
+        self.builder.wrap(0, SyntaxKind::Program);
 
-        if self.errors.len() != 0 {
-            return Err(self.errors.join("\n"));
+        let mut checker = ConstantChecker::new();
+        checker.visit_program(&program);
+        self.errors.extend(checker.errors);
+
+        for error in self.lexer.errors() {
+            self.errors
+                .push(ParseError::new(error.message.clone(), error.span));
+        }
+
+        if !self.errors.is_empty() {
+            let messages: Vec<String> = self.errors.iter().map(|e| e.to_string()).collect();
+            return Err(messages.join("\n"));
         }
 
         Ok(program)
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, String> {
+    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
+        let checkpoint = self.builder.checkpoint();
+        let statement = self.nested(|parser| parser.parse_bare_statement())?;
+        self.builder.wrap(checkpoint, statement_kind(&statement));
+        Ok(statement)
+    }
+
+    // The functions the parser recurses through return what they parse
+    // straight from the call that parsed it, without a `?` in between, so
+    // their stack frames stay small.
+    fn parse_bare_statement(&mut self) -> Result<Statement, ParseError> {
         match self.cur_token {
-            Token::LET => self.parse_let_statement(),
+            Token::LET | Token::CONST => self.parse_let_statement(),
             Token::RETURN => self.parse_return_statement(),
+            Token::THROW => self.parse_throw_statement(),
+            Token::WHILE => self.parse_while_statement(),
+            Token::BREAK => Ok(self.parse_break_statement()),
+            Token::CONTINUE => Ok(self.parse_continue_statement()),
+            Token::FOR => self.parse_for_statement(),
+            Token::FUNCTION if matches!(self.peek_token, Token::IDENT(_)) => {
+                self.parse_function_statement()
+            }
             _ => self.parse_expression_statement(),
         }
     }
 
+    // Parses one level of nesting in the ast.
+    fn nested<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Parser) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if let Some(max) = self.max_depth.filter(|max| self.depth >= *max) {
+            return Err(ParseError::new(
+                format!("nested more than {} levels deep", max),
+                self.cur_span,
+            ));
+        }
+        self.depth += 1;
+        let result = crate::with_stack(|| parse(self));
+        self.depth -= 1;
+        result
+    }
+
     fn skip_statement(&mut self) {
-        while self.cur_token != Token::SEMICOLON {
+        while self.cur_token != Token::SEMICOLON && self.cur_token != Token::EOF {
             self.next_token();
         }
     }
 
-    fn parse_expression_statement(&mut self) -> Result<Statement, String> {
+    fn expect_peek(&mut self, token: Token) -> Result<(), ParseError> {
+        if self.peek_token != token {
+            return Err(ParseError::new(
+                format!(
+                    "expected next token to be {:?}, got {:?}",
+                    token, self.peek_token
+                ),
+                self.peek_span(),
+            ));
+        }
+        self.next_token();
+        Ok(())
+    }
+
+    fn peek_span(&self) -> Span {
+        self.peek_syntax_token
+            .as_ref()
+            .map_or(self.cur_span, |token| token.span)
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span::new(start.start, self.cur_span.end)
+    }
+
+    fn parse_expression_statement(&mut self) -> Result<Statement, ParseError> {
         let token = self.cur_token.clone();
+        let start = self.cur_span;
         let expression = self.parse_expression(Precedence::LOWEST)?;
 
         if self.peek_token == Token::SEMICOLON {
@@ -79,40 +229,76 @@ impl Parser {
         Ok(Statement::ExpressionStatement(ExpressionStatement {
             token,
             expression,
+            span: self.span_from(start),
         }))
     }
 
-    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, String> {
-        let mut left_exp = match self.cur_token {
-            Token::IDENT(_) => self.parser_identifier(),
-            Token::INT(_) => self.parse_integer_literal(),
-            Token::BANG | Token::MINUS => self.parse_prefix_expression(),
-            _ => Err(format!("no prefix parse function for {:?}", self.cur_token)),
-        }?;
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, ParseError> {
+        self.nested(|parser| parser.parse_operand_and_operators(precedence))
+    }
+
+    // The operators that bind tighter than `precedence` are applied to the
+    // operand in a loop, so a chain of them takes a single level.
+    fn parse_operand_and_operators(
+        &mut self,
+        precedence: Precedence,
+    ) -> Result<Expression, ParseError> {
+        let checkpoint = self.builder.checkpoint();
+        let grouped = self.cur_token == Token::LPAREN;
+        let mut left_exp = self.parse_operand()?;
+        let kind = match grouped {
+            true => SyntaxKind::ParenExpression,
+            false => expression_kind(&left_exp),
+        };
+        self.builder.wrap(checkpoint, kind);
 
+        // only operators bind tighter than `Precedence::LOWEST`
         while self.peek_token != Token::SEMICOLON
             && precedence.value() < self.peek_precedence().value()
         {
-            match self.peek_token {
-                Token::PLUS
-                | Token::MINUS
-                | Token::SLASH
-                | Token::ASTERISK
-                | Token::GT
-                | Token::LT
-                | Token::EQ
-                | Token::NotEQ => {
-                    self.next_token();
-                    left_exp = self.parse_infix_expression(left_exp)?;
-                }
-                _ => return Ok(left_exp),
-            }
+            self.next_token();
+            left_exp = self.parse_operator(left_exp)?;
+            self.builder.wrap(checkpoint, expression_kind(&left_exp));
         }
 
         Ok(left_exp)
     }
 
-    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, String> {
+    fn parse_operand(&mut self) -> Result<Expression, ParseError> {
+        match self.cur_token {
+            Token::IDENT(_) => self.parser_identifier(),
+            Token::INT(_) => self.parse_integer_literal(),
+            Token::FLOAT(_) => self.parse_float_literal(),
+            Token::TRUE | Token::FALSE => self.parse_boolean(),
+            Token::BANG | Token::MINUS | Token::TILDE => self.parse_prefix_expression(),
+            Token::LPAREN => self.parse_grouped_expression(),
+            Token::IF => self.parse_if_expression(),
+            Token::MATCH => self.parse_match_expression(),
+            Token::TRY => self.parse_try_expression(),
+            Token::FUNCTION => self.parse_function_literal(),
+            Token::STRING(_) => self.parse_string_literal(),
+            Token::LBRACKET => self.parse_array_literal(),
+            Token::LBRACE => self.parse_hash_literal(),
+            _ => Err(ParseError::new(
+                format!("no prefix parse function for {:?}", self.cur_token),
+                self.cur_span,
+            )),
+        }
+    }
+
+    // Parses the operator at the current token, applied to `left`.
+    fn parse_operator(&mut self, left: Expression) -> Result<Expression, ParseError> {
+        match self.cur_token {
+            Token::ASSIGN | Token::PlusEQ | Token::MinusEQ | Token::AsteriskEQ | Token::SlashEQ => {
+                self.parse_assign_expression(left)
+            }
+            Token::LPAREN => self.parse_call_expression(left),
+            Token::LBRACKET => self.parse_index_expression(left),
+            _ => self.parse_infix_expression(left),
+        }
+    }
+
+    fn parse_infix_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let operator = self.cur_token.literal();
         let precedence = self.cur_precedence();
@@ -122,14 +308,38 @@ impl Parser {
         Ok(Expression::InfixExpression(InfixExpression {
             token,
             operator,
+            span: self.span_from(left.span()),
             left: Box::new(left),
             right: Box::new(right),
         }))
     }
 
-    fn parse_prefix_expression(&mut self) -> Result<Expression, String> {
+    // Assignment is right associative, `a = b = c` assigns `c` to both.
+    fn parse_assign_expression(&mut self, target: Expression) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let operator = self.cur_token.literal();
+        if !AssignExpression::is_target(&target) {
+            self.errors.push(ParseError::new(
+                format!("cannot assign to {}", target.string()),
+                target.span(),
+            ));
+        }
+        self.next_token();
+        let value = self.parse_expression(Precedence::LOWEST)?;
+
+        Ok(Expression::AssignExpression(AssignExpression {
+            token,
+            operator,
+            span: self.span_from(target.span()),
+            target: Box::new(target),
+            value: Box::new(value),
+        }))
+    }
+
+    fn parse_prefix_expression(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        let operator = self.cur_token.literal();
         self.next_token();
 
         let right = self.parse_expression(Precedence::PREFIX)?;
@@ -138,83 +348,768 @@ impl Parser {
             token,
             operator,
             right: Box::new(right),
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_grouped_expression(&mut self) -> Result<Expression, ParseError> {
+        self.next_token();
+        let expression = self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek(Token::RPAREN)?;
+        Ok(expression)
+    }
+
+    fn parse_if_expression(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.expect_peek(Token::LPAREN)?;
+        self.next_token();
+        let condition = self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek(Token::RPAREN)?;
+        self.expect_peek(Token::LBRACE)?;
+        let consequence = self.parse_block_statement()?;
+
+        let mut alternative = None;
+        if self.peek_token == Token::ELSE {
+            self.next_token();
+            self.expect_peek(Token::LBRACE)?;
+            alternative = Some(self.parse_block_statement()?);
+        }
+
+        Ok(Expression::IfExpression(IfExpression {
+            token,
+            condition: Box::new(condition),
+            consequence,
+            alternative,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_try_expression(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.expect_peek(Token::LBRACE)?;
+        let body = self.parse_block_statement()?;
+
+        let mut catch = None;
+        if self.peek_token == Token::CATCH {
+            self.next_token();
+            let checkpoint = self.builder.checkpoint();
+            catch = Some(Box::new(self.parse_catch_clause()?));
+            self.builder.wrap(checkpoint, SyntaxKind::CatchClause);
+        }
+        let mut finally = None;
+        if self.peek_token == Token::FINALLY {
+            self.next_token();
+            self.expect_peek(Token::LBRACE)?;
+            finally = Some(self.parse_block_statement()?);
+        }
+        if catch.is_none() && finally.is_none() {
+            return Err(ParseError::new(
+                format!(
+                    "expected catch or finally after try block, got {:?}",
+                    self.peek_token
+                ),
+                self.peek_span(),
+            ));
+        }
+
+        Ok(Expression::TryExpression(TryExpression {
+            token,
+            body,
+            catch,
+            finally,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_catch_clause(&mut self) -> Result<CatchClause, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.expect_peek(Token::LPAREN)?;
+        self.next_token();
+        let parameter = self.parse_binding("catch parameter")?;
+        self.expect_peek(Token::RPAREN)?;
+        self.expect_peek(Token::LBRACE)?;
+        let body = self.parse_block_statement()?;
+
+        Ok(CatchClause {
+            token,
+            parameter,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.expect_peek(Token::LPAREN)?;
+        self.next_token();
+        let subject = self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek(Token::RPAREN)?;
+        self.expect_peek(Token::LBRACE)?;
+
+        let mut arms = vec![];
+        while self.peek_token != Token::RBRACE {
+            self.next_token();
+            let checkpoint = self.builder.checkpoint();
+            arms.push(self.parse_match_arm()?);
+            self.builder.wrap(checkpoint, SyntaxKind::MatchArm);
+
+            if self.peek_token != Token::RBRACE {
+                self.expect_peek(Token::COMMA)?;
+            }
+        }
+        self.expect_peek(Token::RBRACE)?;
+
+        Ok(Expression::MatchExpression(MatchExpression {
+            token,
+            subject: Box::new(subject),
+            arms,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_match_arm(&mut self) -> Result<MatchArm, ParseError> {
+        let start = self.cur_span;
+        let pattern = self.parse_pattern()?;
+        self.check_bindings(&pattern);
+
+        let mut guard = None;
+        if self.peek_token == Token::IF {
+            self.next_token();
+            self.next_token();
+            guard = Some(self.parse_expression(Precedence::LOWEST)?);
+        }
+        self.expect_peek(Token::FatArrow)?;
+        self.next_token();
+        let body = self.parse_expression(Precedence::LOWEST)?;
+
+        Ok(MatchArm {
+            pattern,
+            guard,
+            body,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern, ParseError> {
+        self.nested(|parser| parser.parse_pattern_kind())
+    }
+
+    fn parse_pattern_kind(&mut self) -> Result<Pattern, ParseError> {
+        let checkpoint = self.builder.checkpoint();
+        match &self.cur_token {
+            Token::IDENT(name) if name == "_" => Ok(Pattern::Wildcard(self.cur_span)),
+            Token::IDENT(_) => Ok(Pattern::Binding(self.parse_binding("pattern")?)),
+            Token::LBRACKET => {
+                let pattern = self.parse_array_pattern()?;
+                self.builder.wrap(checkpoint, SyntaxKind::ArrayPattern);
+                Ok(Pattern::Array(pattern))
+            }
+            Token::LBRACE => {
+                let pattern = self.parse_hash_pattern()?;
+                self.builder.wrap(checkpoint, SyntaxKind::HashPattern);
+                Ok(Pattern::Hash(pattern))
+            }
+            _ => Ok(Pattern::Literal(self.parse_literal_pattern("pattern")?)),
+        }
+    }
+
+    fn check_bindings(&mut self, pattern: &Pattern) {
+        let bindings = pattern.bindings();
+        for (i, name) in bindings.iter().enumerate() {
+            if bindings[..i].iter().any(|other| other.value == name.value) {
+                self.errors.push(ParseError::new(
+                    format!("{} is bound more than once in the same pattern", name.value),
+                    name.span,
+                ));
+            }
+        }
+    }
+
+    fn parse_array_pattern(&mut self) -> Result<ArrayPattern, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        let mut elements = vec![];
+        let mut rest = None;
+
+        while self.peek_token != Token::RBRACKET {
+            self.next_token();
+            if self.cur_token == Token::ELLIPSIS {
+                self.next_token();
+                rest = Some(Box::new(match &self.cur_token {
+                    Token::IDENT(name) if name == "_" => Pattern::Wildcard(self.cur_span),
+                    _ => Pattern::Binding(self.parse_binding("rest pattern")?),
+                }));
+                if self.peek_token == Token::COMMA {
+                    self.next_token();
+                }
+                if self.peek_token != Token::RBRACKET {
+                    return Err(ParseError::new(
+                        "rest pattern must come last".to_string(),
+                        self.peek_span(),
+                    ));
+                }
+                break;
+            }
+            elements.push(self.parse_pattern()?);
+
+            if self.peek_token != Token::RBRACKET {
+                self.expect_peek(Token::COMMA)?;
+            }
+        }
+        self.expect_peek(Token::RBRACKET)?;
+
+        Ok(ArrayPattern {
+            token,
+            elements,
+            rest,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_hash_pattern(&mut self) -> Result<HashPattern, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        let mut pairs = vec![];
+
+        while self.peek_token != Token::RBRACE {
+            self.next_token();
+            if let Token::IDENT(_) = self.cur_token {
+                // `{name}` binds the value stored under "name"
+                let name = self.parse_binding("pattern")?;
+                let key = Expression::StringLiteral(StringLiteral {
+                    token: Token::STRING(name.value.clone()),
+                    value: name.value.clone(),
+                    span: name.span,
+                });
+                pairs.push((key, Pattern::Binding(name)));
+            } else {
+                let key = self.parse_literal_pattern("hash pattern key")?;
+                self.expect_peek(Token::COLON)?;
+                self.next_token();
+                pairs.push((key, self.parse_pattern()?));
+            }
+
+            if self.peek_token != Token::RBRACE {
+                self.expect_peek(Token::COMMA)?;
+            }
+        }
+        self.expect_peek(Token::RBRACE)?;
+
+        Ok(HashPattern {
+            token,
+            pairs,
+            span: self.span_from(start),
+        })
+    }
+
+    // A literal, or a negated integer or float, described as `what` in the
+    // error when it is something else.
+    fn parse_literal_pattern(&mut self, what: &str) -> Result<Expression, ParseError> {
+        match self.cur_token {
+            Token::INT(_)
+            | Token::FLOAT(_)
+            | Token::STRING(_)
+            | Token::TRUE
+            | Token::FALSE
+            | Token::MINUS => {}
+            _ => {
+                return Err(ParseError::new(
+                    format!("expected {}, got {:?}", what, self.cur_token),
+                    self.cur_span,
+                ))
+            }
+        }
+        let literal = self.parse_expression(Precedence::PREFIX)?;
+        let is_literal = match &literal {
+            Expression::PrefixExpression(prefix) => matches!(
+                *prefix.right,
+                Expression::IntegerLiteral(_) | Expression::FloatLiteral(_)
+            ),
+            Expression::IntegerLiteral(_)
+            | Expression::FloatLiteral(_)
+            | Expression::StringLiteral(_)
+            | Expression::Boolean(_) => true,
+            _ => false,
+        };
+        if !is_literal {
+            return Err(ParseError::new(
+                format!("expected {}, got {}", what, literal.string()),
+                literal.span(),
+            ));
+        }
+        Ok(literal)
+    }
+
+    fn parse_block_statement(&mut self) -> Result<BlockStatement, ParseError> {
+        let checkpoint = self.builder.checkpoint();
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        let mut statements = vec![];
+        self.next_token();
+
+        while self.cur_token != Token::RBRACE {
+            if self.cur_token == Token::EOF {
+                return Err(ParseError::new(
+                    "expected RBRACE to close block, got EOF".to_string(),
+                    self.cur_span,
+                ));
+            }
+            statements.push(self.parse_statement()?);
+            self.next_token();
+        }
+        self.builder.wrap(checkpoint, SyntaxKind::BlockStatement);
+
+        Ok(BlockStatement {
+            token,
+            statements,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_function_literal(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        let function = self.parse_function(token, start, None)?;
+        Ok(Expression::FunctionLiteral(function))
+    }
+
+    fn parse_function_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.next_token();
+        let checkpoint = self.builder.checkpoint();
+        self.builder.wrap(checkpoint, SyntaxKind::Identifier);
+        let name = Identifier {
+            token: self.cur_token.clone(),
+            value: self.cur_token.literal(),
+            span: self.cur_span,
+        };
+        let function = self.parse_function(token.clone(), start, Some(name.value.clone()))?;
+
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
+
+        Ok(Statement::FunctionStatement(FunctionStatement {
+            token,
+            name,
+            function,
+            span: self.span_from(start),
         }))
     }
 
-    fn parse_integer_literal(&mut self) -> Result<Expression, String> {
+    // The parameters and body of a function, from the `(` on.
+    fn parse_function(
+        &mut self,
+        token: Token,
+        start: Span,
+        name: Option<String>,
+    ) -> Result<FunctionLiteral, ParseError> {
+        self.expect_peek(Token::LPAREN)?;
+        let parameters = self.parse_function_parameters()?;
+        self.expect_peek(Token::LBRACE)?;
+        let loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.parse_block_statement();
+        self.loop_depth = loop_depth;
+        let body = body?;
+
+        Ok(FunctionLiteral {
+            token,
+            parameters,
+            body,
+            span: self.span_from(start),
+            name,
+        })
+    }
+
+    fn parse_function_parameters(&mut self) -> Result<Vec<Identifier>, ParseError> {
+        let mut parameters = vec![];
+        if self.peek_token == Token::RPAREN {
+            self.next_token();
+            return Ok(parameters);
+        }
+
+        self.next_token();
+        parameters.push(self.parse_parameter()?);
+        while self.peek_token == Token::COMMA {
+            self.next_token();
+            self.next_token();
+            parameters.push(self.parse_parameter()?);
+        }
+        self.expect_peek(Token::RPAREN)?;
+
+        Ok(parameters)
+    }
+
+    fn parse_parameter(&mut self) -> Result<Identifier, ParseError> {
+        self.parse_binding("parameter")
+    }
+
+    // An identifier that introduces a variable, described as `what` in the
+    // error when it is something else.
+    fn parse_binding(&mut self, what: &str) -> Result<Identifier, ParseError> {
+        match self.cur_token {
+            Token::IDENT(_) => {
+                let checkpoint = self.builder.checkpoint();
+                self.builder.wrap(checkpoint, SyntaxKind::Identifier);
+                Ok(Identifier {
+                    token: self.cur_token.clone(),
+                    value: self.cur_token.literal(),
+                    span: self.cur_span,
+                })
+            }
+            _ => Err(ParseError::new(
+                format!("expected {} to be IDENT, got {:?}", what, self.cur_token),
+                self.cur_span,
+            )),
+        }
+    }
+
+    fn parse_call_expression(&mut self, function: Expression) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let arguments = self.parse_expression_list(Token::RPAREN)?;
+
+        Ok(Expression::CallExpression(CallExpression {
+            token,
+            span: self.span_from(function.span()),
+            function: Box::new(function),
+            arguments,
+        }))
+    }
+
+    // Comma separated expressions up to `end`, for call arguments and
+    // array literals.
+    fn parse_expression_list(&mut self, end: Token) -> Result<Vec<Expression>, ParseError> {
+        let mut list = vec![];
+        if self.peek_token == end {
+            self.next_token();
+            return Ok(list);
+        }
+
+        self.next_token();
+        list.push(self.parse_expression(Precedence::LOWEST)?);
+        while self.peek_token == Token::COMMA {
+            self.next_token();
+            self.next_token();
+            list.push(self.parse_expression(Precedence::LOWEST)?);
+        }
+        self.expect_peek(end)?;
+
+        Ok(list)
+    }
+
+    fn parse_index_expression(&mut self, left: Expression) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        self.next_token();
+        let index = self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek(Token::RBRACKET)?;
+
+        Ok(Expression::IndexExpression(IndexExpression {
+            token,
+            span: self.span_from(left.span()),
+            left: Box::new(left),
+            index: Box::new(index),
+        }))
+    }
+
+    fn parse_string_literal(&mut self) -> Result<Expression, ParseError> {
+        let value = match &self.cur_token {
+            Token::STRING(value) => value.clone(),
+            _ => panic!("expected token to be STRING"),
+        };
+
+        Ok(Expression::StringLiteral(StringLiteral {
+            token: self.cur_token.clone(),
+            value,
+            span: self.cur_span,
+        }))
+    }
+
+    fn parse_array_literal(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        let elements = self.parse_expression_list(Token::RBRACKET)?;
+
+        Ok(Expression::ArrayLiteral(ArrayLiteral {
+            token,
+            elements,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_hash_literal(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        let mut pairs = vec![];
+
+        while self.peek_token != Token::RBRACE {
+            self.next_token();
+            let key = self.parse_expression(Precedence::LOWEST)?;
+            self.expect_peek(Token::COLON)?;
+            self.next_token();
+            let value = self.parse_expression(Precedence::LOWEST)?;
+            pairs.push((key, value));
+
+            if self.peek_token != Token::RBRACE {
+                self.expect_peek(Token::COMMA)?;
+            }
+        }
+        self.expect_peek(Token::RBRACE)?;
+
+        Ok(Expression::HashLiteral(HashLiteral {
+            token,
+            pairs,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_integer_literal(&mut self) -> Result<Expression, ParseError> {
         let token = self.cur_token.clone();
         let value = match &token {
             Token::INT(value) => value.clone(),
             _ => panic!("expected token to be INT"),
         };
 
-        Ok(Expression::IntegerLiteral(IntegerLiteral { token, value }))
+        Ok(Expression::IntegerLiteral(IntegerLiteral {
+            token,
+            value,
+            span: self.cur_span,
+        }))
+    }
+
+    fn parse_float_literal(&mut self) -> Result<Expression, ParseError> {
+        let token = self.cur_token.clone();
+        let value = match &token {
+            Token::FLOAT(value) => *value,
+            _ => panic!("expected token to be FLOAT"),
+        };
+
+        Ok(Expression::FloatLiteral(FloatLiteral {
+            token,
+            value,
+            span: self.cur_span,
+        }))
+    }
+
+    fn parse_boolean(&mut self) -> Result<Expression, ParseError> {
+        Ok(Expression::Boolean(Boolean {
+            token: self.cur_token.clone(),
+            value: self.cur_token == Token::TRUE,
+            span: self.cur_span,
+        }))
     }
 
-    fn parser_identifier(&mut self) -> Result<Expression, String> {
+    fn parser_identifier(&mut self) -> Result<Expression, ParseError> {
         Ok(Expression::Identifier(Identifier {
             token: self.cur_token.clone(),
             value: self.cur_token.literal(),
+            span: self.cur_span,
         }))
     }
 
-    fn parse_return_statement(&mut self) -> Result<Statement, String> {
+    fn parse_while_statement(&mut self) -> Result<Statement, ParseError> {
         let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.expect_peek(Token::LPAREN)?;
         self.next_token();
+        let condition = self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek(Token::RPAREN)?;
+        self.expect_peek(Token::LBRACE)?;
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
 
-        let expression = Expression::Identifier(Identifier {
-            token: self.cur_token.clone(),
-            value: self.cur_token.literal(),
-        });
+        Ok(Statement::WhileStatement(WhileStatement {
+            token,
+            condition,
+            body: body?,
+            span: self.span_from(start),
+        }))
+    }
 
-        // skipping the expression until we encounter a semicolon
-        self.skip_statement();
+    fn parse_for_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.expect_peek(Token::LPAREN)?;
+        self.next_token();
+        let mut key = None;
+        let mut value = self.parse_binding("loop variable")?;
+        if self.peek_token == Token::COMMA {
+            self.next_token();
+            self.next_token();
+            key = Some(value);
+            value = self.parse_binding("loop variable")?;
+        }
+        self.expect_peek(Token::IN)?;
+        self.next_token();
+        let iterable = self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek(Token::RPAREN)?;
+        self.expect_peek(Token::LBRACE)?;
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
+
+        Ok(Statement::ForStatement(ForStatement {
+            token,
+            key,
+            value,
+            iterable,
+            body: body?,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_break_statement(&mut self) -> Statement {
+        let token = self.cur_token.clone();
+        let span = self.parse_loop_jump("break");
+        Statement::BreakStatement(BreakStatement { token, span })
+    }
+
+    fn parse_continue_statement(&mut self) -> Statement {
+        let token = self.cur_token.clone();
+        let span = self.parse_loop_jump("continue");
+        Statement::ContinueStatement(ContinueStatement { token, span })
+    }
+
+    // Consumes the optional semicolon after `break` or `continue`, and
+    // reports one that is not inside a loop. The error does not stop the
+    // parse, the statement itself is well formed.
+    fn parse_loop_jump(&mut self, keyword: &str) -> Span {
+        let start = self.cur_span;
+        if self.loop_depth == 0 {
+            self.errors.push(ParseError::new(
+                format!("{} outside of a loop", keyword),
+                start,
+            ));
+        }
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
+        self.span_from(start)
+    }
+
+    fn parse_return_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.next_token();
+
+        let return_value = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
 
         Ok(Statement::ReturnStatement(ReturnStatement {
             token,
-            return_value: expression,
+            return_value,
+            span: self.span_from(start),
         }))
     }
 
-    fn parse_let_statement(&mut self) -> Result<Statement, String> {
+    fn parse_throw_statement(&mut self) -> Result<Statement, ParseError> {
         let token = self.cur_token.clone();
+        let start = self.cur_span;
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
+
+        Ok(Statement::ThrowStatement(ThrowStatement {
+            token,
+            value,
+            span: self.span_from(start),
+        }))
+    }
+
+    // `let` or `const` followed by an array or hash pattern instead of a
+    // name.
+    fn parse_destructure_statement(
+        &mut self,
+        token: Token,
+        start: Span,
+    ) -> Result<Statement, ParseError> {
+        self.next_token();
+        let pattern = self.parse_pattern()?;
+        self.check_bindings(&pattern);
+        self.expect_peek(Token::ASSIGN)?;
+        self.next_token();
+
+        let value = self.parse_expression(Precedence::LOWEST)?;
+
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
+
+        Ok(Statement::DestructureStatement(DestructureStatement {
+            kind: match token {
+                Token::CONST => BindingKind::Const,
+                _ => BindingKind::Let,
+            },
+            token,
+            pattern,
+            value,
+            span: self.span_from(start),
+        }))
+    }
+
+    fn parse_let_statement(&mut self) -> Result<Statement, ParseError> {
+        let token = self.cur_token.clone();
+        let start = self.cur_span;
         match self.peek_token {
             Token::IDENT(_) => self.next_token(),
+            Token::LBRACKET | Token::LBRACE => {
+                return self.parse_destructure_statement(token, start)
+            }
             _ => {
-                return Err(format!(
-                    "expected next token to be IDENT, got {:?}",
-                    self.peek_token
+                return Err(ParseError::new(
+                    format!("expected next token to be IDENT, got {:?}", self.peek_token),
+                    self.peek_span(),
                 ))
             }
         };
 
+        let checkpoint = self.builder.checkpoint();
+        self.builder.wrap(checkpoint, SyntaxKind::Identifier);
         let name = Identifier {
             token: self.cur_token.clone(),
             value: self.cur_token.literal(),
+            span: self.cur_span,
         };
 
-        match self.peek_token {
-            Token::ASSIGN => self.next_token(),
-            _ => {
-                return Err(format!(
-                    "expected next token to be ASSIGN, got {:?}",
-                    self.peek_token
-                ))
-            }
-        }
+        self.expect_peek(Token::ASSIGN)?;
+        self.next_token();
 
-        let expression = Expression::Identifier(Identifier {
-            token: self.peek_token.clone(),
-            value: self.peek_token.literal(),
-        });
+        let mut value = self.parse_expression(Precedence::LOWEST)?;
+        if let Expression::FunctionLiteral(function) = &mut value {
+            function.name = Some(name.value.clone());
+        }
 
-        // skipping the expression until we encounter a semicolon
-        self.skip_statement();
+        if self.peek_token == Token::SEMICOLON {
+            self.next_token();
+        }
 
         Ok(Statement::LetStatement(LetStatement {
+            kind: match token {
+                Token::CONST => BindingKind::Const,
+                _ => BindingKind::Let,
+            },
             token,
             name,
-            value: expression,
+            value,
+            span: self.span_from(start),
         }))
     }
 
@@ -226,37 +1121,273 @@ impl Parser {
     }
 }
 
-enum Precedence {
+fn statement_kind(statement: &Statement) -> SyntaxKind {
+    match statement {
+        Statement::LetStatement(_) => SyntaxKind::LetStatement,
+        Statement::DestructureStatement(_) => SyntaxKind::DestructureStatement,
+        Statement::ReturnStatement(_) => SyntaxKind::ReturnStatement,
+        Statement::ExpressionStatement(_) => SyntaxKind::ExpressionStatement,
+        Statement::WhileStatement(_) => SyntaxKind::WhileStatement,
+        Statement::BreakStatement(_) => SyntaxKind::BreakStatement,
+        Statement::ContinueStatement(_) => SyntaxKind::ContinueStatement,
+        Statement::ForStatement(_) => SyntaxKind::ForStatement,
+        Statement::ThrowStatement(_) => SyntaxKind::ThrowStatement,
+        Statement::FunctionStatement(_) => SyntaxKind::FunctionStatement,
+    }
+}
+
+fn expression_kind(expression: &Expression) -> SyntaxKind {
+    match expression {
+        Expression::Identifier(_) => SyntaxKind::Identifier,
+        Expression::IntegerLiteral(_) => SyntaxKind::IntegerLiteral,
+        Expression::FloatLiteral(_) => SyntaxKind::FloatLiteral,
+        Expression::PrefixExpression(_) => SyntaxKind::PrefixExpression,
+        Expression::InfixExpression(_) => SyntaxKind::InfixExpression,
+        Expression::Boolean(_) => SyntaxKind::Boolean,
+        Expression::IfExpression(_) => SyntaxKind::IfExpression,
+        Expression::FunctionLiteral(_) => SyntaxKind::FunctionLiteral,
+        Expression::CallExpression(_) => SyntaxKind::CallExpression,
+        Expression::StringLiteral(_) => SyntaxKind::StringLiteral,
+        Expression::ArrayLiteral(_) => SyntaxKind::ArrayLiteral,
+        Expression::HashLiteral(_) => SyntaxKind::HashLiteral,
+        Expression::IndexExpression(_) => SyntaxKind::IndexExpression,
+        Expression::AssignExpression(_) => SyntaxKind::AssignExpression,
+        Expression::MatchExpression(_) => SyntaxKind::MatchExpression,
+        Expression::TryExpression(_) => SyntaxKind::TryExpression,
+    }
+}
+
+/// Finds assignments to constants that fail whenever they run. Names bound
+/// outside the program, or whose kind depends on which branch ran, are left
+/// to the evaluator.
+struct ConstantChecker {
+    // one per function body, loop body, match arm and catch block, innermost
+    // last
+    scopes: Vec<Scope>,
+    errors: Vec<ParseError>,
+}
+
+#[derive(Default)]
+struct Scope {
+    // `None` for a name bound differently on different paths
+    bindings: HashMap<String, Option<BindingKind>>,
+    // if branches and try blocks entered within this scope
+    nested: usize,
+}
+
+impl ConstantChecker {
+    fn new() -> ConstantChecker {
+        ConstantChecker {
+            scopes: vec![Scope::default()],
+            errors: vec![],
+        }
+    }
+
+    fn declare(&mut self, name: &Identifier, kind: BindingKind) {
+        let scope = self.scopes.last_mut().unwrap();
+        let previous = scope.bindings.get(&name.value).copied().flatten();
+        if previous == Some(BindingKind::Const) {
+            self.errors.push(ParseError::new(
+                format!("cannot redeclare constant {}", name.value),
+                name.span,
+            ));
+        }
+        let kind = if scope.nested > 0 && previous != Some(kind) {
+            None
+        } else {
+            Some(kind)
+        };
+        scope.bindings.insert(name.value.clone(), kind);
+    }
+
+    fn is_constant(&self, name: &str) -> bool {
+        let binding = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.bindings.get(name));
+        binding == Some(&Some(BindingKind::Const))
+    }
+
+    // Declares the functions in `statements` up front, as the evaluator
+    // does.
+    fn hoist(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            if let Statement::FunctionStatement(declaration) = statement {
+                self.declare(&declaration.name, BindingKind::Let);
+            }
+        }
+    }
+
+    // Checks `body` in a new scope that starts with `names` bound by `let`.
+    fn visit_scope<'a>(
+        &mut self,
+        names: impl IntoIterator<Item = &'a Identifier>,
+        body: &BlockStatement,
+    ) {
+        self.in_scope(names, |checker| {
+            checker.hoist(&body.statements);
+            for statement in &body.statements {
+                checker.visit_statement(statement);
+            }
+        });
+    }
+
+    fn in_scope<'a>(
+        &mut self,
+        names: impl IntoIterator<Item = &'a Identifier>,
+        visit: impl FnOnce(&mut ConstantChecker),
+    ) {
+        let mut scope = Scope::default();
+        for name in names {
+            scope
+                .bindings
+                .insert(name.value.clone(), Some(BindingKind::Let));
+        }
+        self.scopes.push(scope);
+        visit(self);
+        self.scopes.pop();
+    }
+}
+
+impl Visitor for ConstantChecker {
+    fn visit_program(&mut self, program: &Program) {
+        self.hoist(&program.statements);
+        visit::walk_program(self, program);
+    }
+
+    // the name was declared by `hoist`
+    fn visit_function_statement(&mut self, declaration: &FunctionStatement) {
+        self.visit_function_literal(&declaration.function);
+    }
+
+    fn visit_let_statement(&mut self, let_statement: &LetStatement) {
+        self.visit_expression(&let_statement.value);
+        self.declare(&let_statement.name, let_statement.kind);
+    }
+
+    fn visit_destructure_statement(&mut self, destructure: &DestructureStatement) {
+        self.visit_expression(&destructure.value);
+        for name in destructure.pattern.bindings() {
+            self.declare(name, destructure.kind);
+        }
+    }
+
+    fn visit_assign_expression(&mut self, assign: &AssignExpression) {
+        let mut target = &*assign.target;
+        while let Expression::IndexExpression(index) = target {
+            target = &index.left;
+        }
+        if let Expression::Identifier(name) = target {
+            if self.is_constant(&name.value) {
+                self.errors.push(ParseError::new(
+                    format!("cannot assign to constant {}", name.value),
+                    name.span,
+                ));
+            }
+        }
+        visit::walk_assign_expression(self, assign);
+    }
+
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        self.scopes.last_mut().unwrap().nested += 1;
+        self.hoist(&block.statements);
+        visit::walk_block_statement(self, block);
+        self.scopes.last_mut().unwrap().nested -= 1;
+    }
+
+    fn visit_function_literal(&mut self, function: &FunctionLiteral) {
+        self.visit_scope(&function.parameters, &function.body);
+    }
+
+    fn visit_for_statement(&mut self, for_statement: &ForStatement) {
+        self.visit_expression(&for_statement.iterable);
+        let names = for_statement.key.iter().chain([&for_statement.value]);
+        self.visit_scope(names, &for_statement.body);
+    }
+
+    fn visit_while_statement(&mut self, while_statement: &WhileStatement) {
+        self.visit_expression(&while_statement.condition);
+        self.visit_scope([], &while_statement.body);
+    }
+
+    fn visit_try_expression(&mut self, try_expression: &TryExpression) {
+        self.visit_block_statement(&try_expression.body);
+        if let Some(catch) = &try_expression.catch {
+            self.visit_scope([&catch.parameter], &catch.body);
+        }
+        if let Some(finally) = &try_expression.finally {
+            self.visit_block_statement(finally);
+        }
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        self.in_scope(arm.pattern.bindings(), |checker| {
+            visit::walk_match_arm(checker, arm)
+        });
+    }
+}
+
+pub(crate) enum Precedence {
     LOWEST,
+    ASSIGN,      // =, +=, -=, *= or /=
+    OR,          // ||
+    AND,         // &&
+    BITOR,       // |
+    BITXOR,      // ^
+    BITAND,      // &
     EQUALS,      // ==
-    LESSGREATER, // > or <
+    LESSGREATER, // >, <, >= or <=
+    RANGE,       // .. or ..=
+    SHIFT,       // << or >>
     SUM,         // +
     PRODUCT,     // *
-    PREFIX,      // -X or !X
+    PREFIX,      // -X, !X or ~X
     CALL,        // myFunction(X)
+    INDEX,       // array[index]
 }
 
 impl Precedence {
-    fn from_token(token: &Token) -> Precedence {
+    pub(crate) fn from_token(token: &Token) -> Precedence {
         match token {
+            Token::ASSIGN | Token::PlusEQ | Token::MinusEQ | Token::AsteriskEQ | Token::SlashEQ => {
+                Precedence::ASSIGN
+            }
+            Token::OR => Precedence::OR,
+            Token::AND => Precedence::AND,
+            Token::PIPE => Precedence::BITOR,
+            Token::CARET => Precedence::BITXOR,
+            Token::AMPERSAND => Precedence::BITAND,
             Token::EQ | Token::NotEQ => Precedence::EQUALS,
-            Token::LT | Token::GT => Precedence::LESSGREATER,
+            Token::LT | Token::GT | Token::LtEQ | Token::GtEQ => Precedence::LESSGREATER,
+            Token::DOTDOT | Token::DotDotEQ => Precedence::RANGE,
+            Token::LSHIFT | Token::RSHIFT => Precedence::SHIFT,
             Token::PLUS | Token::MINUS => Precedence::SUM,
-            Token::SLASH | Token::ASTERISK => Precedence::PRODUCT,
+            Token::SLASH | Token::ASTERISK | Token::PERCENT => Precedence::PRODUCT,
             Token::LPAREN => Precedence::CALL,
+            Token::LBRACKET => Precedence::INDEX,
             _ => Precedence::LOWEST,
         }
     }
 
-    fn value(&self) -> i32 {
+    pub(crate) fn value(&self) -> i32 {
         match self {
             Precedence::LOWEST => 1,
-            Precedence::EQUALS => 2,
-            Precedence::LESSGREATER => 3,
-            Precedence::SUM => 4,
-            Precedence::PRODUCT => 5,
-            Precedence::PREFIX => 6,
-            Precedence::CALL => 7,
+            Precedence::ASSIGN => 2,
+            Precedence::OR => 3,
+            Precedence::AND => 4,
+            Precedence::BITOR => 5,
+            Precedence::BITXOR => 6,
+            Precedence::BITAND => 7,
+            Precedence::EQUALS => 8,
+            Precedence::LESSGREATER => 9,
+            Precedence::RANGE => 10,
+            Precedence::SHIFT => 11,
+            Precedence::SUM => 12,
+            Precedence::PRODUCT => 13,
+            Precedence::PREFIX => 14,
+            Precedence::CALL => 15,
+            Precedence::INDEX => 16,
         }
     }
 }
@@ -264,7 +1395,8 @@ impl Precedence {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{Expression, Identifier, LetStatement, Statement};
+    use crate::ast::{Expression, Identifier, LetStatement, Statement, StringLiteral};
+    use crate::token::Span;
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -282,36 +1414,48 @@ let foobar = 838383;
             statements: vec![
                 Statement::LetStatement(LetStatement {
                     token: Token::LET,
+                    kind: BindingKind::Let,
                     name: Identifier {
                         token: Token::IDENT("x".to_string()),
                         value: "x".to_string(),
+                        span: Span::new(5, 6),
                     },
-                    value: Expression::Identifier(Identifier {
-                        token: Token::INT(5),
-                        value: "5".to_string(),
+                    value: Expression::IntegerLiteral(IntegerLiteral {
+                        token: Token::INT(5.into()),
+                        value: 5.into(),
+                        span: Span::new(9, 10),
                     }),
+                    span: Span::new(1, 11),
                 }),
                 Statement::LetStatement(LetStatement {
                     token: Token::LET,
+                    kind: BindingKind::Let,
                     name: Identifier {
                         token: Token::IDENT("y".to_string()),
                         value: "y".to_string(),
+                        span: Span::new(16, 17),
                     },
-                    value: Expression::Identifier(Identifier {
-                        token: Token::INT(10),
-                        value: "10".to_string(),
+                    value: Expression::IntegerLiteral(IntegerLiteral {
+                        token: Token::INT(10.into()),
+                        value: 10.into(),
+                        span: Span::new(20, 22),
                     }),
+                    span: Span::new(12, 23),
                 }),
                 Statement::LetStatement(LetStatement {
                     token: Token::LET,
+                    kind: BindingKind::Let,
                     name: Identifier {
                         token: Token::IDENT("foobar".to_string()),
                         value: "foobar".to_string(),
+                        span: Span::new(28, 34),
                     },
-                    value: Expression::Identifier(Identifier {
-                        token: Token::INT(838383),
-                        value: "838383".to_string(),
+                    value: Expression::IntegerLiteral(IntegerLiteral {
+                        token: Token::INT(838383.into()),
+                        value: 838383.into(),
+                        span: Span::new(37, 43),
                     }),
+                    span: Span::new(24, 44),
                 }),
             ],
         };
@@ -321,7 +1465,77 @@ let foobar = 838383;
             Err(e) => panic!("parse_program() returned an error: {}", e),
         };
 
-        assert_eq!(program, expected_program);
+        assert_eq!(program, expected_program);
+    }
+
+    #[test]
+    fn test_const_statements() {
+        let mut parser = Parser::new(Lexer::new("const x = 5; let y = x;"));
+        let program = parser.parse_program().unwrap();
+
+        let kinds: Vec<BindingKind> = program
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::LetStatement(let_statement) => let_statement.kind,
+                _ => panic!("statement is not a let statement"),
+            })
+            .collect();
+        assert_eq!(kinds, vec![BindingKind::Const, BindingKind::Let]);
+        assert_eq!(program.string(), "const x = 5;\nlet y = x;");
+    }
+
+    #[test]
+    fn test_constant_errors() {
+        let tests = vec![
+            (
+                "const x = 1; x = 2;",
+                Some("cannot assign to constant x at 13..14"),
+            ),
+            (
+                "const a = [1]; a[0] += 1;",
+                Some("cannot assign to constant a at 15..16"),
+            ),
+            (
+                "const x = 1; let f = fn() { if (true) { x = 2 } };",
+                Some("cannot assign to constant x at 40..41"),
+            ),
+            (
+                "const x = 1; let x = 2;",
+                Some("cannot redeclare constant x at 17..18"),
+            ),
+            (
+                "const x = 1; for (x in []) { x = 2 } let f = fn(x) { x = 3 };",
+                None,
+            ),
+            ("let x = 1; if (true) { const x = 2; } x = 3;", None),
+            ("while (true) { const x = 1; break; }", None),
+            (
+                "const x = 1; const x = 2; x",
+                Some("cannot redeclare constant x at 19..20"),
+            ),
+            (
+                "const x = 1; if (true) { const x = 2 }",
+                Some("cannot redeclare constant x at 31..32"),
+            ),
+            ("const x = 1; while (true) { const x = 2; break; }", None),
+            ("let f = fn() { x = 1 }; const x = 2;", None),
+            ("const x = 1; match (2) { [x] => x = 3, _ => 0 }", None),
+            (
+                "const [a, b] = [1, 2]; a = 3;",
+                Some("cannot assign to constant a at 23..24"),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            assert_eq!(
+                parser.parse_program().err().as_deref(),
+                expected,
+                "{}",
+                input
+            );
+        }
     }
 
     #[test]
@@ -339,7 +1553,79 @@ let 838383;
         assert!(program.is_err());
         assert_eq!(
             program.err().unwrap(),
-            "expected next token to be ASSIGN, got INT(5)\nexpected next token to be IDENT, got ASSIGN\nexpected next token to be IDENT, got INT(838383)")
+            "expected next token to be ASSIGN, got INT(5) at 8..9\n\
+             expected next token to be IDENT, got ASSIGN at 15..16\n\
+             expected next token to be IDENT, got INT(838383) at 25..31"
+        )
+    }
+
+    #[test]
+    fn test_lexer_errors() {
+        let input = "let x = 5; /* unterminated";
+
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+
+        let program = parser.parse_program();
+        assert_eq!(
+            program.err().unwrap(),
+            "unterminated block comment at 11..13"
+        );
+    }
+
+    #[test]
+    fn test_nesting_errors() {
+        // the statement and the innermost operand are levels too
+        let arrays = |n| format!("{}1{}", "[".repeat(n), "]".repeat(n));
+        let sum = |n| format!("1{}", " + 1".repeat(n));
+        let tests = vec![
+            (arrays(98), None),
+            (
+                arrays(99),
+                Some("nested more than 100 levels deep at 99..100"),
+            ),
+            (
+                arrays(5000),
+                Some("nested more than 100 levels deep at 99..100"),
+            ),
+            // a chain of operators is a single level however long it is
+            (sum(10_000), None),
+            (
+                format!("{}1", "-".repeat(100)),
+                Some("nested more than 100 levels deep at 99..100"),
+            ),
+            (
+                format!("let {}a{} = 1", "[".repeat(100), "]".repeat(100)),
+                Some("nested more than 100 levels deep at 103..104"),
+            ),
+            (
+                "if (true) { ".repeat(50) + &"}".repeat(50),
+                Some("nested more than 100 levels deep at 592..596"),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(&input));
+            let error = parser.parse_program().err();
+            assert_eq!(error.as_deref(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_max_nesting() {
+        let arrays = |n| format!("{}1{}", "[".repeat(n), "]".repeat(n));
+        let tests = vec![
+            (Some(3), arrays(1), None),
+            (Some(3), arrays(2), Some("nested more than 3 levels deep at 2..3")),
+            (None, arrays(5000), None),
+        ];
+
+        for (max, input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(&input));
+            parser.set_max_nesting(max);
+            let error = parser.parse_program().err();
+            assert_eq!(error.as_deref(), expected, "{}", input);
+        }
     }
 
     #[test]
@@ -357,24 +1643,30 @@ return 993322;
             statements: vec![
                 Statement::ReturnStatement(ReturnStatement {
                     token: Token::RETURN,
-                    return_value: Expression::Identifier(Identifier {
-                        token: Token::INT(5),
-                        value: "5".to_string(),
+                    return_value: Expression::IntegerLiteral(IntegerLiteral {
+                        token: Token::INT(5.into()),
+                        value: 5.into(),
+                        span: Span::new(8, 9),
                     }),
+                    span: Span::new(1, 10),
                 }),
                 Statement::ReturnStatement(ReturnStatement {
                     token: Token::RETURN,
-                    return_value: Expression::Identifier(Identifier {
-                        token: Token::INT(10),
-                        value: "10".to_string(),
+                    return_value: Expression::IntegerLiteral(IntegerLiteral {
+                        token: Token::INT(10.into()),
+                        value: 10.into(),
+                        span: Span::new(18, 20),
                     }),
+                    span: Span::new(11, 21),
                 }),
                 Statement::ReturnStatement(ReturnStatement {
                     token: Token::RETURN,
-                    return_value: Expression::Identifier(Identifier {
-                        token: Token::INT(993322),
-                        value: "993322".to_string(),
+                    return_value: Expression::IntegerLiteral(IntegerLiteral {
+                        token: Token::INT(993322.into()),
+                        value: 993322.into(),
+                        span: Span::new(29, 35),
                     }),
+                    span: Span::new(22, 36),
                 }),
             ],
         };
@@ -400,7 +1692,9 @@ return 993322;
                 expression: Expression::Identifier(Identifier {
                     token: Token::IDENT("foobar".to_string()),
                     value: "foobar".to_string(),
+                    span: Span::new(0, 6),
                 }),
+                span: Span::new(0, 7),
             })],
         };
 
@@ -408,6 +1702,8 @@ return 993322;
             Ok(program) => program,
             Err(e) => panic!("parse_program() returned an error: {}", e),
         };
+
+        assert_eq!(program, expected_program);
     }
 
     #[test]
@@ -419,11 +1715,13 @@ return 993322;
 
         let expected_program = Program {
             statements: vec![Statement::ExpressionStatement(ExpressionStatement {
-                token: Token::INT(5),
+                token: Token::INT(5.into()),
                 expression: Expression::IntegerLiteral(IntegerLiteral {
-                    token: Token::INT(5),
-                    value: 5,
+                    token: Token::INT(5.into()),
+                    value: 5.into(),
+                    span: Span::new(0, 1),
                 }),
+                span: Span::new(0, 2),
             })],
         };
 
@@ -529,21 +1827,50 @@ return 993322;
             ("a + b * c + d / e - f", "(((a + (b * c)) + (d / e)) - f)"),
             ("3 + 4; -5 * 5", "(3 + 4)((-5) * 5)"),
             ("5 > 4 == 3 < 4", "((5 > 4) == (3 < 4))"),
+            ("a <= b == c >= d", "((a <= b) == (c >= d))"),
+            ("a + b % c * d", "(a + ((b % c) * d))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("0..n - 1", "(0 .. (n - 1))"),
+            ("a..=b << 1 == c", "((a ..= (b << 1)) == c)"),
+            ("x < 1..2", "(x < (1 .. 2))"),
+            ("a && b || c && d", "((a && b) || (c && d))"),
+            ("a == b && !c || d", "(((a == b) && (!c)) || d)"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("a & b == c", "(a & (b == c))"),
+            ("a || b | c && d", "(a || ((b | c) && d))"),
+            ("1 << 2 + 3 < 4 >> 1", "((1 << (2 + 3)) < (4 >> 1))"),
+            ("~a & ~-b", "((~a) & (~(-b)))"),
             ("5 < 4 != 3 > 4", "((5 < 4) != (3 > 4))"),
             (
                 "3 + 4 * 5 == 3 * 1 + 4 * 5",
                 "((3 + (4 * 5)) == ((3 * 1) + (4 * 5)))",
             ),
-            // ("true", "true"),
-            // ("false", "false"),
-            //("3 > 5 == false", "((3 > 5) == false)"),
-            //("3 < 5 == true", "((3 < 5) == true)"),
-            //("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4)"),
-            //("(5 + 5) * 2", "((5 + 5) * 2)"),
-            //("2 / (5 + 5)", "(2 / (5 + 5))"),
-            //("-(5 + 5)", "(-(5 + 5))"),
-            //("!(true == true)", "(!(true == true))"),
-            //("a + add(b * c) + d", "((a + add((b * c))) + d)"),
+            ("true", "true"),
+            ("false", "false"),
+            ("3 > 5 == false", "((3 > 5) == false)"),
+            ("3 < 5 == true", "((3 < 5) == true)"),
+            ("1 + (2 + 3) + 4", "((1 + (2 + 3)) + 4)"),
+            ("(5 + 5) * 2", "((5 + 5) * 2)"),
+            ("2 / (5 + 5)", "(2 / (5 + 5))"),
+            ("-(5 + 5)", "(-(5 + 5))"),
+            ("!(true == true)", "(!(true == true))"),
+            ("a + add(b * c) + d", "((a + add((b * c))) + d)"),
+            (
+                "add(a, b, 1, 2 * 3, 4 + 5, add(6, 7 * 8))",
+                "add(a, b, 1, (2 * 3), (4 + 5), add(6, (7 * 8)))",
+            ),
+            (
+                "add(a + b + c * d / f + g)",
+                "add((((a + b) + ((c * d) / f)) + g))",
+            ),
+            (
+                "a * [1, 2, 3, 4][b * c] * d",
+                "((a * ([1, 2, 3, 4][(b * c)])) * d)",
+            ),
+            (
+                "add(a * b[2], b[1], 2 * [1, 2][1])",
+                "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
+            ),
         ];
 
         for (input, expected) in tests {
@@ -558,4 +1885,456 @@ return 993322;
             assert_eq!(program.string().replace("\n", ""), expected);
         }
     }
+
+    fn parse_single_expression(input: &str) -> Expression {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+
+        let mut program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(e) => panic!("parse_program() returned an error: {}", e),
+        };
+
+        assert_eq!(program.statements.len(), 1);
+        match program.statements.remove(0) {
+            Statement::ExpressionStatement(expression_statement) => expression_statement.expression,
+            _ => panic!("statement is not an expression statement"),
+        }
+    }
+
+    #[test]
+    fn test_boolean_expression() {
+        let expression = parse_single_expression("true;");
+        assert_eq!(
+            expression,
+            Expression::Boolean(Boolean {
+                token: Token::TRUE,
+                value: true,
+                span: Span::new(0, 4),
+            })
+        );
+    }
+
+    #[test]
+    fn test_if_else_expression() {
+        let expression = parse_single_expression("if (x < y) { x } else { y; }");
+
+        let if_expression = match expression {
+            Expression::IfExpression(if_expression) => if_expression,
+            _ => panic!("expression is not an if expression"),
+        };
+
+        assert_eq!(if_expression.condition.string(), "(x < y)");
+        assert_eq!(if_expression.consequence.statements.len(), 1);
+        assert_eq!(if_expression.consequence.string(), "x");
+        assert_eq!(if_expression.consequence.span, Span::new(11, 16));
+        let alternative = if_expression.alternative.unwrap();
+        assert_eq!(alternative.string(), "y");
+        assert_eq!(alternative.span, Span::new(22, 28));
+    }
+
+    #[test]
+    fn test_assign_expressions() {
+        let tests = vec![
+            ("x = 5", "(x = 5)"),
+            ("a = b = c", "(a = (b = c))"),
+            ("x += y || z", "(x += (y || z))"),
+            ("a[i][j] *= 2 + 3", "(((a[i])[j]) *= (2 + 3))"),
+            ("let x = y /= 2;", "let x = (y /= 2);"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            assert_eq!(program.string(), expected, "{}", input);
+        }
+
+        let mut parser = Parser::new(Lexer::new("1 = 2; a + b -= c; f()[0] = 1"));
+        assert_eq!(
+            parser.parse_program().err().unwrap(),
+            "cannot assign to 1 at 0..1\n\
+             cannot assign to (a + b) at 7..12\n\
+             cannot assign to (f()[0]) at 19..25"
+        );
+    }
+
+    #[test]
+    fn test_while_statement() {
+        let input = "while (i < 10) { if (i == 5) { break; } continue }";
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program().unwrap();
+
+        let while_statement = match &program.statements[..] {
+            [Statement::WhileStatement(while_statement)] => while_statement,
+            _ => panic!("program is not a single while statement"),
+        };
+        assert_eq!(while_statement.condition.string(), "(i < 10)");
+        assert_eq!(while_statement.body.string(), "if(i == 5) break;continue;");
+        assert_eq!(while_statement.span, Span::new(0, 50));
+    }
+
+    #[test]
+    fn test_for_statement() {
+        let tests = vec![
+            ("for (x in xs) { x }", "for(x in xs) x"),
+            (
+                "for (k, v in {1: 2}) { break; }",
+                "for(k, v in {1: 2}) break;",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            match &program.statements[..] {
+                [Statement::ForStatement(for_statement)] => {
+                    assert_eq!(for_statement.span, Span::new(0, input.len()))
+                }
+                _ => panic!("program is not a single for statement"),
+            }
+            assert_eq!(program.string(), expected);
+        }
+
+        let mut parser = Parser::new(Lexer::new("for (1 in xs) {}"));
+        assert_eq!(
+            parser.parse_program().err().unwrap(),
+            "expected loop variable to be IDENT, got INT(1) at 5..6"
+        );
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let tests = vec![
+            (
+                r#"match (x) { 1 => a, -2.5 => b, "s" => c, true => d, _ => e }"#,
+                r#"match(x) {1 => a, (-2.5) => b, "s" => c, true => d, _ => e}"#,
+            ),
+            (
+                r#"match (xs) { [a, [b, _]] if a > b => a, {name, "age": 3} => name, }"#,
+                r#"match(xs) {[a, [b, _]] if (a > b) => a, {name, "age": 3} => name}"#,
+            ),
+            ("match (f(x)) {}", "match(f(x)) {}"),
+        ];
+
+        for (input, expected) in tests {
+            let expression = parse_single_expression(input);
+            assert!(matches!(expression, Expression::MatchExpression(_)));
+            assert_eq!(expression.span(), Span::new(0, input.len()));
+            assert_eq!(expression.string(), expected);
+        }
+
+        let errors = vec![
+            (
+                "match (x) { [a, a] => a }",
+                "a is bound more than once in the same pattern at 16..17",
+            ),
+            (
+                "match (x) { -y => 1 }",
+                "expected pattern, got (-y) at 12..14",
+            ),
+            (
+                "match (x) { (1) => 1 }",
+                "expected pattern, got LPAREN at 12..13",
+            ),
+            (
+                "match (x) { {[]: y} => y }",
+                "expected hash pattern key, got LBRACKET at 13..14",
+            ),
+            (
+                "match (x) { 1 => a 2 => b }",
+                "expected next token to be COMMA, got INT(2) at 19..20",
+            ),
+        ];
+        for (input, expected) in errors {
+            let mut parser = Parser::new(Lexer::new(input));
+            assert_eq!(parser.parse_program().err().unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_destructure_statements() {
+        let tests = vec![
+            ("let [a, b, ...rest] = arr;", "let [a, b, ...rest] = arr;"),
+            (
+                r#"const {name, "age": [x, _]} = person"#,
+                r#"const {name, "age": [x, _]} = person;"#,
+            ),
+            ("let [..._,] = xs;", "let [..._] = xs;"),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            match &program.statements[..] {
+                [Statement::DestructureStatement(destructure)] => {
+                    assert_eq!(destructure.span, Span::new(0, input.len()))
+                }
+                _ => panic!("program is not a single destructure statement"),
+            }
+            assert_eq!(program.string(), expected);
+        }
+
+        let errors = vec![
+            (
+                "let [a, ...r, b] = xs;",
+                "rest pattern must come last at 14..15",
+            ),
+            (
+                "let [...1] = xs;",
+                "expected rest pattern to be IDENT, got INT(1) at 8..9",
+            ),
+            (
+                "let {a, a} = h;",
+                "a is bound more than once in the same pattern at 8..9",
+            ),
+        ];
+        for (input, expected) in errors {
+            let mut parser = Parser::new(Lexer::new(input));
+            assert_eq!(parser.parse_program().err().unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_throw_and_try() {
+        let tests = vec![
+            ("throw x", "throw x;"),
+            (r#"throw "a" + b;"#, r#"throw ("a" + b);"#),
+            (
+                "let y = try { f() } catch (e) { e } finally { g() };",
+                "let y = try f()catch(e) efinally g();",
+            ),
+            ("try { f() } finally {}", "try f()finally "),
+        ];
+
+        for (input, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            assert_eq!(program.statements[0].span(), Span::new(0, input.len()));
+            assert_eq!(program.string(), expected);
+        }
+
+        let errors = vec![
+            (
+                "try { f() }",
+                "expected catch or finally after try block, got EOF at 11..11",
+            ),
+            (
+                "try {} catch (1) {}",
+                "expected catch parameter to be IDENT, got INT(1) at 14..15",
+            ),
+            ("throw;", "no prefix parse function for SEMICOLON at 5..6"),
+        ];
+        for (input, expected) in errors {
+            let mut parser = Parser::new(Lexer::new(input));
+            assert_eq!(parser.parse_program().err().unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_break_outside_of_loop() {
+        let input = "break; while (true) { let f = fn() { continue; }; break }";
+        let mut parser = Parser::new(Lexer::new(input));
+
+        assert_eq!(
+            parser.parse_program().err().unwrap(),
+            "break outside of a loop at 0..5\n\
+             continue outside of a loop at 37..45"
+        );
+    }
+
+    #[test]
+    fn test_function_statements() {
+        let tests = vec![
+            ("fn add(a, b) { a + b }", "add", "fn add(a, b) (a + b)"),
+            ("fn f() {};", "f", "fn f() "),
+        ];
+
+        for (input, name, expected) in tests {
+            let mut parser = Parser::new(Lexer::new(input));
+            let program = parser.parse_program().unwrap();
+            match &program.statements[..] {
+                [Statement::FunctionStatement(declaration)] => {
+                    assert_eq!(declaration.name.value, name);
+                    assert_eq!(declaration.function.name.as_deref(), Some(name));
+                    assert_eq!(declaration.span, Span::new(0, input.len()));
+                }
+                _ => panic!("program is not a single function declaration"),
+            }
+            assert_eq!(program.string(), expected);
+        }
+
+        let input = "let f = fn() {}; const g = fn() {}; let h = id(fn() {}); fn() {}();";
+        let mut parser = Parser::new(Lexer::new(input));
+        let program = parser.parse_program().unwrap();
+        let mut names = vec![];
+        for statement in &program.statements {
+            let value = match statement {
+                Statement::LetStatement(let_statement) => &let_statement.value,
+                Statement::ExpressionStatement(statement) => &statement.expression,
+                _ => panic!("unexpected statement at {}", statement.span()),
+            };
+            let function = match value {
+                Expression::FunctionLiteral(function) => function,
+                Expression::CallExpression(call) => match &*call.function {
+                    Expression::FunctionLiteral(function) => function,
+                    _ => match &call.arguments[0] {
+                        Expression::FunctionLiteral(function) => function,
+                        _ => panic!("no function literal in {}", value.string()),
+                    },
+                },
+                _ => panic!("no function literal in {}", value.string()),
+            };
+            names.push(function.name.as_deref());
+        }
+        assert_eq!(names, vec![Some("f"), Some("g"), None, None]);
+
+        let errors = vec![
+            (
+                "fn f {}",
+                "expected next token to be LPAREN, got LBRACE at 5..6",
+            ),
+            (
+                "const f = 1; if (x) { fn f() {} }",
+                "cannot redeclare constant f at 25..26",
+            ),
+        ];
+        for (input, expected) in errors {
+            let mut parser = Parser::new(Lexer::new(input));
+            assert_eq!(parser.parse_program().err().unwrap(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_function_literal_parsing() {
+        let tests = vec![
+            ("fn() {};", vec![]),
+            ("fn(x) {};", vec!["x"]),
+            ("fn(x, y, z) { x + y; };", vec!["x", "y", "z"]),
+        ];
+
+        for (input, expected) in tests {
+            let function = match parse_single_expression(input) {
+                Expression::FunctionLiteral(function) => function,
+                _ => panic!("expression is not a function literal"),
+            };
+            let parameters: Vec<String> = function
+                .parameters
+                .iter()
+                .map(|p| p.value.clone())
+                .collect();
+            assert_eq!(parameters, expected);
+        }
+    }
+
+    #[test]
+    fn test_call_expression_parsing() {
+        let call = match parse_single_expression("add(1, 2 * 3, 4 + 5);") {
+            Expression::CallExpression(call) => call,
+            _ => panic!("expression is not a call expression"),
+        };
+
+        assert_eq!(call.function.string(), "add");
+        let arguments: Vec<String> = call.arguments.iter().map(|a| a.string()).collect();
+        assert_eq!(arguments, vec!["1", "(2 * 3)", "(4 + 5)"]);
+    }
+
+    #[test]
+    fn test_string_literal_expression() {
+        let expression = parse_single_expression(r#""hello \"world\"";"#);
+        assert_eq!(
+            expression,
+            Expression::StringLiteral(StringLiteral {
+                token: Token::STRING("hello \"world\"".to_string()),
+                value: "hello \"world\"".to_string(),
+                span: Span::new(0, 17),
+            })
+        );
+        assert_eq!(expression.string(), r#""hello \"world\"""#);
+    }
+
+    #[test]
+    fn test_float_literal_expression() {
+        let expression = parse_single_expression("2.5e-3;");
+        assert_eq!(
+            expression,
+            Expression::FloatLiteral(FloatLiteral {
+                token: Token::FLOAT(0.0025),
+                value: 0.0025,
+                span: Span::new(0, 6),
+            })
+        );
+        assert_eq!(expression.string(), "0.0025");
+    }
+
+    #[test]
+    fn test_array_and_index_expressions() {
+        let array = match parse_single_expression("[1, 2 * 2, 3 + 3]") {
+            Expression::ArrayLiteral(array) => array,
+            _ => panic!("expression is not an array literal"),
+        };
+        let elements: Vec<String> = array.elements.iter().map(|e| e.string()).collect();
+        assert_eq!(elements, vec!["1", "(2 * 2)", "(3 + 3)"]);
+        assert_eq!(array.span, Span::new(0, 17));
+
+        let index = match parse_single_expression("myArray[1 + 1]") {
+            Expression::IndexExpression(index) => index,
+            _ => panic!("expression is not an index expression"),
+        };
+        assert_eq!(index.left.string(), "myArray");
+        assert_eq!(index.index.string(), "(1 + 1)");
+        assert_eq!(index.span, Span::new(0, 14));
+    }
+
+    #[test]
+    fn test_hash_literals() {
+        let tests = vec![
+            ("{}", vec![]),
+            (
+                r#"{"one": 1, "two": 2}"#,
+                vec![(r#""one""#, "1"), (r#""two""#, "2")],
+            ),
+            (
+                r#"{"one": 0 + 1, true: 10 - 8, 3: 15 / 5}"#,
+                vec![
+                    (r#""one""#, "(0 + 1)"),
+                    ("true", "(10 - 8)"),
+                    ("3", "(15 / 5)"),
+                ],
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let hash = match parse_single_expression(input) {
+                Expression::HashLiteral(hash) => hash,
+                _ => panic!("expression is not a hash literal"),
+            };
+            let pairs: Vec<(String, String)> = hash
+                .pairs
+                .iter()
+                .map(|(key, value)| (key.string(), value.string()))
+                .collect();
+            let expected: Vec<(String, String)> = expected
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            assert_eq!(pairs, expected, "{}", input);
+        }
+
+        let mut parser = Parser::new(Lexer::new(r#"{"a" 1}"#));
+        assert_eq!(
+            parser.parse_program().err().unwrap(),
+            "expected next token to be COLON, got INT(1) at 5..6"
+        );
+    }
+
+    #[test]
+    fn test_unterminated_block() {
+        let lexer = Lexer::new("if (x) { x");
+        let mut parser = Parser::new(lexer);
+
+        assert_eq!(
+            parser.parse_program().err().unwrap(),
+            "expected RBRACE to close block, got EOF at 10..10"
+        );
+    }
 }
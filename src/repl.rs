@@ -1,23 +1,92 @@
+use monkey_language_rust::{Error, Frame, Interpreter};
 use std::io::{self, Write};
-use crate::lexer::Lexer;
-use crate::token::Token;
 
 const PROMPT: &str = ">> ";
 
+// Lines of a stack trace printed at either end when it is longer than twice
+// this; the ones in between are left out.
+const TRACE_ENDS: usize = 10;
+
 pub fn start() {
+    let mut interpreter = Interpreter::new();
     let mut input = String::new();
     loop {
         print!("{}", PROMPT);
         io::stdout().flush().unwrap();
         input.clear();
-        io::stdin().read_line(&mut input).unwrap();
-        let mut lexer = Lexer::new(&input);
-        loop {
-            let tok = lexer.next_token();
-            println!("{:?}", tok);
-            if tok == Token::EOF {
-                break;
+        if io::stdin().read_line(&mut input).unwrap() == 0 {
+            break;
+        }
+        match interpreter.eval(&input) {
+            Ok(value) => println!("{}", value),
+            Err(Error::Runtime(e)) => {
+                println!("{}", e);
+                for line in trace(&e.stack) {
+                    println!("    {}", line);
+                }
             }
+            Err(e) => println!("{}", e),
         }
     }
 }
+
+// The lines printed for `stack`. A run of the same frame, as recursion
+// leaves, is printed once with a count, and only the ends of a trace that is
+// still long are kept.
+fn trace(stack: &[Frame]) -> Vec<String> {
+    let mut lines = vec![];
+    let mut frames = stack.iter().peekable();
+    while let Some(frame) = frames.next() {
+        lines.push(frame.to_string());
+        let mut repeated = 0;
+        while frames.next_if_eq(&frame).is_some() {
+            repeated += 1;
+        }
+        if repeated > 0 {
+            lines.push(format!("... repeated {} more times", repeated));
+        }
+    }
+
+    if lines.len() > 2 * TRACE_ENDS {
+        let elided = lines.len() - 2 * TRACE_ENDS;
+        lines.splice(
+            TRACE_ENDS..lines.len() - TRACE_ENDS,
+            [format!("... {} more lines", elided)],
+        );
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use monkey_language_rust::Span;
+
+    fn frame(function: &str, start: usize) -> Frame {
+        Frame {
+            function: Some(function.to_string()),
+            span: Span::new(start, start + 1),
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut stack = vec![frame("f", 1); 500];
+        stack.push(frame("g", 2));
+        assert_eq!(
+            trace(&stack),
+            vec![
+                "in f called at 1..2",
+                "... repeated 499 more times",
+                "in g called at 2..3",
+            ]
+        );
+
+        let stack: Vec<Frame> = (0..30).map(|i| frame("h", i)).collect();
+        let lines = trace(&stack);
+        assert_eq!(lines.len(), 21);
+        assert_eq!(lines[9], "in h called at 9..10");
+        assert_eq!(lines[10], "... 10 more lines");
+        assert_eq!(lines[11], "in h called at 20..21");
+    }
+}
@@ -0,0 +1,314 @@
+//! S-expression dump of the ast, one statement per line, e.g.
+//! `(let x (+ 1 (* 2 3)))`. Unlike `Program::string` every node is spelled
+//! out, so it shows the exact shape of the tree.
+
+use crate::ast::{quote, BlockStatement, Expression, Pattern, Program, Statement};
+
+pub fn to_sexp(program: &Program) -> String {
+    let mut out = String::new();
+    for statement in &program.statements {
+        out.push_str(&statement_to_sexp(statement));
+        out.push('\n');
+    }
+    out
+}
+
+fn statement_to_sexp(statement: &Statement) -> String {
+    match statement {
+        Statement::LetStatement(let_statement) => format!(
+            "({} {} {})",
+            let_statement.kind.keyword(),
+            let_statement.name.value,
+            expression_to_sexp(&let_statement.value)
+        ),
+        Statement::DestructureStatement(destructure) => format!(
+            "({} {} {})",
+            destructure.kind.keyword(),
+            pattern_to_sexp(&destructure.pattern),
+            expression_to_sexp(&destructure.value)
+        ),
+        Statement::ReturnStatement(return_statement) => format!(
+            "(return {})",
+            expression_to_sexp(&return_statement.return_value)
+        ),
+        Statement::ThrowStatement(throw_statement) => {
+            format!("(throw {})", expression_to_sexp(&throw_statement.value))
+        }
+        Statement::FunctionStatement(declaration) => format!(
+            "(defn {} {})",
+            declaration.name.value,
+            expression_to_sexp(&Expression::FunctionLiteral(declaration.function.clone()))
+        ),
+        Statement::ExpressionStatement(expression_statement) => format!(
+            "(expr {})",
+            expression_to_sexp(&expression_statement.expression)
+        ),
+        Statement::WhileStatement(while_statement) => format!(
+            "(while {} {})",
+            expression_to_sexp(&while_statement.condition),
+            block_to_sexp(&while_statement.body)
+        ),
+        Statement::ForStatement(for_statement) => {
+            let variables = match &for_statement.key {
+                Some(key) => format!("({} {})", key.value, for_statement.value.value),
+                None => for_statement.value.value.clone(),
+            };
+            format!(
+                "(for {} {} {})",
+                variables,
+                expression_to_sexp(&for_statement.iterable),
+                block_to_sexp(&for_statement.body)
+            )
+        }
+        Statement::BreakStatement(_) => "(break)".to_string(),
+        Statement::ContinueStatement(_) => "(continue)".to_string(),
+    }
+}
+
+fn block_to_sexp(block: &BlockStatement) -> String {
+    let mut out = "(block".to_string();
+    for statement in &block.statements {
+        out.push(' ');
+        out.push_str(&statement_to_sexp(statement));
+    }
+    out.push(')');
+    out
+}
+
+fn expression_to_sexp(expression: &Expression) -> String {
+    crate::with_stack(|| expression_kind_to_sexp(expression))
+}
+
+fn expression_kind_to_sexp(expression: &Expression) -> String {
+    match expression {
+        Expression::Identifier(identifier) => identifier.value.clone(),
+        Expression::IntegerLiteral(integer_literal) => integer_literal.value.to_string(),
+        Expression::FloatLiteral(float_literal) => float_literal.string(),
+        Expression::Boolean(boolean) => boolean.value.to_string(),
+        Expression::PrefixExpression(prefix) => {
+            format!(
+                "({} {})",
+                prefix.operator,
+                expression_to_sexp(&prefix.right)
+            )
+        }
+        Expression::AssignExpression(assign) => format!(
+            "({} {} {})",
+            assign.operator,
+            expression_to_sexp(&assign.target),
+            expression_to_sexp(&assign.value)
+        ),
+        Expression::InfixExpression(infix) => format!(
+            "({} {} {})",
+            infix.operator,
+            expression_to_sexp(&infix.left),
+            expression_to_sexp(&infix.right)
+        ),
+        Expression::IfExpression(if_expression) => {
+            let mut out = format!(
+                "(if {} {}",
+                expression_to_sexp(&if_expression.condition),
+                block_to_sexp(&if_expression.consequence)
+            );
+            if let Some(alternative) = &if_expression.alternative {
+                out.push(' ');
+                out.push_str(&block_to_sexp(alternative));
+            }
+            out.push(')');
+            out
+        }
+        Expression::TryExpression(try_expression) => {
+            let mut out = format!("(try {}", block_to_sexp(&try_expression.body));
+            if let Some(catch) = &try_expression.catch {
+                out.push_str(&format!(
+                    " (catch {} {})",
+                    catch.parameter.value,
+                    block_to_sexp(&catch.body)
+                ));
+            }
+            if let Some(finally) = &try_expression.finally {
+                out.push_str(&format!(" (finally {})", block_to_sexp(finally)));
+            }
+            out.push(')');
+            out
+        }
+        Expression::FunctionLiteral(function) => {
+            let parameters: Vec<&str> = function
+                .parameters
+                .iter()
+                .map(|parameter| parameter.value.as_str())
+                .collect();
+            format!(
+                "(fn ({}) {})",
+                parameters.join(" "),
+                block_to_sexp(&function.body)
+            )
+        }
+        Expression::CallExpression(call) => {
+            let mut out = format!("(call {}", expression_to_sexp(&call.function));
+            for argument in &call.arguments {
+                out.push(' ');
+                out.push_str(&expression_to_sexp(argument));
+            }
+            out.push(')');
+            out
+        }
+        Expression::StringLiteral(string_literal) => quote(&string_literal.value),
+        Expression::ArrayLiteral(array) => {
+            let mut out = "(array".to_string();
+            for element in &array.elements {
+                out.push(' ');
+                out.push_str(&expression_to_sexp(element));
+            }
+            out.push(')');
+            out
+        }
+        Expression::HashLiteral(hash) => {
+            let mut out = "(hash".to_string();
+            for (key, value) in &hash.pairs {
+                out.push_str(&format!(
+                    " ({} {})",
+                    expression_to_sexp(key),
+                    expression_to_sexp(value)
+                ));
+            }
+            out.push(')');
+            out
+        }
+        Expression::IndexExpression(index) => format!(
+            "(index {} {})",
+            expression_to_sexp(&index.left),
+            expression_to_sexp(&index.index)
+        ),
+        Expression::MatchExpression(match_expression) => {
+            let mut out = format!("(match {}", expression_to_sexp(&match_expression.subject));
+            for arm in &match_expression.arms {
+                out.push_str(&format!(" (arm {}", pattern_to_sexp(&arm.pattern)));
+                if let Some(guard) = &arm.guard {
+                    out.push_str(&format!(" (if {})", expression_to_sexp(guard)));
+                }
+                out.push_str(&format!(" {})", expression_to_sexp(&arm.body)));
+            }
+            out.push(')');
+            out
+        }
+    }
+}
+
+fn pattern_to_sexp(pattern: &Pattern) -> String {
+    crate::with_stack(|| pattern_kind_to_sexp(pattern))
+}
+
+fn pattern_kind_to_sexp(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Literal(literal) => expression_to_sexp(literal),
+        Pattern::Wildcard(_) => "_".to_string(),
+        Pattern::Binding(identifier) => identifier.value.clone(),
+        Pattern::Array(array) => {
+            let mut out = "(array".to_string();
+            for element in &array.elements {
+                out.push(' ');
+                out.push_str(&pattern_to_sexp(element));
+            }
+            if let Some(rest) = &array.rest {
+                out.push_str(&format!(" (rest {})", pattern_to_sexp(rest)));
+            }
+            out.push(')');
+            out
+        }
+        Pattern::Hash(hash) => {
+            let mut out = "(hash".to_string();
+            for (key, pattern) in &hash.pairs {
+                out.push_str(&format!(
+                    " ({} {})",
+                    expression_to_sexp(key),
+                    pattern_to_sexp(pattern)
+                ));
+            }
+            out.push(')');
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::Path;
+
+    fn dump(input: &str) -> String {
+        let mut parser = Parser::new(Lexer::new(input));
+        match parser.parse_program() {
+            Ok(program) => to_sexp(&program),
+            Err(e) => format!("error: {}\n", e.replace('\n', "\nerror: ")),
+        }
+    }
+
+    #[test]
+    fn test_to_sexp() {
+        let tests = vec![
+            ("let x = 1 + 2 * 3;", "(let x (+ 1 (* 2 3)))\n"),
+            ("x; -x", "(expr x)\n(expr (- x))\n"),
+            ("return !true == false;", "(return (== (! true) false))\n"),
+            (
+                "if (a) { b; return c } else { }",
+                "(expr (if a (block (expr b) (return c)) (block)))\n",
+            ),
+            (
+                "fn(a, b) { a }(1, f())",
+                "(expr (call (fn (a b) (block (expr a))) 1 (call f)))\n",
+            ),
+            (
+                r#"{"a": [1, "b"]}["a"][0]"#,
+                "(expr (index (index (hash (\"a\" (array 1 \"b\"))) \"a\") 0))\n",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(dump(input), expected);
+        }
+    }
+
+    // Every `tests/golden/NAME.mk` is parsed and its dump compared with
+    // `NAME.sexp` next to it. Run with `UPDATE_GOLDEN=1` to (re)write the
+    // expected files after an intended parser change.
+    #[test]
+    fn test_golden_files() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+        let mut fixtures: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "mk"))
+            .collect();
+        fixtures.sort();
+        assert!(!fixtures.is_empty(), "no fixtures in {}", dir.display());
+
+        for fixture in fixtures {
+            let actual = dump(&fs::read_to_string(&fixture).unwrap());
+            let golden = fixture.with_extension("sexp");
+            if update {
+                fs::write(&golden, &actual).unwrap();
+                continue;
+            }
+            let expected = fs::read_to_string(&golden).unwrap_or_else(|_| {
+                panic!(
+                    "missing {}, run with UPDATE_GOLDEN=1 to create it",
+                    golden.display()
+                )
+            });
+            assert_eq!(
+                actual,
+                expected,
+                "{} does not match {}",
+                fixture.display(),
+                golden.display()
+            );
+        }
+    }
+}
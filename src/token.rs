@@ -1,4 +1,61 @@
+use num_bigint::BigInt;
 use phf::{phf_map, Map};
+use std::fmt;
+
+/// Half-open range of character offsets into the lexer input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+}
+
+/// Whitespace or a comment, kept so the source can be reproduced exactly.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+/// A token together with its source text and the trivia that precedes it.
+/// Concatenating the trivia and text of every token up to and including
+/// `EOF` gives back the original input.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxToken {
+    pub kind: Token,
+    pub text: String,
+    pub span: Span,
+    pub leading_trivia: Vec<Trivia>,
+}
+
+impl SyntaxToken {
+    pub fn full_text(&self) -> String {
+        let mut out = String::new();
+        for trivia in &self.leading_trivia {
+            out.push_str(&trivia.text);
+        }
+        out.push_str(&self.text);
+        out
+    }
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
@@ -7,7 +64,9 @@ pub enum Token {
 
     // Identifiers + literals
     IDENT(String),
-    INT(i64),
+    INT(BigInt),
+    FLOAT(f64),
+    STRING(String),
 
     // Operators
     ASSIGN,
@@ -18,18 +77,42 @@ pub enum Token {
     BANG,
     ASTERISK,
     SLASH,
+    PERCENT,
 
     LT,
     GT,
+    LtEQ,
+    GtEQ,
+
+    AND,
+    OR,
+
+    AMPERSAND,
+    PIPE,
+    CARET,
+    TILDE,
+    LSHIFT,
+    RSHIFT,
+    DOTDOT,
+    DotDotEQ,
+    ELLIPSIS,
+    PlusEQ,
+    MinusEQ,
+    AsteriskEQ,
+    SlashEQ,
+    FatArrow,
 
     //Delimiters
     COMMA,
     SEMICOLON,
+    COLON,
 
     LPAREN,
     RPAREN,
     LBRACE,
     RBRACE,
+    LBRACKET,
+    RBRACKET,
 
     // keywords
     FUNCTION,
@@ -39,6 +122,17 @@ pub enum Token {
     IF,
     ELSE,
     RETURN,
+    WHILE,
+    BREAK,
+    CONTINUE,
+    FOR,
+    IN,
+    CONST,
+    MATCH,
+    THROW,
+    TRY,
+    CATCH,
+    FINALLY,
 }
 use Token::*;
 
@@ -49,6 +143,8 @@ impl Token {
             EOF => "EOF".to_string(),
             IDENT(s) => s.to_string(),
             INT(i) => i.to_string(),
+            FLOAT(f) => format!("{:?}", f),
+            STRING(s) => s.to_string(),
             ASSIGN => "=".to_string(),
             EQ => "==".to_string(),
             NotEQ => "!=".to_string(),
@@ -57,14 +153,36 @@ impl Token {
             BANG => "!".to_string(),
             ASTERISK => "*".to_string(),
             SLASH => "/".to_string(),
+            PERCENT => "%".to_string(),
             LT => "<".to_string(),
             GT => ">".to_string(),
+            LtEQ => "<=".to_string(),
+            GtEQ => ">=".to_string(),
+            AND => "&&".to_string(),
+            OR => "||".to_string(),
+            AMPERSAND => "&".to_string(),
+            PIPE => "|".to_string(),
+            CARET => "^".to_string(),
+            TILDE => "~".to_string(),
+            LSHIFT => "<<".to_string(),
+            RSHIFT => ">>".to_string(),
+            DOTDOT => "..".to_string(),
+            DotDotEQ => "..=".to_string(),
+            ELLIPSIS => "...".to_string(),
+            PlusEQ => "+=".to_string(),
+            MinusEQ => "-=".to_string(),
+            AsteriskEQ => "*=".to_string(),
+            SlashEQ => "/=".to_string(),
+            FatArrow => "=>".to_string(),
             COMMA => ",".to_string(),
             SEMICOLON => ";".to_string(),
+            COLON => ":".to_string(),
             LPAREN => "(".to_string(),
             RPAREN => ")".to_string(),
             LBRACE => "{".to_string(),
             RBRACE => "}".to_string(),
+            LBRACKET => "[".to_string(),
+            RBRACKET => "]".to_string(),
             FUNCTION => "fn".to_string(),
             LET => "let".to_string(),
             TRUE => "true".to_string(),
@@ -72,6 +190,17 @@ impl Token {
             IF => "if".to_string(),
             ELSE => "else".to_string(),
             RETURN => "return".to_string(),
+            WHILE => "while".to_string(),
+            BREAK => "break".to_string(),
+            CONTINUE => "continue".to_string(),
+            FOR => "for".to_string(),
+            IN => "in".to_string(),
+            CONST => "const".to_string(),
+            MATCH => "match".to_string(),
+            THROW => "throw".to_string(),
+            TRY => "try".to_string(),
+            CATCH => "catch".to_string(),
+            FINALLY => "finally".to_string(),
         }
     }
 }
@@ -84,4 +213,15 @@ pub static KEYWORDS: Map<&'static str, Token> = phf_map! {
     "if" => IF,
     "else" => ELSE,
     "return" => RETURN,
+    "while" => WHILE,
+    "break" => BREAK,
+    "continue" => CONTINUE,
+    "for" => FOR,
+    "in" => IN,
+    "const" => CONST,
+    "match" => MATCH,
+    "throw" => THROW,
+    "try" => TRY,
+    "catch" => CATCH,
+    "finally" => FINALLY,
 };
@@ -0,0 +1,807 @@
+//! Traversal of the ast. Implementors override the `visit_*` methods for the
+//! nodes they care about; the defaults call the matching `walk_*` function,
+//! which visits the node's children. An override that still wants to descend
+//! calls `walk_*` itself.
+
+use crate::ast::{
+    ArrayLiteral, AssignExpression, BlockStatement, Boolean, BreakStatement, CallExpression,
+    ContinueStatement, DestructureStatement, Expression, ExpressionStatement, FloatLiteral,
+    ForStatement, FunctionLiteral, FunctionStatement, HashLiteral, Identifier, IfExpression,
+    IndexExpression, InfixExpression, IntegerLiteral, LetStatement, MatchArm, MatchExpression,
+    Pattern, PrefixExpression, Program, ReturnStatement, Statement, StringLiteral, ThrowStatement,
+    TryExpression, WhileStatement,
+};
+
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program);
+    }
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement);
+    }
+    fn visit_let_statement(&mut self, let_statement: &LetStatement) {
+        walk_let_statement(self, let_statement);
+    }
+    fn visit_destructure_statement(&mut self, destructure: &DestructureStatement) {
+        walk_destructure_statement(self, destructure);
+    }
+    fn visit_return_statement(&mut self, return_statement: &ReturnStatement) {
+        walk_return_statement(self, return_statement);
+    }
+    fn visit_throw_statement(&mut self, throw_statement: &ThrowStatement) {
+        walk_throw_statement(self, throw_statement);
+    }
+    fn visit_function_statement(&mut self, declaration: &FunctionStatement) {
+        walk_function_statement(self, declaration);
+    }
+    fn visit_expression_statement(&mut self, expression_statement: &ExpressionStatement) {
+        walk_expression_statement(self, expression_statement);
+    }
+    fn visit_while_statement(&mut self, while_statement: &WhileStatement) {
+        walk_while_statement(self, while_statement);
+    }
+    fn visit_for_statement(&mut self, for_statement: &ForStatement) {
+        walk_for_statement(self, for_statement);
+    }
+    fn visit_break_statement(&mut self, _break_statement: &BreakStatement) {}
+    fn visit_continue_statement(&mut self, _continue_statement: &ContinueStatement) {}
+    fn visit_block_statement(&mut self, block: &BlockStatement) {
+        walk_block_statement(self, block);
+    }
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression);
+    }
+    fn visit_identifier(&mut self, _identifier: &Identifier) {}
+    fn visit_integer_literal(&mut self, _integer_literal: &IntegerLiteral) {}
+    fn visit_float_literal(&mut self, _float_literal: &FloatLiteral) {}
+    fn visit_boolean(&mut self, _boolean: &Boolean) {}
+    fn visit_string_literal(&mut self, _string_literal: &StringLiteral) {}
+    fn visit_prefix_expression(&mut self, prefix: &PrefixExpression) {
+        walk_prefix_expression(self, prefix);
+    }
+    fn visit_infix_expression(&mut self, infix: &InfixExpression) {
+        walk_infix_expression(self, infix);
+    }
+    fn visit_if_expression(&mut self, if_expression: &IfExpression) {
+        walk_if_expression(self, if_expression);
+    }
+    fn visit_function_literal(&mut self, function: &FunctionLiteral) {
+        walk_function_literal(self, function);
+    }
+    fn visit_call_expression(&mut self, call: &CallExpression) {
+        walk_call_expression(self, call);
+    }
+    fn visit_array_literal(&mut self, array: &ArrayLiteral) {
+        walk_array_literal(self, array);
+    }
+    fn visit_hash_literal(&mut self, hash: &HashLiteral) {
+        walk_hash_literal(self, hash);
+    }
+    fn visit_index_expression(&mut self, index: &IndexExpression) {
+        walk_index_expression(self, index);
+    }
+    fn visit_assign_expression(&mut self, assign: &AssignExpression) {
+        walk_assign_expression(self, assign);
+    }
+    fn visit_match_expression(&mut self, match_expression: &MatchExpression) {
+        walk_match_expression(self, match_expression);
+    }
+    fn visit_try_expression(&mut self, try_expression: &TryExpression) {
+        walk_try_expression(self, try_expression);
+    }
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        walk_match_arm(self, arm);
+    }
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern);
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for statement in &program.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    match statement {
+        Statement::LetStatement(let_statement) => visitor.visit_let_statement(let_statement),
+        Statement::ReturnStatement(return_statement) => {
+            visitor.visit_return_statement(return_statement)
+        }
+        Statement::ExpressionStatement(expression_statement) => {
+            visitor.visit_expression_statement(expression_statement)
+        }
+        Statement::WhileStatement(while_statement) => {
+            visitor.visit_while_statement(while_statement)
+        }
+        Statement::ForStatement(for_statement) => visitor.visit_for_statement(for_statement),
+        Statement::DestructureStatement(destructure) => {
+            visitor.visit_destructure_statement(destructure)
+        }
+        Statement::ThrowStatement(throw_statement) => {
+            visitor.visit_throw_statement(throw_statement)
+        }
+        Statement::FunctionStatement(declaration) => visitor.visit_function_statement(declaration),
+        Statement::BreakStatement(break_statement) => {
+            visitor.visit_break_statement(break_statement)
+        }
+        Statement::ContinueStatement(continue_statement) => {
+            visitor.visit_continue_statement(continue_statement)
+        }
+    }
+}
+
+pub fn walk_let_statement<V: Visitor + ?Sized>(visitor: &mut V, let_statement: &LetStatement) {
+    visitor.visit_identifier(&let_statement.name);
+    visitor.visit_expression(&let_statement.value);
+}
+
+pub fn walk_destructure_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    destructure: &DestructureStatement,
+) {
+    visitor.visit_pattern(&destructure.pattern);
+    visitor.visit_expression(&destructure.value);
+}
+
+pub fn walk_return_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    return_statement: &ReturnStatement,
+) {
+    visitor.visit_expression(&return_statement.return_value);
+}
+
+pub fn walk_throw_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    throw_statement: &ThrowStatement,
+) {
+    visitor.visit_expression(&throw_statement.value);
+}
+
+pub fn walk_function_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    declaration: &FunctionStatement,
+) {
+    visitor.visit_identifier(&declaration.name);
+    visitor.visit_function_literal(&declaration.function);
+}
+
+pub fn walk_expression_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    expression_statement: &ExpressionStatement,
+) {
+    visitor.visit_expression(&expression_statement.expression);
+}
+
+pub fn walk_while_statement<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    while_statement: &WhileStatement,
+) {
+    visitor.visit_expression(&while_statement.condition);
+    visitor.visit_block_statement(&while_statement.body);
+}
+
+pub fn walk_for_statement<V: Visitor + ?Sized>(visitor: &mut V, for_statement: &ForStatement) {
+    if let Some(key) = &for_statement.key {
+        visitor.visit_identifier(key);
+    }
+    visitor.visit_identifier(&for_statement.value);
+    visitor.visit_expression(&for_statement.iterable);
+    visitor.visit_block_statement(&for_statement.body);
+}
+
+pub fn walk_block_statement<V: Visitor + ?Sized>(visitor: &mut V, block: &BlockStatement) {
+    for statement in &block.statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    crate::with_stack(|| walk_expression_kind(visitor, expression))
+}
+
+fn walk_expression_kind<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    match expression {
+        Expression::Identifier(identifier) => visitor.visit_identifier(identifier),
+        Expression::IntegerLiteral(integer_literal) => {
+            visitor.visit_integer_literal(integer_literal)
+        }
+        Expression::FloatLiteral(float_literal) => visitor.visit_float_literal(float_literal),
+        Expression::Boolean(boolean) => visitor.visit_boolean(boolean),
+        Expression::PrefixExpression(prefix) => visitor.visit_prefix_expression(prefix),
+        Expression::InfixExpression(infix) => visitor.visit_infix_expression(infix),
+        Expression::IfExpression(if_expression) => visitor.visit_if_expression(if_expression),
+        Expression::FunctionLiteral(function) => visitor.visit_function_literal(function),
+        Expression::CallExpression(call) => visitor.visit_call_expression(call),
+        Expression::StringLiteral(string_literal) => visitor.visit_string_literal(string_literal),
+        Expression::ArrayLiteral(array) => visitor.visit_array_literal(array),
+        Expression::HashLiteral(hash) => visitor.visit_hash_literal(hash),
+        Expression::IndexExpression(index) => visitor.visit_index_expression(index),
+        Expression::AssignExpression(assign) => visitor.visit_assign_expression(assign),
+        Expression::MatchExpression(match_expression) => {
+            visitor.visit_match_expression(match_expression)
+        }
+        Expression::TryExpression(try_expression) => visitor.visit_try_expression(try_expression),
+    }
+}
+
+pub fn walk_prefix_expression<V: Visitor + ?Sized>(visitor: &mut V, prefix: &PrefixExpression) {
+    visitor.visit_expression(&prefix.right);
+}
+
+pub fn walk_infix_expression<V: Visitor + ?Sized>(visitor: &mut V, infix: &InfixExpression) {
+    visitor.visit_expression(&infix.left);
+    visitor.visit_expression(&infix.right);
+}
+
+pub fn walk_if_expression<V: Visitor + ?Sized>(visitor: &mut V, if_expression: &IfExpression) {
+    visitor.visit_expression(&if_expression.condition);
+    visitor.visit_block_statement(&if_expression.consequence);
+    if let Some(alternative) = &if_expression.alternative {
+        visitor.visit_block_statement(alternative);
+    }
+}
+
+pub fn walk_function_literal<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionLiteral) {
+    for parameter in &function.parameters {
+        visitor.visit_identifier(parameter);
+    }
+    visitor.visit_block_statement(&function.body);
+}
+
+pub fn walk_call_expression<V: Visitor + ?Sized>(visitor: &mut V, call: &CallExpression) {
+    visitor.visit_expression(&call.function);
+    for argument in &call.arguments {
+        visitor.visit_expression(argument);
+    }
+}
+
+pub fn walk_array_literal<V: Visitor + ?Sized>(visitor: &mut V, array: &ArrayLiteral) {
+    for element in &array.elements {
+        visitor.visit_expression(element);
+    }
+}
+
+pub fn walk_hash_literal<V: Visitor + ?Sized>(visitor: &mut V, hash: &HashLiteral) {
+    for (key, value) in &hash.pairs {
+        visitor.visit_expression(key);
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_index_expression<V: Visitor + ?Sized>(visitor: &mut V, index: &IndexExpression) {
+    visitor.visit_expression(&index.left);
+    visitor.visit_expression(&index.index);
+}
+
+pub fn walk_assign_expression<V: Visitor + ?Sized>(visitor: &mut V, assign: &AssignExpression) {
+    visitor.visit_expression(&assign.target);
+    visitor.visit_expression(&assign.value);
+}
+
+pub fn walk_match_expression<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    match_expression: &MatchExpression,
+) {
+    visitor.visit_expression(&match_expression.subject);
+    for arm in &match_expression.arms {
+        visitor.visit_match_arm(arm);
+    }
+}
+
+pub fn walk_try_expression<V: Visitor + ?Sized>(visitor: &mut V, try_expression: &TryExpression) {
+    visitor.visit_block_statement(&try_expression.body);
+    if let Some(catch) = &try_expression.catch {
+        visitor.visit_identifier(&catch.parameter);
+        visitor.visit_block_statement(&catch.body);
+    }
+    if let Some(finally) = &try_expression.finally {
+        visitor.visit_block_statement(finally);
+    }
+}
+
+pub fn walk_match_arm<V: Visitor + ?Sized>(visitor: &mut V, arm: &MatchArm) {
+    visitor.visit_pattern(&arm.pattern);
+    if let Some(guard) = &arm.guard {
+        visitor.visit_expression(guard);
+    }
+    visitor.visit_expression(&arm.body);
+}
+
+pub fn walk_pattern<V: Visitor + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    crate::with_stack(|| walk_pattern_kind(visitor, pattern))
+}
+
+fn walk_pattern_kind<V: Visitor + ?Sized>(visitor: &mut V, pattern: &Pattern) {
+    match pattern {
+        Pattern::Literal(literal) => visitor.visit_expression(literal),
+        Pattern::Wildcard(_) => {}
+        Pattern::Binding(identifier) => visitor.visit_identifier(identifier),
+        Pattern::Array(array) => {
+            for element in &array.elements {
+                visitor.visit_pattern(element);
+            }
+            if let Some(rest) = &array.rest {
+                visitor.visit_pattern(rest);
+            }
+        }
+        Pattern::Hash(hash) => {
+            for (key, pattern) in &hash.pairs {
+                visitor.visit_expression(key);
+                visitor.visit_pattern(pattern);
+            }
+        }
+    }
+}
+
+/// Like `Visitor`, but with mutable access so nodes can be rewritten in
+/// place, including replacing a whole `Expression` or `Statement`.
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program);
+    }
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement);
+    }
+    fn visit_let_statement_mut(&mut self, let_statement: &mut LetStatement) {
+        walk_let_statement_mut(self, let_statement);
+    }
+    fn visit_destructure_statement_mut(&mut self, destructure: &mut DestructureStatement) {
+        walk_destructure_statement_mut(self, destructure);
+    }
+    fn visit_return_statement_mut(&mut self, return_statement: &mut ReturnStatement) {
+        walk_return_statement_mut(self, return_statement);
+    }
+    fn visit_throw_statement_mut(&mut self, throw_statement: &mut ThrowStatement) {
+        walk_throw_statement_mut(self, throw_statement);
+    }
+    fn visit_function_statement_mut(&mut self, declaration: &mut FunctionStatement) {
+        walk_function_statement_mut(self, declaration);
+    }
+    fn visit_expression_statement_mut(&mut self, expression_statement: &mut ExpressionStatement) {
+        walk_expression_statement_mut(self, expression_statement);
+    }
+    fn visit_while_statement_mut(&mut self, while_statement: &mut WhileStatement) {
+        walk_while_statement_mut(self, while_statement);
+    }
+    fn visit_for_statement_mut(&mut self, for_statement: &mut ForStatement) {
+        walk_for_statement_mut(self, for_statement);
+    }
+    fn visit_break_statement_mut(&mut self, _break_statement: &mut BreakStatement) {}
+    fn visit_continue_statement_mut(&mut self, _continue_statement: &mut ContinueStatement) {}
+    fn visit_block_statement_mut(&mut self, block: &mut BlockStatement) {
+        walk_block_statement_mut(self, block);
+    }
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression);
+    }
+    fn visit_identifier_mut(&mut self, _identifier: &mut Identifier) {}
+    fn visit_integer_literal_mut(&mut self, _integer_literal: &mut IntegerLiteral) {}
+    fn visit_float_literal_mut(&mut self, _float_literal: &mut FloatLiteral) {}
+    fn visit_boolean_mut(&mut self, _boolean: &mut Boolean) {}
+    fn visit_string_literal_mut(&mut self, _string_literal: &mut StringLiteral) {}
+    fn visit_prefix_expression_mut(&mut self, prefix: &mut PrefixExpression) {
+        walk_prefix_expression_mut(self, prefix);
+    }
+    fn visit_infix_expression_mut(&mut self, infix: &mut InfixExpression) {
+        walk_infix_expression_mut(self, infix);
+    }
+    fn visit_if_expression_mut(&mut self, if_expression: &mut IfExpression) {
+        walk_if_expression_mut(self, if_expression);
+    }
+    fn visit_function_literal_mut(&mut self, function: &mut FunctionLiteral) {
+        walk_function_literal_mut(self, function);
+    }
+    fn visit_call_expression_mut(&mut self, call: &mut CallExpression) {
+        walk_call_expression_mut(self, call);
+    }
+    fn visit_array_literal_mut(&mut self, array: &mut ArrayLiteral) {
+        walk_array_literal_mut(self, array);
+    }
+    fn visit_hash_literal_mut(&mut self, hash: &mut HashLiteral) {
+        walk_hash_literal_mut(self, hash);
+    }
+    fn visit_index_expression_mut(&mut self, index: &mut IndexExpression) {
+        walk_index_expression_mut(self, index);
+    }
+    fn visit_assign_expression_mut(&mut self, assign: &mut AssignExpression) {
+        walk_assign_expression_mut(self, assign);
+    }
+    fn visit_match_expression_mut(&mut self, match_expression: &mut MatchExpression) {
+        walk_match_expression_mut(self, match_expression);
+    }
+    fn visit_try_expression_mut(&mut self, try_expression: &mut TryExpression) {
+        walk_try_expression_mut(self, try_expression);
+    }
+    fn visit_match_arm_mut(&mut self, arm: &mut MatchArm) {
+        walk_match_arm_mut(self, arm);
+    }
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        walk_pattern_mut(self, pattern);
+    }
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for statement in &mut program.statements {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    match statement {
+        Statement::LetStatement(let_statement) => visitor.visit_let_statement_mut(let_statement),
+        Statement::ReturnStatement(return_statement) => {
+            visitor.visit_return_statement_mut(return_statement)
+        }
+        Statement::ExpressionStatement(expression_statement) => {
+            visitor.visit_expression_statement_mut(expression_statement)
+        }
+        Statement::WhileStatement(while_statement) => {
+            visitor.visit_while_statement_mut(while_statement)
+        }
+        Statement::ForStatement(for_statement) => visitor.visit_for_statement_mut(for_statement),
+        Statement::DestructureStatement(destructure) => {
+            visitor.visit_destructure_statement_mut(destructure)
+        }
+        Statement::ThrowStatement(throw_statement) => {
+            visitor.visit_throw_statement_mut(throw_statement)
+        }
+        Statement::FunctionStatement(declaration) => {
+            visitor.visit_function_statement_mut(declaration)
+        }
+        Statement::BreakStatement(break_statement) => {
+            visitor.visit_break_statement_mut(break_statement)
+        }
+        Statement::ContinueStatement(continue_statement) => {
+            visitor.visit_continue_statement_mut(continue_statement)
+        }
+    }
+}
+
+pub fn walk_let_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    let_statement: &mut LetStatement,
+) {
+    visitor.visit_identifier_mut(&mut let_statement.name);
+    visitor.visit_expression_mut(&mut let_statement.value);
+}
+
+pub fn walk_destructure_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    destructure: &mut DestructureStatement,
+) {
+    visitor.visit_pattern_mut(&mut destructure.pattern);
+    visitor.visit_expression_mut(&mut destructure.value);
+}
+
+pub fn walk_return_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    return_statement: &mut ReturnStatement,
+) {
+    visitor.visit_expression_mut(&mut return_statement.return_value);
+}
+
+pub fn walk_throw_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    throw_statement: &mut ThrowStatement,
+) {
+    visitor.visit_expression_mut(&mut throw_statement.value);
+}
+
+pub fn walk_function_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    declaration: &mut FunctionStatement,
+) {
+    visitor.visit_identifier_mut(&mut declaration.name);
+    visitor.visit_function_literal_mut(&mut declaration.function);
+}
+
+pub fn walk_expression_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    expression_statement: &mut ExpressionStatement,
+) {
+    visitor.visit_expression_mut(&mut expression_statement.expression);
+}
+
+pub fn walk_while_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    while_statement: &mut WhileStatement,
+) {
+    visitor.visit_expression_mut(&mut while_statement.condition);
+    visitor.visit_block_statement_mut(&mut while_statement.body);
+}
+
+pub fn walk_for_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    for_statement: &mut ForStatement,
+) {
+    if let Some(key) = &mut for_statement.key {
+        visitor.visit_identifier_mut(key);
+    }
+    visitor.visit_identifier_mut(&mut for_statement.value);
+    visitor.visit_expression_mut(&mut for_statement.iterable);
+    visitor.visit_block_statement_mut(&mut for_statement.body);
+}
+
+pub fn walk_block_statement_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    block: &mut BlockStatement,
+) {
+    for statement in &mut block.statements {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    crate::with_stack(|| walk_expression_kind_mut(visitor, expression))
+}
+
+fn walk_expression_kind_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    match expression {
+        Expression::Identifier(identifier) => visitor.visit_identifier_mut(identifier),
+        Expression::IntegerLiteral(integer_literal) => {
+            visitor.visit_integer_literal_mut(integer_literal)
+        }
+        Expression::FloatLiteral(float_literal) => visitor.visit_float_literal_mut(float_literal),
+        Expression::Boolean(boolean) => visitor.visit_boolean_mut(boolean),
+        Expression::PrefixExpression(prefix) => visitor.visit_prefix_expression_mut(prefix),
+        Expression::InfixExpression(infix) => visitor.visit_infix_expression_mut(infix),
+        Expression::IfExpression(if_expression) => visitor.visit_if_expression_mut(if_expression),
+        Expression::FunctionLiteral(function) => visitor.visit_function_literal_mut(function),
+        Expression::CallExpression(call) => visitor.visit_call_expression_mut(call),
+        Expression::StringLiteral(string_literal) => {
+            visitor.visit_string_literal_mut(string_literal)
+        }
+        Expression::ArrayLiteral(array) => visitor.visit_array_literal_mut(array),
+        Expression::HashLiteral(hash) => visitor.visit_hash_literal_mut(hash),
+        Expression::IndexExpression(index) => visitor.visit_index_expression_mut(index),
+        Expression::AssignExpression(assign) => visitor.visit_assign_expression_mut(assign),
+        Expression::MatchExpression(match_expression) => {
+            visitor.visit_match_expression_mut(match_expression)
+        }
+        Expression::TryExpression(try_expression) => {
+            visitor.visit_try_expression_mut(try_expression)
+        }
+    }
+}
+
+pub fn walk_prefix_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    prefix: &mut PrefixExpression,
+) {
+    visitor.visit_expression_mut(&mut prefix.right);
+}
+
+pub fn walk_infix_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    infix: &mut InfixExpression,
+) {
+    visitor.visit_expression_mut(&mut infix.left);
+    visitor.visit_expression_mut(&mut infix.right);
+}
+
+pub fn walk_if_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    if_expression: &mut IfExpression,
+) {
+    visitor.visit_expression_mut(&mut if_expression.condition);
+    visitor.visit_block_statement_mut(&mut if_expression.consequence);
+    if let Some(alternative) = &mut if_expression.alternative {
+        visitor.visit_block_statement_mut(alternative);
+    }
+}
+
+pub fn walk_function_literal_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    function: &mut FunctionLiteral,
+) {
+    for parameter in &mut function.parameters {
+        visitor.visit_identifier_mut(parameter);
+    }
+    visitor.visit_block_statement_mut(&mut function.body);
+}
+
+pub fn walk_call_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    call: &mut CallExpression,
+) {
+    visitor.visit_expression_mut(&mut call.function);
+    for argument in &mut call.arguments {
+        visitor.visit_expression_mut(argument);
+    }
+}
+
+pub fn walk_array_literal_mut<V: VisitorMut + ?Sized>(visitor: &mut V, array: &mut ArrayLiteral) {
+    for element in &mut array.elements {
+        visitor.visit_expression_mut(element);
+    }
+}
+
+pub fn walk_hash_literal_mut<V: VisitorMut + ?Sized>(visitor: &mut V, hash: &mut HashLiteral) {
+    for (key, value) in &mut hash.pairs {
+        visitor.visit_expression_mut(key);
+        visitor.visit_expression_mut(value);
+    }
+}
+
+pub fn walk_index_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    index: &mut IndexExpression,
+) {
+    visitor.visit_expression_mut(&mut index.left);
+    visitor.visit_expression_mut(&mut index.index);
+}
+
+pub fn walk_assign_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    assign: &mut AssignExpression,
+) {
+    visitor.visit_expression_mut(&mut assign.target);
+    visitor.visit_expression_mut(&mut assign.value);
+}
+
+pub fn walk_match_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    match_expression: &mut MatchExpression,
+) {
+    visitor.visit_expression_mut(&mut match_expression.subject);
+    for arm in &mut match_expression.arms {
+        visitor.visit_match_arm_mut(arm);
+    }
+}
+
+pub fn walk_try_expression_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    try_expression: &mut TryExpression,
+) {
+    visitor.visit_block_statement_mut(&mut try_expression.body);
+    if let Some(catch) = &mut try_expression.catch {
+        visitor.visit_identifier_mut(&mut catch.parameter);
+        visitor.visit_block_statement_mut(&mut catch.body);
+    }
+    if let Some(finally) = &mut try_expression.finally {
+        visitor.visit_block_statement_mut(finally);
+    }
+}
+
+pub fn walk_match_arm_mut<V: VisitorMut + ?Sized>(visitor: &mut V, arm: &mut MatchArm) {
+    visitor.visit_pattern_mut(&mut arm.pattern);
+    if let Some(guard) = &mut arm.guard {
+        visitor.visit_expression_mut(guard);
+    }
+    visitor.visit_expression_mut(&mut arm.body);
+}
+
+pub fn walk_pattern_mut<V: VisitorMut + ?Sized>(visitor: &mut V, pattern: &mut Pattern) {
+    crate::with_stack(|| walk_pattern_kind_mut(visitor, pattern))
+}
+
+fn walk_pattern_kind_mut<V: VisitorMut + ?Sized>(visitor: &mut V, pattern: &mut Pattern) {
+    match pattern {
+        Pattern::Literal(literal) => visitor.visit_expression_mut(literal),
+        Pattern::Wildcard(_) => {}
+        Pattern::Binding(identifier) => visitor.visit_identifier_mut(identifier),
+        Pattern::Array(array) => {
+            for element in &mut array.elements {
+                visitor.visit_pattern_mut(element);
+            }
+            if let Some(rest) = &mut array.rest {
+                visitor.visit_pattern_mut(rest);
+            }
+        }
+        Pattern::Hash(hash) => {
+            for (key, pattern) in &mut hash.pairs {
+                visitor.visit_expression_mut(key);
+                visitor.visit_pattern_mut(pattern);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::token::Token;
+    use pretty_assertions::assert_eq;
+
+    fn parse(input: &str) -> Program {
+        let lexer = Lexer::new(input);
+        let mut parser = Parser::new(lexer);
+        match parser.parse_program() {
+            Ok(program) => program,
+            Err(e) => panic!("parse_program() returned an error: {}", e),
+        }
+    }
+
+    #[derive(Default)]
+    struct IdentifierCollector {
+        identifiers: Vec<String>,
+    }
+
+    impl Visitor for IdentifierCollector {
+        fn visit_identifier(&mut self, identifier: &Identifier) {
+            self.identifiers.push(identifier.value.clone());
+        }
+    }
+
+    #[test]
+    fn test_visitor_reaches_every_identifier() {
+        let program = parse(
+            "let f = fn(a, b) { if (a < b) { return c; } else { -d } }; f(e, g + h(i)); \
+             [j, {k: l}][m]",
+        );
+
+        let mut collector = IdentifierCollector::default();
+        collector.visit_program(&program);
+
+        assert_eq!(
+            collector.identifiers,
+            vec!["f", "a", "b", "a", "b", "c", "d", "f", "e", "g", "h", "i", "j", "k", "l", "m"]
+        );
+    }
+
+    // skips function bodies by not walking into them
+    #[derive(Default)]
+    struct TopLevelCalls {
+        calls: usize,
+    }
+
+    impl Visitor for TopLevelCalls {
+        fn visit_function_literal(&mut self, _function: &FunctionLiteral) {}
+        fn visit_call_expression(&mut self, call: &CallExpression) {
+            self.calls += 1;
+            walk_call_expression(self, call);
+        }
+    }
+
+    #[test]
+    fn test_visitor_override_can_stop_descending() {
+        let program = parse("let f = fn() { g(); h() }; f(f());");
+
+        let mut counter = TopLevelCalls::default();
+        counter.visit_program(&program);
+
+        assert_eq!(counter.calls, 2);
+    }
+
+    struct ConstantFolder;
+
+    impl VisitorMut for ConstantFolder {
+        fn visit_expression_mut(&mut self, expression: &mut Expression) {
+            walk_expression_mut(self, expression);
+
+            let span = expression.span();
+            let value = match expression {
+                Expression::InfixExpression(infix) => match (&*infix.left, &*infix.right) {
+                    (Expression::IntegerLiteral(left), Expression::IntegerLiteral(right)) => {
+                        match infix.operator.as_str() {
+                            "+" => &left.value + &right.value,
+                            "-" => &left.value - &right.value,
+                            "*" => &left.value * &right.value,
+                            _ => return,
+                        }
+                    }
+                    _ => return,
+                },
+                _ => return,
+            };
+            *expression = Expression::IntegerLiteral(IntegerLiteral {
+                token: Token::INT(value.clone()),
+                value,
+                span,
+            });
+        }
+    }
+
+    #[test]
+    fn test_visitor_mut_replaces_expressions() {
+        let mut program = parse("let x = 1 + 2 * 3; f(x, 4 - 1, y * 2);");
+
+        ConstantFolder.visit_program_mut(&mut program);
+
+        assert_eq!(program.string(), "let x = 7;\nf(x, 3, (y * 2))");
+    }
+}
@@ -0,0 +1,7 @@
+let total = 0;
+total = total + 1;
+total += 2 * 3;
+let grid = [[0, 0], [0, 0]];
+grid[1][0] -= 1;
+let a = let_b = 1;
+const limit = 10;
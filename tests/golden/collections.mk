@@ -0,0 +1,4 @@
+let people = [{"name": "Alice", "age": 24}, {"name": "Anna", "age": 28}];
+people[0]["name"];
+let getName = fn(person) { person["name"] };
+len("tab\there") + first([1, 2])[0];
@@ -0,0 +1,3 @@
+/* leading /* nested */ comment */
+let x = 1; // trailing
+/* between */ x
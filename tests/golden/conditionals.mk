@@ -0,0 +1,3 @@
+if (x < y) { x }
+if (x > y) { return x; } else { y; z }
+let max = fn(a, b) { if (a > b) { a } else { b } };
@@ -0,0 +1,7 @@
+let result = fib(10);
+fn fib(n) {
+    if (n < 2) { n } else { fib(n - 1) + fib(n - 2) }
+}
+fn is_even(n) { if (n == 0) { true } else { is_odd(n - 1) } };
+fn is_odd(n) { if (n == 0) { false } else { is_even(n - 1) } }
+let square = fn(x) { x * x };
@@ -0,0 +1,5 @@
+let [first, second, ...rest] = [1, 2, 3, 4];
+let [_, [inner]] = [0, [1]];
+const {name, "age": age} = {"name": "ann", "age": 3};
+let {"tags": [tag, ..._]} = person;
+match (xs) { [] => 0, [head, ...tail] => head + sum(tail) };
@@ -0,0 +1,3 @@
+let = 5;
+let x 10;
+let y = 3;
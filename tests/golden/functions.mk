@@ -0,0 +1,6 @@
+// definitions and calls
+let add = fn(a, b) { a + b };
+let noop = fn() {};
+add(1, add(2, 3));
+fn(x) { return x; }(5);
+let apply = fn(f, x) { f(x) };
@@ -0,0 +1,3 @@
+let x = 5;
+let y = x;
+let total = 1 + 2 * 3 - 4 / 2;
@@ -0,0 +1,9 @@
+let i = 0;
+while (i < 10) {
+    let i = i + 1;
+    if (i % 2 == 0) { continue; }
+    if (i > 7) { break }
+}
+for (x in [1, 2, 3]) { puts(x); }
+for (key, value in {"a": 1}) { puts(key, value) }
+for (i in 0..=n - 1) { puts(xs[i..len(xs)]) }
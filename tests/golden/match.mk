@@ -0,0 +1,13 @@
+let describe = fn(x) {
+    match (x) {
+        0 => "zero",
+        -1.5 => "negative one and a half",
+        n if n < 0 => "negative",
+        [] => "empty",
+        [first, _] => first,
+        {name, "age": age} if age > 17 => name,
+        {"tags": [tag]} => tag,
+        _ => "something else",
+    }
+};
+match (describe(x)) { "zero" => true, _ => false };
@@ -0,0 +1,10 @@
+-a * b;
+!-a;
+a + b + c;
+a + b * c + d / e - f;
+5 > 4 == 3 < 4;
+3 + 4 * 5 == 3 * 1 + 4 * 5;
+(5 + 5) * 2;
+-(5 + 5);
+!(true == true);
+a + add(b * c) + d;
@@ -0,0 +1,7 @@
+let safe_div = fn(a, b) {
+    if (b == 0) { throw {"reason": "division by zero", "a": a}; }
+    a / b
+};
+let result = try { safe_div(1, 0) } catch (e) { e["value"]["reason"] } finally { puts("done") };
+try { len(1) } catch (err) { throw err };
+try { cleanup() } finally {};